@@ -9,28 +9,34 @@ extern crate fnv;
 extern crate links;
 extern crate test;
 
-use links::link_state::{LinkState, LinkData, ProcData, HashLinks, new_logger};
+use links::link_state::{LinkState, LinkDb, LinkData, ProcData, HashLinks, new_logger};
+use links::link_state::link_db::synthetic::Wiki;
+use links::link_state::namespace::Namespaces;
 use links::cache;
 
-use std::path::{Path, PathBuf};
+use std::env;
+use std::fs;
+use std::process;
 
-//const BENCH_MANIFEST_PATH: &'static str = "/home/owen/wikidata/dumps/simple_20170201_dump2";
-//const BENCH_RANKS_PATH: &'static str = "/home/owen/wikidata/dumps/simple_20170201_ranks2";
-const BENCH_MANIFEST_PATH: &'static str = "/home/owen/wikidata/dumps3/links.json";
-const BENCH_RANKS_PATH: &'static str = "/home/owen/wikidata/dumps3/metadata.json";
-const IS_SIMPLE: bool = true;
+// a random wiki about the size of a small edition
+const BENCH_PAGES: usize = 200_000;
+const BENCH_SEED: u32 = 2017;
 
 lazy_static! {
     static ref HL: HashLinks = {
-        let m = PathBuf::from(BENCH_MANIFEST_PATH);
-        let r = Path::new(BENCH_RANKS_PATH);
-
-        //let ls_dt = LinkState::<LinkData>::from_file(m, new_logger()).unwrap();
+        // the wiki's dumps are parsed and exported into a temp dir, then imported
+        let dir = env::temp_dir().join(format!("links_benches_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dumps = Wiki::random(BENCH_PAGES, BENCH_SEED).write(&dir).unwrap();
+        let ls_dt: LinkState<LinkData> = LinkState::<LinkDb>::new(&dumps, Namespaces::main())
+            .unwrap().into();
+        let m = dir.join("links.json");
+        ls_dt.export(m.clone()).unwrap();
         let ls_dt = LinkState::<LinkData>::import(m, new_logger()).unwrap();
-        //let ls_rd = LinkState::<ProcData>::from_ranks(ls_dt, r);
+
         let mut ls_rd: LinkState<ProcData> = ls_dt.into();
-        ls_rd.import(r);
-        //let ls_rd = LinkState::<ProcData>::import(ls_dt, r);
+        ls_rd.compute_ranks(&dir.join("ranks.csv")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
         
         let ls_hl: LinkState<HashLinks> = ls_rd.into();
         ls_hl.extract()
@@ -39,6 +45,18 @@ lazy_static! {
 }
 
  
+/// The first page with links, and a page `hops` links away from it
+fn pair(hops: usize) -> (u32, u32) {
+    let mut ids: Vec<u32> = HL.get_links().keys().cloned().collect();
+    ids.sort();
+    let src = ids.into_iter().find(|id| HL.get_links()[id].get_children().is_empty() == false)
+        .unwrap();
+    let dst = HL.get_links().keys().cloned()
+        .find(|&dst| HL.bfs(src, dst).len() == Some(hops))
+        .unwrap();
+    (src, dst)
+}
+
 pub fn add_two(a: i32) -> i32 {
     a + 2
 }
@@ -115,13 +133,7 @@ mod tests {
     fn path_cache_lookup(b: &mut Bencher) {
         // select a medium-length path
         // path length: 4
-        let (src, dst) = if IS_SIMPLE {
-            // "Pope_Anastasius_IV" to "South_West_England": 4 iterations
-            (359684, 54327)
-        } else {
-            // "Dartmouth" to "John_Weber_(darts_player)": 4 iterations
-            (19701041, 44781296)
-        };
+        let (src, dst) = pair(4);
         let db = cache::establish_connection();
 
         b.iter(|| {
@@ -130,13 +142,7 @@ mod tests {
     }
     #[bench]
     fn path_cache_bfs_med(b: &mut Bencher) {
-        let (src, dst) = if IS_SIMPLE {
-            // "Pope_Anastasius_IV" to "South_West_England": 4 iterations
-            (359684, 54327)
-        } else {
-            // "Dartmouth" to "John_Weber_(darts_player)": 4 iterations
-            (19701041, 44781296)
-        };
+        let (src, dst) = pair(4);
         assert!(HL.get_links().get(&src).is_some());
         assert!(HL.get_links().get(&dst).is_some());
         assert_eq!(HL.bfs(src, dst).path.unwrap().len(), 5);
@@ -147,13 +153,7 @@ mod tests {
     }
     #[bench]
     fn path_cache_bfs_short(b: &mut Bencher) {
-        let (src, dst) = if IS_SIMPLE {
-            // "Canada" to "Organization": 2 iterations
-            (219589, 32644)
-        } else {
-            // "Richard_Stallman" to "Jesus": 2 iterations
-            (3434143, 1095706)
-        };
+        let (src, dst) = pair(2);
         assert!(HL.get_links().get(&src).is_some());
         assert!(HL.get_links().get(&dst).is_some());
         assert_eq!(HL.bfs(src, dst).path.unwrap().len(), 3);
@@ -164,13 +164,7 @@ mod tests {
     }
     #[bench]
     fn path_cache_bfs_long(b: &mut Bencher) {
-        let (src, dst) = if IS_SIMPLE {
-            // "Glow_in_the_dark" to "Yolande_Fox": 7 iterations
-            (359127, 533446)
-        } else {
-            // "Joys_Sebastian" to "Philorene_texturata": 
-            (21689617, 36955430)
-        };
+        let (src, dst) = pair(7);
         assert!(HL.get_links().get(&src).is_some());
         assert!(HL.get_links().get(&dst).is_some());
         assert_eq!(HL.bfs(src, dst).path.unwrap().len(), 8);
@@ -17,16 +17,46 @@ extern crate lazy_static;
 extern crate links;
 extern crate test;
 
-use links::link_state::{LinkState, HashLinks, LinkData, new_logger};
-use std::path::PathBuf;
-
-const BENCH_MANIFEST_PATH: &str = "/home/owen/rust/wl/simple/dump1";
+use links::link_state::{LinkState, LinkDb, HashLinks, LinkData, new_logger};
+use links::link_state::link_db::synthetic::Wiki;
+use links::link_state::namespace::Namespaces;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::process;
+
+// a random wiki about the size of a small edition
+const BENCH_PAGES: usize = 200_000;
+const BENCH_SEED: u32 = 2017;
+
+struct Fixture {
+    hl: LinkState<HashLinks>,
+    // searches start from here; how far away each page it can reach is
+    src: u32,
+    distances: BTreeMap<u32, usize>,
+}
 
 lazy_static! {
-    static ref HL: LinkState<HashLinks> = {
-        let m = PathBuf::from(BENCH_MANIFEST_PATH);
+    // the wiki's dumps are parsed and exported into a temp dir, then imported like a real dump
+    static ref FIXTURE: Fixture = {
+        let dir = env::temp_dir().join(format!("links_bfs_bench_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let wiki = Wiki::random(BENCH_PAGES, BENCH_SEED);
+        let dumps = wiki.write(&dir).unwrap();
+        let ls_dt: LinkState<LinkData> = LinkState::<LinkDb>::new(&dumps, Namespaces::main())
+            .unwrap().into();
+        let m = dir.join("links.json");
+        ls_dt.export(m.clone()).unwrap();
         let ls_dt = LinkState::<LinkData>::import(m, new_logger()).unwrap();
-        ls_dt.into()
+        fs::remove_dir_all(&dir).unwrap();
+
+        let expected = wiki.expected(&Namespaces::main());
+        let src = expected.pages.iter()
+            .find(|&(_, p)| p.children.is_empty() == false)
+            .map(|(&id, _)| id).unwrap();
+        let distances = expected.distances(src);
+        Fixture { hl: ls_dt.into(), src, distances }
     };
 }
 
@@ -37,40 +67,36 @@ mod tests {
     use test::Bencher;
     use links::link_state::Path as BfsPath;
 
-    /*  Note: tests w/ time measurements weren't all done on the same system
-     *      Simple wiki tests ("*") were done on an i7 4600U
-     *      English wiki tests ("**") were done on an i5 2300
-     */
-
     /// Generic benching for breadth-first searching (either bfs or bfs2)
     fn bfs_bench_g<F>(b: &mut Bencher, bfs_fn: F, src: u32, dst: u32, len: usize)
         where F: Fn(u32, u32) -> BfsPath
     {
         // be sure to init the data structure before beginning the benchmark
-        lazy_static::initialize(&HL);
+        lazy_static::initialize(&FIXTURE);
         b.iter(|| {
             let p = bfs_fn(src, dst);
             assert_eq!(Some(len), p.len());
         });
     }
     // stubs to make testing a little clearer
-    fn bfs1(src: u32, dst: u32) -> BfsPath { HL.bfs(src, dst) }
-    fn bfs2(src: u32, dst: u32) -> BfsPath { HL.bfs2(src, dst) }
+    fn bfs1(src: u32, dst: u32) -> BfsPath { FIXTURE.hl.bfs(src, dst) }
+    fn bfs2(src: u32, dst: u32) -> BfsPath { FIXTURE.hl.bfs2(src, dst) }
+
+    /// Bench a search to the first page `len` links away (or the farthest, for `None`)
+    fn bfs_len_g<F>(b: &mut Bencher, bfs_fn: F, len: Option<usize>)
+        where F: Fn(u32, u32) -> BfsPath
+    {
+        let distances = &FIXTURE.distances;
+        let len = len.unwrap_or_else(|| *distances.values().max().unwrap());
+        let dst = distances.iter().find(|&(_, &d)| d == len).map(|(&id, _)| id).unwrap();
+        bfs_bench_g(b, bfs_fn, FIXTURE.src, dst, len)
+    }
 
 
 
     /// Bench small searches
     fn bfs_small_g<F: Fn(u32,u32)->BfsPath>(b: &mut Bencher, bfs_fn: F) {
-        if cfg!(feature="simple") {
-            // takes ~1 μs *
-            // Elkton,_Kentucky → United_States → New_York_City → 
-            //  St._Patrick\'s_Cathedral_(New_York)
-            bfs_bench_g(b, bfs_fn, 155171, 228159, 3)
-        } else {
-            // takes ~1 μs **
-            // Kabuki_Rocks → List_of_video_game_musicians → Crypt_of_the_NecroDancer
-            bfs_bench_g(b, bfs_fn, 31073639, 41637705, 2)
-        }
+        bfs_len_g(b, bfs_fn, Some(2))
     }
     #[bench] fn bfs_small_cur(b: &mut Bencher) { bfs_small_g(b, bfs1) }
     #[bench] fn bfs_small_alt(b: &mut Bencher) { bfs_small_g(b, bfs2) }
@@ -79,16 +105,7 @@ mod tests {
 
     /// Bench medium searches
     fn bfs_medium_g<F: Fn(u32,u32)->BfsPath>(b: &mut Bencher, bfs_fn: F) {
-        if cfg!(feature="simple") {
-            // takes ~300 μs *
-            // Jim Jones → November 18 → April 28 → Jessica Alba → The Office
-            bfs_bench_g(b, bfs_fn, 15030, 129573, 4)
-        } else {
-            // takes ~1 ms **
-            // Palazzo_del_Capitano_del_Popolo,_Gubbio → Gothic_architecture →
-            //  Oakland,_California → Oakland_firestorm_of_1991 → Detwiler_Fire
-            bfs_bench_g(b, bfs_fn, 38394676, 54576532, 4)
-        }
+        bfs_len_g(b, bfs_fn, Some(4))
     }
     #[bench] fn bfs_medium_cur(b: &mut Bencher) { bfs_medium_g(b, bfs1) }
     #[bench] fn bfs_medium_alt(b: &mut Bencher) { bfs_medium_g(b, bfs2) }
@@ -97,20 +114,7 @@ mod tests {
 
     /// Bench large searches
     fn bfs_large_g<F: Fn(u32,u32)->BfsPath>(b: &mut Bencher, bfs_fn: F) {
-        if cfg!(feature="simple") {
-            // takes ~150 ms *
-            // Macclenny,_Florida → United_States → November_11 → 2004 → 
-            //  Vasaloppet → Tjejvasan → Annika_Evaldsson → 
-            //  Swedish_Cross-Country_Skiing_Championships → Saltsjöbaden → 
-            //  Grand_Hotel_Saltsjöbaden → Saltsjöbaden_Agreement
-            bfs_bench_g(b, bfs_fn, 152629, 454989, 10)
-        } else {
-            // takes ~500 ms ** (any larger to `cargo bench` takes forever
-            // Jack_Tatum → Ohio_State_Buckeyes_football → Indiana_Hoosiers_football
-            //  → David_Starr_Jordan → Caulophryne_jordani → Caulophryne
-            //  → Caulophryne_bacescui
-            bfs_bench_g(b, bfs_fn, 1684129, 52186157, 6)
-        }
+        bfs_len_g(b, bfs_fn, None)
     }
     #[bench] fn bfs_large_cur(b: &mut Bencher) { bfs_large_g(b, bfs1) }
     #[bench] fn bfs_large_alt(b: &mut Bencher) { bfs_large_g(b, bfs2) }
@@ -0,0 +1,107 @@
+#![feature(test)]
+
+// Compare the tokenizer against the regex it replaced, on a synthetic page.sql statement
+// The statement is built to look like a real one: ~1MB with the occasional escaped title
+// The regex is kept here (rather than in `parse`) only as a fixed baseline: it matches a
+//  whole `(...)` tuple at a time, capturing the id and title of main namespace pages.
+
+extern crate links;
+extern crate regex;
+extern crate test;
+
+use links::link_state::link_db::parse::schema::{Schema, SchemaReader};
+use links::link_state::link_db::parse::Position;
+use links::link_state::link_db::parse::tokenizer::Tokenizer;
+
+const PAGE_HEADER: &str = "CREATE TABLE `page` (
+  `page_id` int(8) unsigned NOT NULL AUTO_INCREMENT,
+  `page_namespace` int(11) NOT NULL DEFAULT '0',
+  `page_title` varbinary(255) NOT NULL DEFAULT '',
+  `page_restrictions` tinyblob NOT NULL,
+  `page_is_redirect` tinyint(1) unsigned NOT NULL DEFAULT '0',
+  `page_is_new` tinyint(1) unsigned NOT NULL DEFAULT '0',
+  `page_random` double unsigned NOT NULL DEFAULT '0',
+  `page_touched` varbinary(14) NOT NULL DEFAULT '',
+  `page_links_updated` varbinary(14) DEFAULT NULL,
+  `page_latest` int(8) unsigned NOT NULL DEFAULT '0',
+  `page_len` int(8) unsigned NOT NULL DEFAULT '0',
+  `page_content_model` varbinary(32) DEFAULT NULL,
+  `page_lang` varbinary(35) DEFAULT NULL,
+  PRIMARY KEY (`page_id`)
+) ENGINE=InnoDB DEFAULT CHARSET=binary;";
+
+// tuples per statement; about the size of a real dump's lines
+const TUPLES: u32 = 10_000;
+
+fn schema() -> Schema {
+    let mut reader = SchemaReader::default();
+    PAGE_HEADER.lines().filter_map(|l| reader.feed(l)).next().unwrap()
+}
+
+// any sql value: a quoted string (which can contain escaped quotes), NULL, or a number
+const ANY: &str = r"(?:'[^'\\]*(?:\\.[^'\\]*)*'|NULL|-?[\d\.]+(?:[eE][+-]?\d+)?)";
+
+// a pattern for each column of page.sql (and `ANY` for the ones that aren't needed)
+fn pages_regex(schema: &Schema) -> String {
+    let columns: Vec<String> = schema.columns().iter().map(|c| match c.as_str() {
+        "page_id"           => String::from(r"(?P<id>\d+)"),
+        "page_namespace"    => String::from("0"),
+        "page_title"        => String::from(r"'(?P<title>[^'\\]*(?:\\.[^'\\]*)*)'"),
+        _ => String::from(ANY),
+    }).collect();
+    format!(r"\({}\)", columns.join(","))
+}
+
+fn statement() -> Vec<u8> {
+    let tuples: Vec<String> = (0..TUPLES).map(|i| {
+        let title = if i % 10 == 0 {
+            format!("Ender\\'s_Game_{}", i)
+        } else {
+            format!("Some_Article_Title_{}", i)
+        };
+        format!("({},{},'{}','',{},0,0.{},'20170101000000',NULL,{},{},'wikitext',NULL)",
+                i, i % 3, title, i % 2, i, i * 7, i * 13)
+    }).collect();
+    format!("INSERT INTO `page` VALUES {};\n", tuples.join(",")).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test::Bencher;
+
+    // 10k tuples (~1MB):  regex ~46 ms,  tokenizer ~3.7 ms
+
+    #[bench]
+    fn parse_pages_regex(b: &mut Bencher) {
+        let re = regex::Regex::new(&pages_regex(&schema())).unwrap();
+        let line = statement();
+        b.iter(|| {
+            let s = String::from_utf8_lossy(&line);
+            let count = re.captures_iter(&s).filter(|c| {
+                let _id: u32 = c.name("id").unwrap().parse().unwrap();
+                c.name("title").is_some()
+            }).count();
+            assert_eq!(count as u32, (TUPLES+2) / 3);
+        });
+    }
+
+    #[bench]
+    fn parse_pages_tokenizer(b: &mut Bencher) {
+        let cols = schema().require(&["page_id", "page_namespace", "page_title"]).unwrap();
+        let line = statement();
+        b.iter(|| {
+            let mut tokens = Tokenizer::new(&line[..], Position::default());
+            let mut count = 0;
+            while let Some(tuple) = tokens.next_tuple().unwrap() {
+                if tuple.get(cols[1]).and_then(|f| f.as_int()) == Some(0) {
+                    let _id = tuple.get(cols[0]).and_then(|f| f.as_u32()).unwrap();
+                    if tuple.get(cols[2]).and_then(|f| f.as_str()).is_some() {
+                        count += 1;
+                    }
+                }
+            }
+            assert_eq!(count, (TUPLES+2) / 3);
+        });
+    }
+}
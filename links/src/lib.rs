@@ -1,14 +1,12 @@
 //#![feature(getpid)]
 #![allow(unknown_lints, bool_comparison)]
 
-// NOTE: when running on the simple wiki, parsing is slightly different
-// run with `cargo build --features=simple`
-
 // LOGGING
 #[macro_use] extern crate slog;
 extern crate slog_term;
 // SERIALIZING
 #[macro_use] extern crate serde_derive;
+extern crate serde;
 extern crate serde_json;
 extern crate csv;
 // MISC
@@ -16,6 +14,11 @@ extern crate clap;
 extern crate fnv;
 extern crate fst;
 extern crate chrono;
+extern crate rand;
+// DECOMPRESSION
+extern crate flate2;
+extern crate bzip2;
+extern crate unicode_normalization;
 
 // COMPONENTS
 pub mod link_state;
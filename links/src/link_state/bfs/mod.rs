@@ -5,6 +5,7 @@ use slog::Logger;
 use std::mem;
 
 use link_state::entry::Entry;
+use link_state::namespace::Namespaces;
 
 const MAX_DEPTH: u32 = 10;
 
@@ -64,6 +65,12 @@ pub struct BFS<'a> {
     // indices of src and dst
     src: u32,
     dst: u32,
+    // if present, the path can only go through pages in these namespaces
+    // (`src` and `dst` themselves can be in any namespace)
+    namespaces: Option<&'a Namespaces>,
+    // whether the path can go through disambiguation pages (which tend to be shortcuts
+    //  between unrelated pages, e.g. `Mercury` between a planet and an element)
+    disambiguation: bool,
 
     // comprehensive list of page_ids reachable from each node
     // for (k,v), there is a path from src → ⋯ → v → k (through children links)
@@ -85,6 +92,8 @@ impl<'a> BFS<'a> {
         BFS {
             links, log,
             src, dst,
+            namespaces: None,
+            disambiguation: true,
             src_seen: FnvHashMap::default(),
             dst_seen: FnvHashMap::default(),
             row_down: FnvHashSet::default(),
@@ -92,6 +101,18 @@ impl<'a> BFS<'a> {
         }
     }
 
+    /// Only search through pages in `namespaces`
+    pub fn with_namespaces(mut self, namespaces: &'a Namespaces) -> BFS<'a> {
+        self.namespaces = Some(namespaces);
+        self
+    }
+
+    /// Don't search through disambiguation pages
+    pub fn without_disambiguation(mut self) -> BFS<'a> {
+        self.disambiguation = false;
+        self
+    }
+
     fn path_from(&self, p: Result<Vec<u32>, PathError>) -> Path {
         Path {
             src: self.src,
@@ -128,7 +149,10 @@ impl<'a> BFS<'a> {
         if self.src == self.dst {
             return Path { src: self.src, dst: self.dst, path: Ok(vec![self.src]) }
         }
-        // do we need to add src/dst to src_seen/dst_seen ? seems inconsistent
+        // src and dst count as seen from their own side, or a direct link wouldn't be
+        //  found until the rows met one step later
+        self.src_seen.insert(self.src, self.src);
+        self.dst_seen.insert(self.dst, self.dst);
         self.row_down.insert(self.src);
         self.row_up.insert(self.dst);
 
@@ -167,29 +191,46 @@ impl<'a> BFS<'a> {
     }
 
     fn iter_down(&mut self, tmp: &mut Set) -> Option<u32> {
-        Self::iter(self.links, &self.row_down, tmp, 
+        let (links, keep) = (self.links, self.filter());
+        Self::iter(&self.row_down, tmp, 
                    &mut self.src_seen, &self.dst_seen, 
-                   Entry::get_children)
+                   |i| links[&i].get_children(), keep)
     }
 
     fn iter_up(&mut self, tmp: &mut Set) -> Option<u32> {
-        Self::iter(self.links, &self.row_up, tmp,
+        let (links, keep) = (self.links, self.filter());
+        Self::iter(&self.row_up, tmp,
                    &mut self.dst_seen, &self.src_seen,
-                   Entry::get_parents)
+                   |i| links[&i].get_parents(), keep)
+    }
+
+    fn filter(&self) -> impl Fn(u32) -> bool + 'a {
+        // whether the path can go through an id
+        let (links, namespaces, disambiguation) = 
+            (self.links, self.namespaces, self.disambiguation);
+        move |i| {
+            let entry = &links[&i];
+            namespaces.is_none_or(|ns| ns.contains(entry.namespace()))
+                && (disambiguation || entry.disambiguation == false)
+        }
     }
 
-    fn iter<F>(links: &'a Links, old_line: &Set, new_line: &mut Set,
-               seen: &mut Map, targets: &Map, next: F)
+    fn iter<F, G>(old_line: &Set, new_line: &mut Set,
+                  seen: &mut Map, targets: &Map, next: F, keep: G)
         -> Option<u32> 
-        where F: Fn(&'a Entry) -> &'a [u32]
+        where F: Fn(u32) -> &'a [u32], G: Fn(u32) -> bool
     {
         // for each element in `old_line`, add its parents/children to `next_line`
         // as we see an entry, add it to `seen`
         // if an element is both `seen` and a `target`, a path has been found
+        // elements that can't be `keep`t are skipped (unless they're a `target`)
         for &old in old_line {
-            for &new in next(&links[&old]) {
+            for &new in next(old) {
                 // only consider ids that haven't been `seen`
                 if seen.contains_key(&new) == false {
+                    if keep(new) == false && targets.contains_key(&new) == false {
+                        continue;
+                    }
                     seen.insert(new, old);
                     // TODO: check a bloom filter or something here
                     if targets.contains_key(&new) {
@@ -267,6 +308,8 @@ impl<'a> BFS2<'a> {
         if self.src == self.dst {
             return Path { src: self.src, dst: self.dst, path: Ok(vec![self.src]) }
         }
+        self.src_seen.insert(self.src, self.src);
+        self.dst_seen.insert(self.dst, self.dst);
         self.row_down.insert(self.src);
         self.row_up.insert(self.dst);
         let mut tmp: IHSet = IHSet::default();
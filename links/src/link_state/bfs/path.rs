@@ -1,5 +1,6 @@
 use fnv;
 use super::Entry;
+use link_state::title;
 
 #[derive(Debug, Clone)]
 pub struct Path {
@@ -30,11 +31,12 @@ impl Path {
         }
     }
     pub fn print(&self, entries: &fnv::FnvHashMap<u32,Entry>) {
-        println!("Path from {}\t(\"{}\")", self.src, entries.get(&self.src).unwrap().title);
-        println!("\t  to {}\t(\"{}\") :", self.dst, entries.get(&self.dst).unwrap().title);
+        let title = |i: &u32| title::display(&entries[i].title);
+        println!("Path from {}\t(\"{}\")", self.src, title(&self.src));
+        println!("\t  to {}\t(\"{}\") :", self.dst, title(&self.dst));
         match self.path {
             Ok(ref v) => for i in v {
-                println!("\t{}:\t\"{}\"", i, entries[i].title);
+                println!("\t{}:\t\"{}\"", i, title(i));
             },
             Err(PathError::NoSuchPath) => println!("\tNo such path exists"),
             Err(PathError::Terminated(i)) => 
@@ -0,0 +1,72 @@
+// Searching two language editions side by side
+// Each edition is its own snapshot; a page is matched with its equivalent in the other
+//  edition through langlinks. Either edition's langlinks will do: a page in `a` is
+//  looked up in `b` by its title in `b`'s language, or else found among `b`'s
+//  langlinks back to `a`'s language.
+
+use fnv::FnvHashMap;
+
+use super::{LinkState, HashLinks, Path};
+
+/// One language edition's snapshot
+pub struct Edition {
+    // e.g. `en` or `simple`, as it appears in the other edition's langlinks
+    pub lang: String,
+    pub links: LinkState<HashLinks>,
+}
+
+/// The same search in both editions
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub a: Path,
+    // between the equivalents of `a`'s src and dst
+    pub b: Path,
+}
+
+impl Comparison {
+    /// Length of the path in `a` and in `b`
+    pub fn lengths(&self) -> (Option<usize>, Option<usize>) {
+        (self.a.len(), self.b.len())
+    }
+}
+
+pub struct Bilingual {
+    a: Edition,
+    b: Edition,
+    // Page in `a`  →  Equivalent in `b` (from `b`'s langlinks)
+    from_b: FnvHashMap<u32, u32>,
+}
+
+impl Bilingual {
+    pub fn new(a: Edition, b: Edition) -> Bilingual {
+        let from_b = match b.links.langlinks() {
+            Some(langlinks) => langlinks.to(&a.lang).filter_map(|(id_b, title)| {
+                a.links.resolve_title(title).map(|id_a| (id_a, id_b))
+            }).collect(),
+            None => FnvHashMap::default(),
+        };
+        Bilingual { a, b, from_b }
+    }
+    pub fn a(&self) -> &Edition {
+        &self.a
+    }
+    pub fn b(&self) -> &Edition {
+        &self.b
+    }
+    /// The page in `b` that's the equivalent of `id` in `a`
+    pub fn translate(&self, id: u32) -> Option<u32> {
+        self.a.links.langlinks()
+            .and_then(|langlinks| langlinks.title_in(id, &self.b.lang))
+            .and_then(|title| self.b.links.resolve_title(title))
+            .or_else(|| self.from_b.get(&id).cloned())
+    }
+    /// Search from `src` to `dst` in `a`, and between their equivalents in `b`
+    /// `None` if either of them has no equivalent in `b`
+    pub fn compare(&self, src: u32, dst: u32) -> Option<Comparison> {
+        let (src_b, dst_b) = (self.translate(src)?, self.translate(dst)?);
+        Some(Comparison {
+            a: self.a.links.bfs(src, dst),
+            b: self.b.links.bfs(src_b, dst_b),
+        })
+    }
+}
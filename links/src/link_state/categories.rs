@@ -0,0 +1,85 @@
+// Category membership (from categorylinks.sql)
+// Categories aren't part of the link graph (unless their namespace is included, see
+//  `namespace`); they're kept on the side as a category → member pages table, along
+//  with the reverse (page → the categories it's in).
+// Categories are named by their title without the namespace prefix (`Physics`, not
+//  `Category:Physics`), as they are in categorylinks.sql.
+
+use fnv::FnvHashMap;
+
+use std::collections::HashMap;
+
+/// A category and the ids of the pages in it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Category {
+    pub title: String,
+    // sorted
+    pub members: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Categories {
+    // sorted by title
+    categories: Vec<Category>,
+    // page → indices into `categories`
+    pages: FnvHashMap<u32, Vec<u32>>,
+}
+
+impl Categories {
+    pub fn new(mut categories: Vec<Category>) -> Categories {
+        categories.sort_by(|a,b| a.title.cmp(&b.title));
+        let mut pages: FnvHashMap<u32, Vec<u32>> = FnvHashMap::default();
+        for (i, category) in categories.iter_mut().enumerate() {
+            category.members.sort();
+            category.members.dedup();
+            for &page in &category.members {
+                pages.entry(page).or_default().push(i as u32);
+            }
+        }
+        Categories { categories, pages }
+    }
+    pub fn from_members(members: HashMap<String, Vec<u32>>) -> Categories {
+        Categories::new(members.into_iter()
+                        .map(|(title, members)| Category { title, members })
+                        .collect())
+    }
+    /// Number of categories
+    pub fn len(&self) -> usize {
+        self.categories.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.categories.is_empty()
+    }
+    /// Every category (sorted by title)
+    pub fn iter(&self) -> ::std::slice::Iter<'_, Category> {
+        self.categories.iter()
+    }
+    /// Pages in `category`
+    pub fn members(&self, category: &str) -> &[u32] {
+        match self.categories.binary_search_by(|c| c.title.as_str().cmp(category)) {
+            Ok(i) => &self.categories[i].members,
+            Err(_) => &[],
+        }
+    }
+    /// Categories `page` is in
+    pub fn of(&self, page: u32) -> Vec<&str> {
+        self.pages.get(&page).map_or(vec![], |cs| {
+            cs.iter().map(|&i| self.categories[i as usize].title.as_str()).collect()
+        })
+    }
+    /// Categories the pages of a path are in, with how many of its pages are in each
+    /// (most common first)
+    pub fn along(&self, path: &[u32]) -> Vec<(&str, usize)> {
+        let mut counts: FnvHashMap<u32, usize> = FnvHashMap::default();
+        for page in path {
+            for &i in self.pages.get(page).map_or(&[][..], |cs| cs) {
+                *counts.entry(i).or_insert(0) += 1;
+            }
+        }
+        let mut along: Vec<(&str, usize)> = counts.into_iter()
+            .map(|(i, n)| (self.categories[i as usize].title.as_str(), n))
+            .collect();
+        along.sort_by(|a,b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        along
+    }
+}
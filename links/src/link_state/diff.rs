@@ -0,0 +1,157 @@
+// What changed between two snapshots (e.g. from consecutive months' dumps)
+// Pages are matched by id, so a page that was moved keeps its links:
+//
+//      added / deleted:    pages only in the newer / older snapshot
+//      renamed:            the same page with a different title
+//      links:              for each page whose links changed, the links it gained and
+//                          lost (all of them for an added or deleted page)
+//
+// Diffing two parses of the same dumps should find nothing, so it's also a way to catch
+//  a change to the parser that changes its output. It's exported either as one JSON
+//  object or as a CSV for each part, with titles shown as on the wiki.
+
+use csv;
+use serde_json;
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+
+use super::{LinkState, LinkData};
+use super::link_data::{IndexedEntry, append_to_pathbuf};
+use super::namespace;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffPage {
+    pub id: u32,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenamedPage {
+    pub id: u32,
+    pub from: String,
+    pub to: String,
+}
+
+/// The links a page gained and lost
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LinkChanges {
+    pub id: u32,
+    // (in the newer snapshot if it's there)
+    pub title: String,
+    // sorted
+    pub added: Vec<u32>,
+    pub removed: Vec<u32>,
+}
+
+impl LinkChanges {
+    /// Number of links added or removed
+    pub fn size(&self) -> usize {
+        self.added.len() + self.removed.len()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SnapshotDiff {
+    // each sorted by id
+    pub added: Vec<DiffPage>,
+    pub deleted: Vec<DiffPage>,
+    pub renamed: Vec<RenamedPage>,
+    pub links: Vec<LinkChanges>,
+}
+
+fn display(ie: &IndexedEntry) -> String {
+    namespace::display(&ie.title).into_owned()
+}
+
+impl SnapshotDiff {
+    /// What changed from `old` to `new`
+    pub fn new(old: &LinkState<LinkData>, new: &LinkState<LinkData>) -> SnapshotDiff {
+        let (old, new) = (old.entries_by_id(), new.entries_by_id());
+        let mut ids: Vec<u32> = old.keys().chain(new.keys()).cloned().collect();
+        ids.sort();
+        ids.dedup();
+
+        let mut diff = SnapshotDiff::default();
+        let none: &[u32] = &[];
+        for id in ids {
+            let (before, after) = (old.get(&id), new.get(&id));
+            match (before, after) {
+                (None, Some(a)) => diff.added.push(DiffPage { id, title: display(a) }),
+                (Some(b), None) => diff.deleted.push(DiffPage { id, title: display(b) }),
+                (Some(b), Some(a)) if b.title != a.title =>
+                    diff.renamed.push(RenamedPage { id, from: display(b), to: display(a) }),
+                _ => {},
+            }
+            let children = |ie: Option<&&IndexedEntry>| -> HashSet<u32> {
+                ie.map_or(none, |ie| ie.children()).iter().cloned().collect()
+            };
+            let (b, a) = (children(before), children(after));
+            let mut added: Vec<u32> = a.difference(&b).cloned().collect();
+            let mut removed: Vec<u32> = b.difference(&a).cloned().collect();
+            if added.is_empty() && removed.is_empty() {
+                continue;
+            }
+            added.sort();
+            removed.sort();
+            let title = after.or(before).map(|ie| display(ie)).unwrap_or_default();
+            diff.links.push(LinkChanges { id, title, added, removed });
+        }
+        diff
+    }
+    /// Whether the snapshots have the same pages, titles, and links
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.deleted.is_empty() && self.renamed.is_empty()
+            && self.links.is_empty()
+    }
+    pub fn links_added(&self) -> usize {
+        self.links.iter().map(|l| l.added.len()).sum()
+    }
+    pub fn links_removed(&self) -> usize {
+        self.links.iter().map(|l| l.removed.len()).sum()
+    }
+    /// The `n` pages whose links changed the most (most links added or removed first)
+    pub fn most_changed(&self, n: usize) -> Vec<&LinkChanges> {
+        let mut ranked: Vec<&LinkChanges> = self.links.iter().collect();
+        ranked.sort_by(|a,b| b.size().cmp(&a.size()).then(a.id.cmp(&b.id)));
+        ranked.truncate(n);
+        ranked
+    }
+    /// Write the whole diff to `dst` as JSON
+    pub fn export_json(&self, dst: &PathBuf) -> io::Result<()> {
+        let mut w = io::BufWriter::new(File::create(dst)?);
+        serde_json::to_writer_pretty(&mut w, self).map_err(io::Error::other)
+    }
+    /// Write each part next to `dst`, e.g. `diff_pages_added.csv` for `diff.csv`
+    pub fn export_csv(&self, dst: &PathBuf) -> Result<(), csv::Error> {
+        // (id, title)
+        for &(name, pages) in &[("_pages_added", &self.added), ("_pages_deleted", &self.deleted)] {
+            let mut w = csv::Writer::from_file(append_to_pathbuf(dst, name, "csv"))?;
+            for p in pages {
+                w.encode((p.id, &p.title))?;
+            }
+        }
+        // (id, old title, new title)
+        let mut w = csv::Writer::from_file(append_to_pathbuf(dst, "_pages_renamed", "csv"))?;
+        for r in &self.renamed {
+            w.encode((r.id, &r.from, &r.to))?;
+        }
+        // (id, title, links added, links removed, added, removed)
+        let mut w = csv::Writer::from_file(append_to_pathbuf(dst, "_link_changes", "csv"))?;
+        let ids = |ids: &[u32]| ids.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(" ");
+        for l in &self.links {
+            w.encode((l.id, &l.title, l.added.len(), l.removed.len(),
+                      ids(&l.added), ids(&l.removed)))?;
+        }
+        Ok(())
+    }
+}
+
+impl LinkState<LinkData> {
+    /// What changed from this snapshot to `newer`
+    pub fn diff(&self, newer: &LinkState<LinkData>) -> SnapshotDiff {
+        SnapshotDiff::new(self, newer)
+    }
+}
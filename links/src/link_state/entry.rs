@@ -11,9 +11,13 @@ pub struct Entry {
     neighbors: Vec<u32>,
     last_parent: u32,
     first_child: u32,
+    // whether it's a disambiguation page (see `bfs::BFS::without_disambiguation`)
+    #[serde(default)]
+    pub disambiguation: bool,
 }
 
 use link_state::link_data::IndexedEntry;
+use link_state::namespace;
 impl From<IndexedEntry> for Entry {
     fn from(i: IndexedEntry) -> Entry {
         Entry {
@@ -21,6 +25,7 @@ impl From<IndexedEntry> for Entry {
             neighbors: i.neighbors,
             last_parent: i.last_parent,
             first_child: i.first_child,
+            disambiguation: i.disambiguation,
         }
     }
 }
@@ -36,6 +41,10 @@ impl Entry {
         let i = self.last_parent as usize;
         &self.neighbors[..i]
     }
+    /// The namespace the entry's page is in (its `title` is a `namespace::key`)
+    pub fn namespace(&self) -> i32 {
+        namespace::split(&self.title).0
+    }
 }
 
 
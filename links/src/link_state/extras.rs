@@ -0,0 +1,105 @@
+// Data about pages that isn't part of the link graph
+// Each table is optional (it's only there if its dump was parsed) and is carried along
+//  from `LinkDb` to `ProcData`/`HashLinks`. Tables are exported next to the manifest,
+//  one file each with one record per line (like the entries), and imported again if
+//  their files are there.
+
+use serde::{Serialize, Deserialize};
+use serde_json;
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use super::categories::{Categories, Category};
+use super::langlinks::{LangLinks, PageLangLinks};
+use super::sections::{Sections, Section};
+use super::wanted::{Wanted, WantedPage};
+use super::page_metadata::{Metadata, PageMetadata};
+use super::link_order::{LinkOrder, PageLinkOrder};
+use super::link_data::append_to_pathbuf;
+
+#[derive(Debug, Clone, Default)]
+pub struct Extras {
+    // from categorylinks.sql
+    pub categories: Option<Categories>,
+    // from langlinks.sql
+    pub langlinks: Option<LangLinks>,
+    // from redirect.sql (missing from snapshots exported before it was kept)
+    pub sections: Option<Sections>,
+    // from pagelinks.sql, if links to missing pages were kept
+    pub wanted: Option<Wanted>,
+    // from page.sql, if its metadata columns were kept
+    pub metadata: Option<Metadata>,
+    // from the XML export: each page's links in the order they're written
+    pub link_order: Option<LinkOrder>,
+}
+
+impl Extras {
+    /// Write each table that's present next to the manifest `dst`
+    pub fn export(&self, dst: &PathBuf) -> io::Result<()> {
+        if let Some(ref categories) = self.categories {
+            write_lines(&append_to_pathbuf(dst, "_categories", "json"), categories.iter())?;
+        }
+        if let Some(ref langlinks) = self.langlinks {
+            let pages = langlinks.pages();
+            write_lines(&append_to_pathbuf(dst, "_langlinks", "json"), pages.iter())?;
+        }
+        if let Some(ref sections) = self.sections {
+            write_lines(&append_to_pathbuf(dst, "_sections", "json"), sections.iter())?;
+        }
+        if let Some(ref wanted) = self.wanted {
+            write_lines(&append_to_pathbuf(dst, "_wanted", "json"), wanted.iter())?;
+        }
+        if let Some(ref metadata) = self.metadata {
+            write_lines(&append_to_pathbuf(dst, "_page_metadata", "json"), metadata.iter())?;
+        }
+        if let Some(ref link_order) = self.link_order {
+            write_lines(&append_to_pathbuf(dst, "_link_order", "json"), link_order.iter())?;
+        }
+        Ok(())
+    }
+    /// Read whichever tables were exported next to the manifest `src`
+    pub fn import(src: &PathBuf) -> io::Result<Extras> {
+        Ok(Extras {
+            categories: read_lines::<Category>(&append_to_pathbuf(src, "_categories", "json"))?
+                .map(Categories::new),
+            langlinks: read_lines::<PageLangLinks>(&append_to_pathbuf(src, "_langlinks", "json"))?
+                .map(LangLinks::new),
+            sections: read_lines::<Section>(&append_to_pathbuf(src, "_sections", "json"))?
+                .map(Sections::new),
+            wanted: read_lines::<WantedPage>(&append_to_pathbuf(src, "_wanted", "json"))?
+                .map(Wanted::new),
+            metadata: 
+                read_lines::<PageMetadata>(&append_to_pathbuf(src, "_page_metadata", "json"))?
+                .map(Metadata::new),
+            link_order:
+                read_lines::<PageLinkOrder>(&append_to_pathbuf(src, "_link_order", "json"))?
+                .map(LinkOrder::new),
+        })
+    }
+}
+
+fn write_lines<'a, T: 'a + Serialize, I: Iterator<Item=&'a T>>(path: &PathBuf, records: I)
+    -> io::Result<()>
+{
+    println!("Writing to `{:?}`", path);
+    let mut w = io::BufWriter::new(File::create(path)?);
+    for record in records {
+        let mut serial = serde_json::to_string(record).map_err(io::Error::other)?;
+        serial.push('\n');
+        w.write_all(&serial.into_bytes())?;
+    }
+    Ok(())
+}
+
+fn read_lines<T: Deserialize>(path: &PathBuf) -> io::Result<Option<Vec<T>>> {
+    // `None` if the table wasn't exported
+    if path.is_file() == false {
+        return Ok(None);
+    }
+    let r = BufReader::new(File::open(path)?);
+    r.lines().map(|l| l.and_then(|s| {
+        serde_json::from_str(&s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    })).collect::<io::Result<Vec<T>>>().map(Some)
+}
@@ -0,0 +1,184 @@
+// Getting to Philosophy (https://en.wikipedia.org/wiki/Wikipedia:Getting_to_Philosophy)
+// Following the first link in the body of an article (see `LinkOrder::first_link`), then
+//  the first link of that one, and so on, ends either at a page without one (a dead end)
+//  or going around a cycle, famously `Philosophy` → … → `Philosophy` for most articles.
+//  The pages that lead into a cycle (without being part of it) are its basin:
+//
+//      cycles:         the pages of each cycle in the order they're followed, starting
+//                      with the lowest id, with how many pages lead into it
+//      reach:          where each page ends up, and in how many steps
+//
+// Only the XML export has the order of links (see `link_db::xml`), so this needs data
+//  parsed from it. Each part can be exported to a CSV, with titles shown as on the wiki.
+
+use csv;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::link_data::append_to_pathbuf;
+use super::link_order::LinkOrder;
+
+/// A cycle of first links and the pages that lead into it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cycle {
+    pub members: Vec<u32>,
+    // pages that end up in it (not counting its members)
+    pub basin: usize,
+    // the most first links any page of the basin follows to reach it
+    pub depth: usize,
+}
+
+/// Where following first links from a page ends up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Destination {
+    // an index into `FirstLinks.cycles`
+    Cycle(usize),
+    // a page without a first link
+    DeadEnd(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reach {
+    pub destination: Destination,
+    // first links followed to get there (0 for members of a cycle and dead ends)
+    pub steps: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FirstLinks {
+    // Page  →  Its first link
+    next: HashMap<u32, u32>,
+    // sorted by basin size (largest first)
+    cycles: Vec<Cycle>,
+    reach: HashMap<u32, Reach>,
+}
+
+impl FirstLinks {
+    /// Follow the first links of `order` from each of `pages`
+    pub fn new<I: IntoIterator<Item=u32>>(order: &LinkOrder, pages: I) -> FirstLinks {
+        let next: HashMap<u32, u32> = order.iter()
+            .filter_map(|p| p.first.map(|f| (p.id, f)))
+            .collect();
+        let mut pages: Vec<u32> = pages.into_iter().collect();
+        pages.sort();
+
+        let mut cycles: Vec<Vec<u32>> = vec![];
+        let mut reach: HashMap<u32, Reach> = HashMap::new();
+        let mut path: Vec<u32> = vec![];
+        // Page  →  Index in `path`
+        let mut on_path: HashMap<u32, usize> = HashMap::new();
+        for id in pages {
+            path.clear();
+            on_path.clear();
+            let mut cur = id;
+            // where the end of `path` leads, and in how many steps from there
+            let (destination, steps) = loop {
+                if let Some(r) = reach.get(&cur) {
+                    break (r.destination, r.steps + 1);
+                }
+                if let Some(&i) = on_path.get(&cur) {
+                    let cycle = Destination::Cycle(cycles.len());
+                    for &member in &path[i..] {
+                        reach.insert(member, Reach { destination: cycle, steps: 0 });
+                    }
+                    let start = (i..path.len()).min_by_key(|&j| path[j]).unwrap_or(i);
+                    let mut members = path[start..].to_vec();
+                    members.extend_from_slice(&path[i..start]);
+                    cycles.push(members);
+                    path.truncate(i);
+                    break (cycle, 1);
+                }
+                on_path.insert(cur, path.len());
+                path.push(cur);
+                match next.get(&cur) {
+                    Some(&n) => cur = n,
+                    None => {
+                        path.pop();
+                        reach.insert(cur, Reach { destination: Destination::DeadEnd(cur),
+                                                  steps: 0 });
+                        break (Destination::DeadEnd(cur), 1);
+                    },
+                }
+            };
+            for (i, &page) in path.iter().rev().enumerate() {
+                reach.insert(page, Reach { destination, steps: steps + i });
+            }
+        }
+
+        let mut cycles: Vec<Cycle> = cycles.into_iter()
+            .map(|members| Cycle { members, basin: 0, depth: 0 })
+            .collect();
+        for r in reach.values() {
+            if let (Destination::Cycle(c), true) = (r.destination, r.steps > 0) {
+                cycles[c].basin += 1;
+                cycles[c].depth = cycles[c].depth.max(r.steps);
+            }
+        }
+        // (renumber the cycles from largest basin to smallest)
+        let mut ranked: Vec<usize> = (0..cycles.len()).collect();
+        ranked.sort_by(|&a, &b| cycles[b].basin.cmp(&cycles[a].basin)
+                       .then(cycles[a].members[0].cmp(&cycles[b].members[0])));
+        let mut renumbered = vec![0; cycles.len()];
+        for (new, &old) in ranked.iter().enumerate() {
+            renumbered[old] = new;
+        }
+        for r in reach.values_mut() {
+            if let Destination::Cycle(ref mut c) = r.destination {
+                *c = renumbered[*c];
+            }
+        }
+        let cycles = ranked.iter().map(|&old| cycles[old].clone()).collect();
+        FirstLinks { next, cycles, reach }
+    }
+    /// Every cycle (largest basin first)
+    pub fn cycles(&self) -> &[Cycle] {
+        &self.cycles
+    }
+    /// Where following first links from `id` ends up
+    pub fn reach(&self, id: u32) -> Option<&Reach> {
+        self.reach.get(&id)
+    }
+    pub fn first_link(&self, id: u32) -> Option<u32> {
+        self.next.get(&id).cloned()
+    }
+    /// The pages visited following first links from `id`, until one repeats (or there are
+    /// no more)
+    pub fn follow(&self, id: u32) -> Vec<u32> {
+        let mut path = vec![id];
+        let mut cur = id;
+        while let Some(&n) = self.next.get(&cur) {
+            path.push(n);
+            if path[..path.len()-1].contains(&n) {
+                break;
+            }
+            cur = n;
+        }
+        path
+    }
+    /// Write each part next to `dst`, e.g. `links_first_link_cycles.csv` for `links.json`
+    pub fn export<F: Fn(u32) -> String>(&self, dst: &PathBuf, title: F)
+        -> Result<(), csv::Error>
+    {
+        // (cycle, length, basin, depth, members)
+        let mut w = csv::Writer::from_file(append_to_pathbuf(dst, "_first_link_cycles", "csv"))?;
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            let members: Vec<String> = cycle.members.iter()
+                .map(|&id| format!("{} {}", id, title(id)))
+                .collect();
+            w.encode((i, cycle.members.len(), cycle.basin, cycle.depth, members.join(" → ")))?;
+        }
+        // (id, title, cycle, dead end, steps)
+        let mut w = csv::Writer::from_file(append_to_pathbuf(dst, "_first_link_basins", "csv"))?;
+        let mut pages: Vec<(&u32, &Reach)> = self.reach.iter().collect();
+        pages.sort_by_key(|&(id, _)| *id);
+        for (&id, r) in pages {
+            let (cycle, dead_end) = match r.destination {
+                Destination::Cycle(c) => (Some(c), None),
+                Destination::DeadEnd(d) => (None, Some(d)),
+            };
+            w.encode((id, title(id), cycle, dead_end, r.steps))?;
+        }
+        Ok(())
+    }
+}
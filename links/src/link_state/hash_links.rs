@@ -4,10 +4,16 @@ use fst;
 use fnv;
 use slog;
 
-use super::{LinkState, LinkData, HashLinks};
+use super::{LinkState, LinkData, HashLinks, Categories, LangLinks, Sections, Wanted,
+            Metadata, LinkOrder, FirstLinks};
+use super::page_metadata::PageMetadata;
+use super::sections::Section;
+use super::wanted::WantedPage;
 use super::Entry;
 use super::bfs::{BFS,BFS2};
 use super::Path;
+use super::namespace::Namespaces;
+use super::title;
 
 use std::io;
 
@@ -18,6 +24,98 @@ impl LinkState<HashLinks> {
         let bfs = BFS::new(null, &self.state.links, src, dst);
         bfs.search()
     }
+    /// Like `bfs`, but the path can only go through pages in `namespaces`
+    pub fn bfs_in(&self, src: u32, dst: u32, namespaces: &Namespaces) -> Path {
+        let null = slog::Logger::root(slog::Discard, o!());
+        let bfs = BFS::new(null, &self.state.links, src, dst).with_namespaces(namespaces);
+        bfs.search()
+    }
+    /// Like `bfs_in` (if `namespaces` are given), but the path can't go through 
+    /// disambiguation pages either
+    pub fn bfs_without_disambiguation(&self, src: u32, dst: u32, 
+                                      namespaces: Option<&Namespaces>) -> Path {
+        let null = slog::Logger::root(slog::Discard, o!());
+        let mut bfs = BFS::new(null, &self.state.links, src, dst).without_disambiguation();
+        if let Some(ns) = namespaces {
+            bfs = bfs.with_namespaces(ns);
+        }
+        bfs.search()
+    }
+    /// Whether `id` is a disambiguation page (if page_props.sql was parsed)
+    pub fn is_disambiguation(&self, id: u32) -> bool {
+        self.state.links.get(&id).is_some_and(|e| e.disambiguation)
+    }
+    /// Category membership, if categorylinks.sql was parsed
+    pub fn categories(&self) -> Option<&Categories> {
+        self.state.extras.categories.as_ref()
+    }
+    /// Titles in other language editions, if langlinks.sql was parsed
+    pub fn langlinks(&self) -> Option<&LangLinks> {
+        self.state.extras.langlinks.as_ref()
+    }
+    /// Redirects to sections, if the data has them
+    pub fn sections(&self) -> Option<&Sections> {
+        self.state.extras.sections.as_ref()
+    }
+    /// Missing pages that are linked to, if they were kept
+    pub fn wanted(&self) -> Option<&Wanted> {
+        self.state.extras.wanted.as_ref()
+    }
+    /// The missing page `title` (as it'd be typed in), with the pages that link to it
+    pub fn resolve_wanted(&self, title: &str) -> Option<&WantedPage> {
+        let wanted = self.wanted()?;
+        title::variants(title).iter().filter_map(|k| wanted.get(k)).next()
+    }
+    /// Each page's length, latest revision, and touched time, if they were kept
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.state.extras.metadata.as_ref()
+    }
+    pub fn metadata_of(&self, id: u32) -> Option<&PageMetadata> {
+        self.metadata().and_then(|m| m.get(id))
+    }
+    /// Each page's links in the order they're written, if the data came from an XML export
+    pub fn link_order(&self) -> Option<&LinkOrder> {
+        self.state.extras.link_order.as_ref()
+    }
+    /// Where following the first link of each page leads (see `first_links`), if the data 
+    /// came from an XML export
+    pub fn first_links(&self) -> Option<FirstLinks> {
+        self.link_order().map(|o| FirstLinks::new(o, self.state.links.keys().cloned()))
+    }
+    /// Like `resolve_title`, along with the section it leads to if it's a redirect to one
+    pub fn resolve_section(&self, title: &str) -> Option<(u32, Option<&Section>)> {
+        title::variants(title).iter()
+            .filter_map(|k| self.state.titles.get(k).map(|n| {
+                let section = self.sections().and_then(|s| s.by_title(k));
+                (n as u32, section)
+            }))
+            .next()
+    }
+    /// The section each hop of `path` lands on, if any
+    pub fn path_sections(&self, path: &Path) -> Vec<Option<&Section>> {
+        match (self.sections(), path.path.as_ref()) {
+            (Some(sections), Ok(ids)) => sections.along(ids),
+            _ => vec![],
+        }
+    }
+    /// Categories the pages of `path` are in, with how many of them are in each
+    pub fn path_categories(&self, path: &Path) -> Vec<(&str, usize)> {
+        match (self.state.extras.categories.as_ref(), path.path.as_ref()) {
+            (Some(categories), Ok(ids)) => categories.along(ids),
+            _ => vec![],
+        }
+    }
+    pub fn resolve_title(&self, title: &str) -> Option<u32> {
+        self.state.resolve_title(title)
+    }
+    /// Every page's entry
+    pub fn get_links(&self) -> &fnv::FnvHashMap<u32,Entry> {
+        self.state.get_links()
+    }
+    /// The title of `id` as it'd be shown on the site
+    pub fn title_of(&self, id: u32) -> Option<String> {
+        self.state.links.get(&id).map(|e| title::display(&e.title))
+    }
     pub fn bfs2(&self, src: u32, dst: u32) -> Path {
         let null = slog::Logger::root(slog::Discard, o!());
         let bfs = BFS2::new(null, &self.state.links, src, dst);
@@ -57,7 +155,7 @@ impl LinkState<HashLinks> {
 impl From<LinkState<LinkData>> for LinkState<HashLinks> {
     fn from(old: LinkState<LinkData>) -> LinkState<HashLinks> {
         let (threads, size) = (old.threads, old.size);
-        let (links, log, titles_b) = old.break_down();
+        let (links, log, titles_b, extras) = old.break_down();
         let titles_map = fst::Map::from_bytes(titles_b).expect("invalid fst bytes");
         LinkState {
             threads:    threads,
@@ -70,6 +168,7 @@ impl From<LinkState<LinkData>> for LinkState<HashLinks> {
                 //titles: HashLinks::hash_titles(old.state.titles),
                 links:  links,
                 titles: titles_map,
+                extras,
                 //_titles: HashLinks::hash_titles(titles),
             }
         }
@@ -131,13 +230,13 @@ impl HashLinks {
     }
     */
     fn resolve_title(&self, t: &str) -> Option<u32> {
-        let t = t.trim();
         //if t.is_empty() { return Some(self.select_random()); }
-        //let t = t.to_uppercase();
-        let t = t.replace(' ', "_");
+        // e.g. ` ender's game` is stored as `Ender's_Game`, `Category:Physics` as `14|Physics`
         //let hash = HashLinks::hash_title(&t);
         //self.titles.get(&hash)
-        self.titles.get(&t).map(|n| n as u32) // todo panic if trunc
+        title::variants(t).iter()
+            .filter_map(|k| self.titles.get(k))
+            .next().map(|n| n as u32) // todo panic if trunc
     }
 
 }
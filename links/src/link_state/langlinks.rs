@@ -0,0 +1,62 @@
+// Links to the same page in other language editions (from langlinks.sql)
+// Foreign titles are kept as they are in the dump, i.e. with spaces rather than
+//  underscores and with the namespace prefix (if any) in the other wiki's language.
+
+use fnv::FnvHashMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LangLink {
+    // e.g. `de` or `simple`
+    pub lang: String,
+    pub title: String,
+}
+
+/// A page and its langlinks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageLangLinks {
+    pub id: u32,
+    pub links: Vec<LangLink>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LangLinks {
+    // Page → its title in other languages
+    pages: FnvHashMap<u32, Vec<LangLink>>,
+}
+
+impl LangLinks {
+    pub fn new<I: IntoIterator<Item=PageLangLinks>>(pages: I) -> LangLinks {
+        LangLinks {
+            pages: pages.into_iter().map(|p| (p.id, p.links)).collect(),
+        }
+    }
+    /// Number of pages with langlinks
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+    /// Every page's langlinks, sorted by page id
+    pub fn pages(&self) -> Vec<PageLangLinks> {
+        let mut pages: Vec<PageLangLinks> = self.pages.iter()
+            .map(|(&id, links)| PageLangLinks { id, links: links.clone() })
+            .collect();
+        pages.sort_by_key(|p| p.id);
+        pages
+    }
+    /// Every language `page` has a langlink to
+    pub fn of(&self, page: u32) -> &[LangLink] {
+        self.pages.get(&page).map_or(&[], |l| l)
+    }
+    /// `page`'s title in `lang`
+    pub fn title_in(&self, page: u32, lang: &str) -> Option<&str> {
+        self.of(page).iter().find(|l| l.lang == lang).map(|l| l.title.as_str())
+    }
+    /// Every page with a langlink to `lang`, and its title there
+    pub fn to<'a>(&'a self, lang: &'a str) -> impl Iterator<Item=(u32, &'a str)> + 'a {
+        self.pages.iter().filter_map(move |(&id, links)| {
+            links.iter().find(|l| l.lang == lang).map(|l| (id, l.title.as_str()))
+        })
+    }
+}
@@ -1,5 +1,5 @@
 
-use {slog, fst, serde_json};
+use {slog, serde_json};
 use fnv::FnvHashMap;
 
 use std::io::{self, Read, Write, BufRead, BufReader};
@@ -9,7 +9,10 @@ use std::ffi::OsString;
 use std::thread;
 
 use super::{LinkState, LinkDb, LinkData};
+use super::link_db::parse::ParseReport;
 use super::Entry;
+use super::Extras;
+use super::migrate;
 
 // TODO replace IndexedEntry with (u32, Entry) ?
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,6 +23,13 @@ pub struct IndexedEntry {
     pub neighbors: Vec<u32>,
     pub last_parent: u32,
     pub first_child: u32,
+    // from page_props.sql (only written if it's set)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub disambiguation: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+    *b == false
 }
 
 impl IndexedEntry {
@@ -49,9 +59,16 @@ impl IndexedEntry {
         IndexedEntry {
             id: i,
             title: t,
-            neighbors, last_parent, first_child
+            neighbors, last_parent, first_child,
+            disambiguation: false,
         }
     }
+    pub fn parents(&self) -> &[u32] {
+        &self.neighbors[..self.last_parent as usize]
+    }
+    pub fn children(&self) -> &[u32] {
+        &self.neighbors[self.first_child as usize..]
+    }
 }
 
 impl From<LinkState<LinkDb>> for LinkState<LinkData> {
@@ -59,19 +76,10 @@ impl From<LinkState<LinkDb>> for LinkState<LinkData> {
         // entries will become into lookup table
         // addresses and ranks feed into PostgreSQL
         
-        let (entries_i, titles) = old.state.parts();
+        // (the title fst was built by `Database::title_fst`)
+        let (entries_i, fst_bytes, report, extras) = old.state.parts();
         let mut entries: Vec<Vec<IndexedEntry>> = Vec::with_capacity(old.threads);
 
-        // convert `titles` to a fst Map
-        // for now, do this in memory (can slightly better optimize or something)
-        let mut titles_sorted = titles.clone().into_iter().map(|(title,id)| {
-            (title, u64::from(id))
-        }).collect::<Vec<(String,u64)>>();
-        titles_sorted.sort_by(|a,b| a.0.cmp(&b.0));
-        let mut mb = fst::MapBuilder::memory();
-        mb.extend_iter(titles_sorted.into_iter()).expect("fst population");
-        let fst_bytes = mb.into_inner().expect("fst finilize");
-
         //seems like there should be a more functional way to do this
         //  if .take() didn't consume?  doesn't shallow copy??
         // could stand to be refactored
@@ -93,6 +101,8 @@ impl From<LinkState<LinkDb>> for LinkState<LinkData> {
             state:      LinkData {
                 dumps:  entries,
                 titles: fst_bytes,
+                report: Some(report),
+                extras,
             }
         }
     }
@@ -100,6 +110,9 @@ impl From<LinkState<LinkDb>> for LinkState<LinkData> {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LinkManifest {
+    // format of the snapshot (see `migrate`); missing from the oldest ones
+    #[serde(default)]
+    version: u32,
     threads: usize,
     size:    usize,
     entries: Vec<PathBuf>,
@@ -117,6 +130,7 @@ impl LinkState<LinkData> {
     // need to read from or write to files to restore from/to this state
     fn manifest(&self, mn: &PathBuf) -> LinkManifest {
         LinkManifest {
+            version:    migrate::VERSION,
             threads:    self.threads,
             size:       self.size,
             titles:      append_to_pathbuf(mn, "_titles", "fst"),
@@ -127,7 +141,19 @@ impl LinkState<LinkData> {
             }).collect(),
         }
     }
-    pub fn break_down(self) -> (FnvHashMap<u32,Entry>, slog::Logger, Vec<u8>) {
+    /// What was dropped while parsing, if the data came from sql dumps
+    pub fn report(&self) -> Option<&ParseReport> {
+        self.state.report.as_ref()
+    }
+    /// Whichever optional tables were parsed
+    pub fn extras(&self) -> &Extras {
+        &self.state.extras
+    }
+    /// Entries whose title doesn't lead back to them (empty if the snapshot is sound)
+    pub fn validate(&self) -> Vec<String> {
+        migrate::validate(&self.state.dumps, &self.state.titles)
+    }
+    pub fn break_down(self) -> (FnvHashMap<u32,Entry>, slog::Logger, Vec<u8>, Extras) {
         let mut hm: FnvHashMap<u32,Entry> = 
             FnvHashMap::with_capacity_and_hasher(self.size, Default::default());
         for bucket in self.state.dumps {
@@ -137,11 +163,21 @@ impl LinkState<LinkData> {
                 hm.insert(id, entry);
             }
         }
-        (hm, self.log, self.state.titles)
+        (hm, self.log, self.state.titles, self.state.extras)
     }
     pub fn export(&self, dst: PathBuf) -> io::Result<()> {
         // write output to line-delimited JSON and CSV types
         let manifest = self.manifest(&dst);
+        // write the parse report (if any) alongside the manifest
+        if let Some(ref report) = self.state.report {
+            let report_p = append_to_pathbuf(&dst, "_report", "json");
+            println!("Writing parse report to `{:?}`", report_p);
+            let mut report_w = io::BufWriter::new(File::create(report_p)?);
+            serde_json::to_writer_pretty(&mut report_w, report)
+                .map_err(io::Error::other)?;
+        }
+        // and the optional tables (if any)
+        self.state.extras.export(&dst)?;
         //write manifest
         let mut mn_f = File::create(dst)?;
         let mn_s = serde_json::to_string(&manifest).expect("serialize manifest");
@@ -170,10 +206,32 @@ impl LinkState<LinkData> {
 
 
     pub fn import(src: PathBuf, log: slog::Logger) -> Result<Self,io::Error> { 
-        assert!(src.is_file());
+        if src.is_file() == false {
+            return Err(io::Error::new(io::ErrorKind::NotFound, 
+                                      format!("there's no manifest at `{:?}`", src)));
+        }
         let mut s = String::new();
-        File::open(src).and_then(|mut f: File| f.read_to_string(&mut s))?;
-        let manifest: LinkManifest = serde_json::from_str(&s).unwrap();
+        File::open(&src).and_then(|mut f: File| f.read_to_string(&mut s))?;
+        let manifest: LinkManifest = serde_json::from_str(&s)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, 
+                                        format!("`{:?}` isn't a manifest: {}", src, e)))?;
+        if manifest.version > migrate::VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                "`{:?}` is in format {}, but only up to {} is supported", 
+                src, manifest.version, migrate::VERSION)));
+        }
+
+        // the parse report is optional; it's only written if the data came from sql dumps
+        let report_p = append_to_pathbuf(&src, "_report", "json");
+        let report = if report_p.is_file() {
+            let report_f = File::open(report_p)?;
+            Some(serde_json::from_reader(BufReader::new(report_f))
+                 .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?)
+        } else {
+            None
+        };
+        // as are the optional tables
+        let mut extras = Extras::import(&src)?;
 
         // populate titles
         // for now just copy into memory and convert later
@@ -187,17 +245,21 @@ impl LinkState<LinkData> {
 
         let threads = manifest.entries.into_iter().map(|p| {
             thread::spawn(move || {
-                let f = File::open(p)?;
+                let f = File::open(&p)?;
                 let r = BufReader::new(f);
-                let err = "Deserializing data";
-                r.lines().map(|l| l.map(|s| serde_json::from_str(&s).expect(err)))
-                    .collect::<io::Result<Vec<IndexedEntry>>>()
+                r.lines().enumerate().map(|(i, l)| l.and_then(|s| {
+                    serde_json::from_str(&s).map_err(|e| io::Error::new(
+                            io::ErrorKind::InvalidData, 
+                            format!("bad entry on line {} of `{:?}`: {}", i + 1, p, e)))
+                })).collect::<io::Result<Vec<IndexedEntry>>>()
             })
         }).collect::<Vec<thread::JoinHandle<io::Result<Vec<IndexedEntry>>>>>();
-        // handle these unwraps better? look into `failure`?
-        let data: Vec<Vec<IndexedEntry>> = threads.into_iter()
-            .map(|t| t.join().unwrap().unwrap())
-            .collect();
+        let mut data: Vec<Vec<IndexedEntry>> = threads.into_iter()
+            .map(|t| t.join().unwrap_or_else(|_| Err(io::Error::other(
+                            "a thread reading entries panicked"))))
+            .collect::<io::Result<_>>()?;
+        let titles_b = migrate::migrate(manifest.version, &log, 
+                                        &mut data, titles_b, &mut extras);
 
         Ok(LinkState {
             log,
@@ -206,6 +268,8 @@ impl LinkState<LinkData> {
             state:   LinkData {
                 dumps: data,
                 titles: titles_b,
+                report,
+                extras,
             }
         })
     }
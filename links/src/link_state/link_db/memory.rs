@@ -0,0 +1,40 @@
+// How much memory the process is using, to see which stage of a parse needs the most
+// This reads `/proc/self/status`, so it's only available on linux.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+fn status(field: &str) -> Option<u64> {
+    // e.g. `VmHWM:     123456 kB`
+    let r = BufReader::new(File::open("/proc/self/status").ok()?);
+    r.lines().map_while(Result::ok)
+        .find(|l| l.starts_with(field))
+        .and_then(|l| l[field.len()..].split_whitespace().next()?.parse().ok())
+        .map(|kb: u64| kb * 1024)
+}
+
+/// Most memory the process has used so far (peak resident set size), in bytes
+pub fn peak() -> Option<u64> {
+    status("VmHWM:")
+}
+
+/// Memory the process is using right now (resident set size), in bytes
+pub fn current() -> Option<u64> {
+    status("VmRSS:")
+}
+
+/// e.g. `1.5 GB`, or `unknown`
+pub fn display(bytes: Option<u64>) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let bytes = match bytes {
+        Some(b) => b,
+        None => return String::from("unknown"),
+    };
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
@@ -1,36 +1,144 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::iter::Iterator;
-use std::collections::HashMap;
 
 use super::link_data::IndexedEntry;
+use self::parse::ParseReport;
+use self::parse::database::redirects::RedirectReport;
+use super::namespace::Namespaces;
+use super::Extras;
 use super::{LinkState, LinkDb, new_logger};
 
 pub mod parse;
+pub mod memory;
+pub mod xml;
+#[cfg(any(test, feature = "synthetic"))]
+pub mod synthetic;
+
+// pagelinks.sql is parsed on this many threads unless told otherwise
+pub const DEFAULT_THREADS: usize = 4;
+
+/// The sql dumps to build the link data from
+#[derive(Debug, Clone)]
+pub struct Dumps {
+    pub page: PathBuf,
+    pub redirect: PathBuf,
+    pub pagelinks: PathBuf,
+    // only in newer dumps, which refer to link destinations by an id into linktarget.sql
+    pub linktarget: Option<PathBuf>,
+    // optional: which templates each page transcludes (to leave out their links)
+    pub templatelinks: Option<PathBuf>,
+    // optional: category membership
+    pub categorylinks: Option<PathBuf>,
+    // optional: titles in other language editions
+    pub langlinks: Option<PathBuf>,
+    // optional: page properties (to flag disambiguation pages)
+    pub page_props: Option<PathBuf>,
+    // optional: keep the titles of missing pages that are linked to (see `wanted`)
+    pub wanted: bool,
+    // optional: keep each page's length, latest revision, and touched time
+    pub metadata: bool,
+    // read pagelinks.sql twice (counting, then adding links) to use less memory
+    pub low_memory: bool,
+    // how many threads parse pagelinks.sql (if it isn't compressed); also how many files
+    //  the entries are exported to
+    pub threads: usize,
+}
+
+impl Dumps {
+    pub fn new<P: Into<PathBuf>>(page: P, redirect: P, pagelinks: P) -> Dumps {
+        Dumps {
+            page: page.into(),
+            redirect: redirect.into(),
+            pagelinks: pagelinks.into(),
+            linktarget: None,
+            templatelinks: None,
+            categorylinks: None,
+            langlinks: None,
+            page_props: None,
+            wanted: false,
+            metadata: false,
+            low_memory: false,
+            threads: DEFAULT_THREADS,
+        }
+    }
+}
 
 impl LinkState<LinkDb> {
-    pub fn new(pages_db: &Path, redir_db: &Path, links_db: &Path) -> Self {
+    pub fn new(dumps: &Dumps, namespaces: Namespaces) -> Result<Self, parse::ParseError> {
         let root_log = new_logger();
         let db_log = root_log.new(o!(
-                "pages" => format!("{}", pages_db.display()), 
-                "redir" => format!("{}", redir_db.display()), 
-                "links" => format!("{}", links_db.display())) );
-        let db = parse::populate_db(pages_db, redir_db, links_db, db_log);
-        let ti = db.title_table();
+                "pages" => format!("{}", dumps.page.display()), 
+                "redir" => format!("{}", dumps.redirect.display()), 
+                "links" => format!("{}", dumps.pagelinks.display()),
+                "targets" => format!("{:?}", dumps.linktarget),
+                "templates" => format!("{:?}", dumps.templatelinks),
+                "categories" => format!("{:?}", dumps.categorylinks),
+                "langlinks" => format!("{:?}", dumps.langlinks),
+                "page_props" => format!("{:?}", dumps.page_props),
+                "wanted" => format!("{}", dumps.wanted),
+                "metadata" => format!("{}", dumps.metadata),
+                "low_memory" => format!("{}", dumps.low_memory),
+                "threads" => format!("{}", dumps.threads),
+                "namespaces" => format!("{}", namespaces)) );
+        let threads = dumps.threads.max(1);
+        let (mut db, report) = parse::populate_db(dumps, namespaces, threads, db_log)?;
+        let extras = Extras {
+            categories: db.take_categories(),
+            langlinks:  db.take_langlinks(),
+            sections:   Some(db.take_sections()),
+            wanted:     db.take_wanted(),
+            metadata:   db.take_metadata(),
+            link_order: None,
+        };
+        Ok(LinkState::from_db(db, report, extras, threads, root_log))
+    }
+    /// Build the link data from an XML export (`pages-articles.xml`, optionally compressed)
+    /// instead of the sql dumps, keeping the order each page's links are written in
+    pub fn from_xml(xml: &Path, namespaces: Namespaces) -> Result<Self, parse::ParseError> {
+        let root_log = new_logger();
+        let db_log = root_log.new(o!(
+                "xml" => format!("{}", xml.display()),
+                "namespaces" => format!("{}", namespaces)) );
+        let (mut db, report, order) = xml::populate_db(xml, namespaces, db_log)?;
+        let extras = Extras {
+            sections:   Some(db.take_sections()),
+            link_order: Some(order),
+            ..Extras::default()
+        };
+        Ok(LinkState::from_db(db, report, extras, 1, root_log))
+    }
+    fn from_db(db: parse::database::Database, report: ParseReport, extras: Extras,
+               threads: usize, root_log: slog::Logger) -> Self {
+        let ti = db.title_fst();
+        info!(root_log, "Peak memory while parsing: {}", memory::display(memory::peak()));
         LinkState { 
             size:       db.num_entries(),
-            threads:    4,
+            threads,
             log:        root_log,
             state:      LinkDb {
-                db: db,
+                db,
                 titles: ti,
+                report,
+                extras,
             }
         }
     }
+    /// What was dropped while parsing
+    pub fn report(&self) -> &ParseReport {
+        &self.state.report
+    }
+    /// Double, circular, and broken redirects
+    pub fn redirect_report(&self) -> Option<&RedirectReport> {
+        self.state.db.redirect_report()
+    }
 }
 
+// the entries of a finished `Database`
+type Entries = Box<dyn Iterator<Item=IndexedEntry>>;
+
 impl LinkDb {
-    pub fn parts(self) -> (Box<Iterator<Item=IndexedEntry>>, HashMap<String,u32>) {
-        (self.db.explode(), self.titles)
+    pub fn parts(self) -> (Entries, Vec<u8>, ParseReport, Extras) {
+        (self.db.explode(), self.titles, self.report, self.extras)
     }
 }
 
@@ -0,0 +1,165 @@
+// Links kept in flat arrays while a low-memory build adds them (see `Database::reserve_links`)
+// Once pagelinks.sql's links have been counted, each page's children (and parents) get a
+//  slice of one array sized from the counts, rather than a `Vec` of their own that keeps
+//  growing (and ends up with room to spare) as links are added. Pages are numbered in
+//  the order of their ids:
+//
+//      offsets:    where each page's slice starts (and `offsets[i+1]` where it ends)
+//      lens:       how much of it has been filled
+//      targets:    the slices, one after another
+//
+// Duplicate links are counted too, so a slice can end up partly empty. When the db is
+//  finalized each slice is moved into its page's own `Vec` (see `Csr::unpack`).
+
+use super::helpers::Link;
+
+// ids are grouped this many bits' worth at a time to find their page's number quickly
+const BUCKET_BITS: u32 = 8;
+// how many pages `unpack` moves out before giving back the end of the arrays
+const PAGES_PER_SHRINK: usize = 1 << 12;
+
+/// Page ids in order; a page's number is its position
+pub struct PageIndex {
+    ids: Vec<u32>,
+    // the number of the first page in each bucket of ids (and one past the last page)
+    buckets: Vec<u32>,
+}
+
+impl PageIndex {
+    pub fn new(mut ids: Vec<u32>) -> PageIndex {
+        ids.sort_unstable();
+        let last = ids.last().map_or(0, |&id| (id >> BUCKET_BITS) as usize + 1);
+        let mut buckets = Vec::with_capacity(last + 1);
+        let mut i = 0;
+        for bucket in 0..=last {
+            while i < ids.len() && ((ids[i] >> BUCKET_BITS) as usize) < bucket {
+                i += 1;
+            }
+            buckets.push(i as u32);
+        }
+        PageIndex { ids, buckets }
+    }
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+    /// The number of the page `id`, if it's a page
+    pub fn position(&self, id: u32) -> Option<usize> {
+        let bucket = (id >> BUCKET_BITS) as usize;
+        if bucket + 1 >= self.buckets.len() {
+            return None;
+        }
+        let (lo, hi) = (self.buckets[bucket] as usize, self.buckets[bucket + 1] as usize);
+        self.ids[lo..hi].binary_search(&id).ok().map(|i| lo + i)
+    }
+}
+
+/// How many links each page has, counted before they're added
+/// (duplicates are counted too, so these are upper bounds)
+pub struct Degrees {
+    index: PageIndex,
+    children: Vec<u32>,
+    parents: Vec<u32>,
+}
+
+impl Degrees {
+    pub fn new(index: PageIndex) -> Degrees {
+        let n = index.len();
+        Degrees { index, children: vec![0; n], parents: vec![0; n] }
+    }
+    pub fn count(&mut self, link: &Link) {
+        if let Some(i) = self.index.position(link.src) {
+            self.children[i] += 1;
+        }
+        if let Some(i) = self.index.position(link.dst) {
+            self.parents[i] += 1;
+        }
+    }
+}
+
+// one direction of every page's links
+struct Slices {
+    offsets: Vec<usize>,
+    lens: Vec<u32>,
+    targets: Vec<u32>,
+}
+
+impl Slices {
+    fn new(counts: &[u32]) -> Slices {
+        let mut offsets = Vec::with_capacity(counts.len() + 1);
+        let mut total = 0usize;
+        offsets.push(total);
+        for &count in counts {
+            total += count as usize;
+            offsets.push(total);
+        }
+        Slices { offsets, lens: vec![0; counts.len()], targets: vec![0; total] }
+    }
+    fn is_full(&self, i: usize) -> bool {
+        self.offsets[i] + self.lens[i] as usize == self.offsets[i + 1]
+    }
+    fn push(&mut self, i: usize, target: u32) {
+        self.targets[self.offsets[i] + self.lens[i] as usize] = target;
+        self.lens[i] += 1;
+    }
+    // page `i`'s slice, sorted and without duplicates
+    fn take(&mut self, i: usize) -> Vec<u32> {
+        let start = self.offsets[i];
+        let slice = &mut self.targets[start .. start + self.lens[i] as usize];
+        slice.sort_unstable();
+        let unique = slice.len() - slice.windows(2).filter(|w| w[0] == w[1]).count();
+        let mut ids = Vec::with_capacity(unique);
+        for &id in slice.iter() {
+            if ids.last() != Some(&id) {
+                ids.push(id);
+            }
+        }
+        ids
+    }
+    // give back the slices of page `i` and the ones after it
+    fn shrink_to(&mut self, i: usize) {
+        self.targets.truncate(self.offsets[i]);
+        self.targets.shrink_to_fit();
+    }
+}
+
+/// Every page's children and parents (see above)
+pub struct Csr {
+    index: PageIndex,
+    children: Slices,
+    parents: Slices,
+}
+
+impl Csr {
+    pub fn new(degrees: Degrees) -> Csr {
+        let Degrees { index, children, parents } = degrees;
+        Csr { index, children: Slices::new(&children), parents: Slices::new(&parents) }
+    }
+    /// Add a link between two pages, unless either one isn't a page or its slice is
+    /// already full (which only happens if the links that were counted were different)
+    pub fn push(&mut self, src: u32, dst: u32) -> bool {
+        match (self.index.position(src), self.index.position(dst)) {
+            (Some(s), Some(d)) if self.children.is_full(s) == false
+                               && self.parents.is_full(d) == false => {
+                self.children.push(s, dst);
+                self.parents.push(d, src);
+                true
+            },
+            _ => false,
+        }
+    }
+    /// Hand each page's (id, children, parents) to `f`, sorted and without duplicates
+    /// (from the last page to the first, so the arrays can shrink as they go)
+    pub fn unpack<F: FnMut(u32, Vec<u32>, Vec<u32>)>(mut self, mut f: F) {
+        for i in (0..self.index.len()).rev() {
+            let (children, parents) = (self.children.take(i), self.parents.take(i));
+            f(self.index.ids[i], children, parents);
+            if i % PAGES_PER_SHRINK == 0 {
+                self.children.shrink_to(i);
+                self.parents.shrink_to(i);
+            }
+        }
+    }
+}
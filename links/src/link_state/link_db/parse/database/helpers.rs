@@ -12,9 +12,20 @@ pub enum Entry {
     Redirect {
         title: String,
         target: Option<u32>,
+        // the section of the target it goes to, if any
+        fragment: Option<String>,
     }
 }
 
+// a pagelink between two pages (after following redirects)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Link {
+    pub src: u32,
+    pub dst: u32,
+    // the redirect it went through, if it goes to a section of `dst`
+    pub section: Option<u32>,
+}
+
 //what phase the database is in 
 //TODO should I get rid of the numbers? They don't matter except that without 
 // them it might not be clear that the order of the values is what determines
@@ -24,7 +35,13 @@ pub enum State {
     Begin           = 0,
     AddPages        = 1,
     AddRedirects    = 2,
-    TidyEntries     = 3,
-    AddLinks        = 4,
-    Done            = 5,
+    AddLinkTargets  = 3,
+    TidyEntries     = 4,
+    AddLinks        = 5,
+    AddTemplateLinks= 6,
+    Done            = 7,
+    // categories aren't part of the link graph, so they're added once it's done
+    AddCategories   = 8,
+    AddLangLinks    = 9,
+    AddPageProps    = 10,
 }
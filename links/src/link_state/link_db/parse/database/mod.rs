@@ -1,15 +1,35 @@
-extern crate regex;
-
 use slog;
+use fst;
 mod helpers;
 use self::helpers::*;
+pub use self::helpers::Link;
+mod csr;
+use self::csr::{Csr, PageIndex};
+pub use self::csr::Degrees;
+mod templates;
+use self::templates::Templates;
+pub mod redirects;
+use self::redirects::RedirectReport;
 use super::super::IndexedEntry;
+use link_state::namespace::{self, Namespaces};
+use super::tables::{PageRow, RedirectRow, LinkTargetRow, PageLinkRow, TemplateLinkRow, 
+                    CategoryLinkRow, LangLinkRow, PagePropRow};
+use link_state::categories::Categories;
+use link_state::langlinks::{LangLinks, LangLink, PageLangLinks};
+use link_state::sections::{Sections, Section};
+use link_state::wanted::Wanted;
+use link_state::page_metadata::{Metadata, PageMetadata};
+use super::report::{ParseReport, DropReason};
 
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::iter;
 
 
+// (id, title, parents, children, disambiguation) of a finalized page
+pub type PageView<'a> = (u32, &'a str, &'a [u32], &'a [u32], bool);
+
 // The actual data storing the internal link structure
 pub struct Database {
     // when populating the entries/addresses fields, we may come across redirect pages
@@ -18,80 +38,193 @@ pub struct Database {
     // Address  →  Page
     entries: HashMap<u32, Entry>,
     //  Title   →  Address
+    // titles outside of the main namespace are prefixed by theirs (see `namespace::key`)
     addresses: HashMap<String, u32>,
+    // in newer dumps pagelinks refer to their destination by a linktarget id
+    // Target   →  Title
+    link_targets: HashMap<u32, String>,
+    // whether linktarget.sql is being parsed (without it, links by target can't resolve)
+    uses_linktargets: bool,
+    // every page's links in flat arrays, while a low-memory build adds them
+    //  (see `reserve_links`; they're moved into the entries by `finalize`)
+    csr: Option<Csr>,
+    // which namespaces' pages are kept
+    namespaces: Namespaces,
+    // only present if templatelinks.sql is being parsed
+    templates: Option<Templates>,
+    // only present if categorylinks.sql is being parsed
+    // Category →  Members
+    categories: Option<HashMap<String, Vec<u32>>>,
+    // Target   →  Category
+    category_targets: HashMap<u32, String>,
+    // only present if langlinks.sql is being parsed
+    //  Page    →  Titles in other languages
+    langlinks: Option<HashMap<u32, Vec<LangLink>>>,
+    // redirects to a section of a page (`addresses` leads past them once they're tidied)
+    //  Title   →  Redirect
+    section_titles: HashMap<String, u32>,
+    // links through them
+    // Redirect →  Sources
+    section_links: HashMap<u32, Vec<u32>>,
+    // the redirects themselves (built by `finalize`, before redirects are removed)
+    sections: Vec<Section>,
+    // only present if links to missing pages are being kept
+    //  Title   →  Sources
+    wanted: Option<HashMap<String, Vec<u32>>>,
+    // link targets that aren't pages (only kept along with `wanted`)
+    // Target   →  Title
+    wanted_targets: HashMap<u32, String>,
+    // only present if page_props.sql is being parsed
+    //  Disambiguation pages
+    disambiguations: Option<HashSet<u32>>,
+    // only present if page metadata is being kept (for pages, not redirects)
+    metadata: Option<Vec<PageMetadata>>,
+    // Redirect →  Target that wasn't in page.sql
+    missing_targets: HashMap<u32, String>,
+    // what the redirects looked like before they were tidied up
+    redirect_report: Option<RedirectReport>,
     //internal state
     state: State,
+    // what was dropped along the way
+    report: ParseReport,
     //logging
     log: slog::Logger,
     // keep track of all valid titles that map to valid page_ids
 }
 
 impl Database {
-    pub fn new(log: slog::Logger) -> Database {
+    pub fn new(log: slog::Logger, namespaces: Namespaces) -> Database {
         Database {
             entries: HashMap::new(),
             addresses: HashMap::new(),
+            link_targets: HashMap::new(),
+            uses_linktargets: false,
+            csr: None,
+            namespaces,
+            templates: None,
+            categories: None,
+            category_targets: HashMap::new(),
+            langlinks: None,
+            section_titles: HashMap::new(),
+            section_links: HashMap::new(),
+            sections: vec![],
+            wanted: None,
+            wanted_targets: HashMap::new(),
+            disambiguations: None,
+            metadata: None,
+            missing_targets: HashMap::new(),
+            redirect_report: None,
             state: State::Begin,
+            report: ParseReport::new(),
             log: log,
         }
     }
     pub fn log<T: Display>(&self, text: T) {
         info!(self.log, text);
     }
+    pub fn namespaces(&self) -> &Namespaces {
+        &self.namespaces
+    }
+    pub fn includes(&self, namespace: i32) -> bool {
+        self.namespaces.contains(namespace)
+    }
+    /// Resolve links by their linktarget id (see `add_linktarget`)
+    pub fn collect_linktargets(&mut self) {
+        self.uses_linktargets = true;
+    }
+    pub fn uses_linktargets(&self) -> bool {
+        self.uses_linktargets
+    }
+    /// Keep track of templates and their links for `add_templatelink`
+    pub fn collect_templates(&mut self) {
+        self.templates = Some(Templates::default());
+    }
+    /// Whether links from pages in `namespace` are kept
+    pub fn links_from(&self, namespace: i32) -> bool {
+        // templates' links are kept to find out which links come from them
+        self.includes(namespace) || (namespace == namespace::TEMPLATE && self.templates.is_some())
+    }
+    /// Keep category targets from linktarget.sql for `add_categorylink`
+    pub fn collect_categories(&mut self) {
+        self.categories = Some(HashMap::new());
+    }
+    pub fn take_categories(&mut self) -> Option<Categories> {
+        self.categories.take().map(Categories::from_members)
+    }
+    pub fn collect_langlinks(&mut self) {
+        self.langlinks = Some(HashMap::new());
+    }
+    pub fn take_langlinks(&mut self) -> Option<LangLinks> {
+        self.langlinks.take().map(|l| LangLinks::new(l.into_iter().map(|(id, links)| {
+            PageLangLinks { id, links }
+        })))
+    }
+    /// Keep the titles of missing pages that are linked to, for `take_wanted`
+    pub fn collect_wanted(&mut self) {
+        self.wanted = Some(HashMap::new());
+    }
+    pub fn take_wanted(&mut self) -> Option<Wanted> {
+        self.wanted.take().map(Wanted::from_links)
+    }
+    /// Flag disambiguation pages from page_props.sql (see `add_disambiguation`)
+    pub fn collect_disambiguations(&mut self) {
+        self.disambiguations = Some(HashSet::new());
+    }
+    /// Keep each page's metadata from page.sql, for `take_metadata`
+    pub fn collect_metadata(&mut self) {
+        self.metadata = Some(vec![]);
+    }
+    pub fn keeps_metadata(&self) -> bool {
+        self.metadata.is_some()
+    }
+    pub fn take_metadata(&mut self) -> Option<Metadata> {
+        self.metadata.take().map(Metadata::new)
+    }
+    pub fn take_sections(&mut self) -> Sections {
+        Sections::new(::std::mem::take(&mut self.sections))
+    }
+    /// Double, circular, and broken redirects (once links are being added)
+    pub fn redirect_report(&self) -> Option<&RedirectReport> {
+        self.redirect_report.as_ref()
+    }
+    pub fn report(&mut self) -> &mut ParseReport {
+        &mut self.report
+    }
+    pub fn take_report(&mut self) -> ParseReport {
+        ::std::mem::replace(&mut self.report, ParseReport::new())
+    }
     pub fn num_entries(&self) -> usize {
         self.entries.len()
     }
-    pub fn title_table(&self) -> HashMap<String, u32> {
-        // user can look up multiple variants of a title and get back its page_id
-        //
-        // for now this is mandatory, but it shouldn't be all that expensive
-        //
-        // todo: remove metadata thing; instead replace rankdata w/ analyzedata
-        // all analysis is run by cmd-line args and done on this phase
-        // if no analysis is to be done, then this stage is empty (but still run)
-        // there should only be one dump file
-        
-        // list of all titles originally
-        let mut starters = HashMap::with_capacity(self.entries.len());
-        // list of all capitalized versions of titles
-        let mut capitals = HashMap::new();
-        // terms that two non-caps titles both capitalize to
-        let mut cap_cols = HashSet::new();  // capital collisions; delete
+    /// Every title that leads to a page (its own, or a redirect's), mapped to its page_id
+    /// Other spellings/casings are handled when they're looked up (see `title::variants`)
+    pub fn title_fst(&self) -> Vec<u8> {
+        // an fst has to be built in order, but `entries` and `addresses` are hash maps, so
+        //  every title is collected and sorted first. They're borrowed rather than copied,
+        //  and sorted in place, which puts the peak at 24 bytes (a `&str`, whether it's an
+        //  address, and an id) per title on top of the database, plus the fst itself
+        //  (built in memory, so about its final size).
+        let mut titles: Vec<(&str, bool, u32)> = Vec::with_capacity(self.addresses.len());
         for (&id, entry) in &self.entries {
             let title = match *entry {
                 Entry::Page{ title: ref ti, .. } => ti,
-                Entry::Redirect{ title: ref ti, target: Some(x) } if id==x => ti,
+                Entry::Redirect{ title: ref ti, target: Some(x), .. } if id==x => ti,
                 _ => continue,
             };
-            starters.insert(title.clone(), id);
-        }
-        for (title, &id) in &self.addresses {
-            // redirect isn't present, might as well add it
-            starters.entry(title.clone()).or_insert(id);
-        }
-        for (title,&id) in &starters {
-            // add to the 'capitals' bin if it wasn't there and the caps version 
-            let ti_caps = title.to_uppercase();
-            if starters.contains_key(&ti_caps) {
-                continue;
-            } 
-            if let Some(&id2) = capitals.get(&ti_caps) {
-                if id != id2 {
-                    // 2 different articles capitalized to the same thing
-                    cap_cols.insert(ti_caps);
-                }
-            } else {
-                // haven't seen this capitalization before
-                capitals.insert(ti_caps, id);
+            // a page's own title is nearly always one of the addresses already
+            if self.addresses.get(title) != Some(&id) {
+                titles.push((title, false, id));
             }
         }
-        let mut titles = starters;
-        for (ti,id) in capitals {
-            if cap_cols.contains(&ti) == false {
-                titles.insert(ti, id);
-            }
+        titles.extend(self.addresses.iter().map(|(title, &id)| (title.as_str(), true, id)));
+        // (a page's own title wins over an address)
+        titles.sort_unstable_by(|a,b| a.0.cmp(b.0).then(a.1.cmp(&b.1)));
+        titles.dedup_by(|b,a| a.0 == b.0);
+        let mut mb = fst::MapBuilder::memory();
+        for (title, _, id) in titles {
+            mb.insert(title, u64::from(id)).expect("fst population");
         }
-        titles
+        mb.into_inner().expect("fst finalize")
     }
     //pub fn explode(self) -> 
     //    (Box<iter::Iterator<Item=IndexedEntry>>,
@@ -99,15 +232,32 @@ impl Database {
     pub fn explode(self) -> Box<iter::Iterator<Item=IndexedEntry>> {
         // destroy ourSelf and yield our contents
         // they will go in totally different data structures so yield iterators
-        let entry_iter = self.entries.into_iter().map(|e: (u32,Entry)| {
+        let disambiguations = self.disambiguations.unwrap_or_default();
+        let entry_iter = self.entries.into_iter().map(move |e: (u32,Entry)| {
             match e.1 {
                 Entry::Redirect{..} => panic!("Found redirect during explosion"),
-                Entry::Page{ title: ti, parents: p, children: c } => 
-                    IndexedEntry::from(e.0, ti, p, c)
+                Entry::Page{ title: ti, parents: p, children: c } => {
+                    let mut ie = IndexedEntry::from(e.0, ti, p, c);
+                    ie.disambiguation = disambiguations.contains(&e.0);
+                    ie
+                },
             }});
         Box::new(entry_iter)
     }
-    pub fn add_page(&mut self, data: &regex::Captures) -> bool {
+    /// Each page once it's finalized
+    /// (for when only some of them need to be made into `IndexedEntry`s; see `update`)
+    pub fn pages(&self) -> Box<dyn Iterator<Item=PageView<'_>> + '_> {
+        assert!(self.state >= State::Done, "Tried to list pages in the `{:?}` stage", self.state);
+        Box::new(self.entries.iter().filter_map(move |(&id, e)| match *e {
+            Entry::Page{ ref title, ref parents, ref children } => {
+                let disambiguation = self.disambiguations.as_ref()
+                    .is_some_and(|d| d.contains(&id));
+                Some((id, title.as_str(), parents.as_slice(), children.as_slice(), disambiguation))
+            },
+            Entry::Redirect{..} => None,
+        }))
+    }
+    pub fn add_page(&mut self, data: &PageRow) -> bool {
         //must finish before links/redirects start
         assert!(self.state <= State::AddPages, 
                 "Tried to add page in the wrong stage: `{:?}`", self.state);
@@ -116,12 +266,14 @@ impl Database {
             self.state = State::AddPages;
         }
 
-        let page_id: u32 = data.at(1).unwrap().parse().unwrap();
-        let title = data.at(2).unwrap();
-        let is_redr = data.at(3).unwrap() == "1";
+        let page_id: u32 = data.id;
+        let key = data.key();
+        let title: &str = &key;
+        let is_redr = data.is_redirect;
 
         if self.entries.contains_key(&page_id) {
             error!(self.log, "Tried to add an entry at a claimed location: {}", page_id);
+            self.report.drop(DropReason::DuplicatePage, &page_id);
             return false;
         }
 
@@ -129,6 +281,7 @@ impl Database {
             Entry::Redirect {
                 title:  String::from(title),
                 target: None,
+                fragment: None,
             }
         } else {
             Entry::Page {
@@ -139,9 +292,14 @@ impl Database {
         };
         self.entries.insert(page_id, entry);
         self.addresses.insert(String::from(title), page_id);
+        if let (Some(ref mut metadata), Some(m), false) = 
+            (self.metadata.as_mut(), data.metadata, is_redr) 
+        {
+            metadata.push(m);
+        }
         true
     }
-    pub fn add_redirect(&mut self, data: &regex::Captures) -> bool {
+    pub fn add_redirect(&mut self, data: &RedirectRow) -> bool {
         // must occur after all pages have been added and before any links
         assert!(self.state == State::AddPages || self.state == State::AddRedirects, 
                 "Tried to add a redirect in the `{:?}` stage", self.state);
@@ -150,8 +308,9 @@ impl Database {
             self.state = State::AddRedirects;
         }
 
-        let redir_id: u32 = data.at(1).unwrap().parse().unwrap();
-        let dst_title: &str = data.at(2).unwrap();
+        let redir_id: u32 = data.from;
+        let dst_key = data.key();
+        let dst_title: &str = &dst_key;
 
         //the redirect's target title *should* already be in self.addresses
         //if it is, its address should be of type Redirect(None), 
@@ -161,89 +320,437 @@ impl Database {
         // the `target` should be changed to `Some(dst_id)`
 
         let entry = self.entries.get_mut(&redir_id);
-        if let Some(&mut Entry::Redirect{ target: ref mut t, ..} ) = entry {
+        if let Some(&mut Entry::Redirect{ target: ref mut t, fragment: ref mut frag, ..} ) 
+                = entry {
             if let Some(x) = *t {
                 error!(self.log,
                        "The page_id of a redirect already redirected to page_id `{}`", x);
             }
             if let Some(&true_u32) = self.addresses.get(dst_title) {
                 *t = Some(true_u32);
+                *frag = data.fragment.as_ref().map(|f| f.to_string());
                 return true;
             } else {
                 //warn!(self.log,
                 //      "The dst_title of a redirect was not in the db: `{}`", dst_title);
+                self.missing_targets.insert(redir_id, String::from(dst_title));
+                self.report.drop(DropReason::RedirectUnknownTarget, data);
             }
         } else if entry.is_none() {
             //this can happen if the source has been deleted, or is in a different namespace
             //debug!(self.log, "A redirect source ID is missing from the table: {}", redir_id);
             //warn!(self.log, "A redirect source ID is missing from the table: {}", redir_id);
+            self.report.drop(DropReason::RedirectMissingSource, data);
         } else {
             //should not insert in:
             // we know the redirect page_id but not its redirect_title
             // without a redirect_title, no links/redirects have any way of getting a handle
             error!(self.log, 
                    "The source page_id of a redirect was somehow a `{:?}`", entry);
+            self.report.drop(DropReason::RedirectSourceNotRedirect, data);
         }
         false
     }
-    pub fn add_pagelink(&mut self, data: &regex::Captures) -> bool {
-        //add dst_id to entries[src_id].children and src_id to entries[dst_id].parents
-        
-        // only add pagelinks after adding redirects is finished
-        assert!(self.state == State::AddRedirects || self.state == State::AddLinks,
+    pub fn add_linktarget(&mut self, data: &LinkTargetRow) -> bool {
+        // must occur after all redirects have been added and before any links
+        // (redirect.sql can be empty, like in `begin_links`)
+        assert!(self.state == State::AddPages ||
+                self.state == State::AddRedirects ||
+                self.state == State::AddLinkTargets,
+                "Tried to add a link target in the `{:?}` stage", self.state);
+        if self.state != State::AddLinkTargets {
+            info!(self.log, "Entering the `AddLinkTargets` stage");
+            self.state = State::AddLinkTargets;
+        }
+
+        let target_id: u32 = data.id;
+        let key = data.key();
+        let title: &str = &key;
+
+        // don't bother keeping targets that couldn't resolve to a page anyway
+        if self.addresses.contains_key(title) == false {
+            if self.wanted.is_some() {
+                self.wanted_targets.insert(target_id, String::from(title));
+            }
+            self.report.drop(DropReason::LinkTargetUnknownTitle, data);
+            return false;
+        }
+        self.link_targets.insert(target_id, String::from(title));
+        true
+    }
+    pub fn add_template(&mut self, data: &PageRow) {
+        // (templates are kept whether or not their namespace is included)
+        if let Some(ref mut templates) = self.templates {
+            templates.add_template(data.id, &data.title);
+        }
+    }
+    pub fn add_template_target(&mut self, data: &LinkTargetRow) {
+        // only needed if templatelinks.sql refers to its templates by linktarget
+        if let Some(ref mut templates) = self.templates {
+            templates.add_target(data.id, &data.title);
+        }
+    }
+    pub fn add_templatelink(&mut self, data: &TemplateLinkRow) -> bool {
+        // templates' own links come from pagelinks.sql, so these are added after them
+        assert!(self.state == State::AddLinks || self.state == State::AddTemplateLinks,
+                "Tried to add a template link in the `{:?}` stage", self.state);
+        if self.state == State::AddLinks {
+            info!(self.log, "Entering the `AddTemplateLinks` stage");
+            self.state = State::AddTemplateLinks;
+        }
+
+        let (page_id, template_id) = {
+            let templates = self.templates.as_ref()
+                .expect("Tried to add a template link without collecting templates");
+            match *data {
+                TemplateLinkRow::Title { from, namespace, ref title } => 
+                    (from, if namespace == namespace::TEMPLATE { 
+                        templates.by_title(title) 
+                    } else { 
+                        None 
+                    }),
+                TemplateLinkRow::Target { from, target } => (from, templates.by_target(target)),
+            }
+        };
+        let template_id = match template_id {
+            Some(t) => t,
+            None => {
+                self.report.drop(DropReason::TemplateLinkUnknownTemplate, data);
+                return false;
+            },
+        };
+        if self.is_page(page_id) == false {
+            self.report.drop(DropReason::TemplateLinkMissingSource, data);
+            return false;
+        }
+        if let Some(ref mut templates) = self.templates {
+            templates.transclude(page_id, template_id);
+        }
+        true
+    }
+    pub fn add_category_target(&mut self, data: &LinkTargetRow) {
+        // only needed if categorylinks.sql refers to its categories by linktarget
+        // (these are kept whether or not the category namespace is included)
+        if self.categories.is_some() {
+            self.category_targets.insert(data.id, data.title.to_string());
+        }
+    }
+    pub fn add_categorylink(&mut self, data: &CategoryLinkRow) -> bool {
+        // categories refer to finished pages, so they're added after `finalize`
+        assert!(self.state == State::Done || self.state == State::AddCategories,
+                "Tried to add a category link in the `{:?}` stage", self.state);
+        if self.state == State::Done {
+            info!(self.log, "Entering the `AddCategories` stage");
+            self.state = State::AddCategories;
+        }
+
+        let (page_id, category) = match *data {
+            CategoryLinkRow::Title { from, ref category } => (from, &**category),
+            CategoryLinkRow::Target { from, target } => 
+                match self.category_targets.get(&target) {
+                    Some(category) => (from, category.as_str()),
+                    None => {
+                        self.report.drop(DropReason::CategoryUnknownTarget, data);
+                        return false;
+                    },
+                },
+        };
+        // redirects have been removed by now, so every entry is a page
+        if self.entries.contains_key(&page_id) == false {
+            self.report.drop(DropReason::CategoryMemberNotPage, data);
+            return false;
+        }
+        let categories = self.categories.as_mut()
+            .expect("Tried to add a category link without collecting categories");
+        if let Some(members) = categories.get_mut(category) {
+            members.push(page_id);
+            return true;
+        }
+        categories.insert(String::from(category), vec![page_id]);
+        true
+    }
+    pub fn add_langlink(&mut self, data: &LangLinkRow) -> bool {
+        // like categories, these refer to finished pages
+        assert!(self.state >= State::Done,
+                "Tried to add a langlink in the `{:?}` stage", self.state);
+        if self.state != State::AddLangLinks {
+            info!(self.log, "Entering the `AddLangLinks` stage");
+            self.state = State::AddLangLinks;
+        }
+        if self.entries.contains_key(&data.from) == false {
+            self.report.drop(DropReason::LangLinkMissingSource, data);
+            return false;
+        }
+        let link = LangLink {
+            lang:   data.lang.to_string(),
+            title:  data.title.to_string(),
+        };
+        self.langlinks.as_mut()
+            .expect("Tried to add a langlink without collecting langlinks")
+            .entry(data.from).or_default().push(link);
+        true
+    }
+    pub fn add_disambiguation(&mut self, data: &PagePropRow) -> bool {
+        // like langlinks, these refer to finished pages
+        assert!(self.state >= State::Done,
+                "Tried to add a page prop in the `{:?}` stage", self.state);
+        if self.state != State::AddPageProps {
+            info!(self.log, "Entering the `AddPageProps` stage");
+            self.state = State::AddPageProps;
+        }
+        if self.entries.contains_key(&data.page) == false {
+            self.report.drop(DropReason::PagePropMissingPage, data);
+            return false;
+        }
+        self.disambiguations.as_mut()
+            .expect("Tried to add a disambiguation page without collecting them")
+            .insert(data.page);
+        true
+    }
+    pub fn add_pagelink(&mut self, data: &PageLinkRow) -> bool {
+        self.begin_links();
+        match self.resolve_link(data) {
+            Ok(link) => {
+                self.add_link(link);
+                true
+            },
+            Err(reason) => {
+                self.drop_link(reason, data);
+                false
+            },
+        }
+    }
+    /// Record why a link was dropped (and what it was after, if it was a missing page)
+    pub fn drop_link(&mut self, reason: DropReason, data: &PageLinkRow) {
+        if let Some((title, src)) = self.wanted_link(reason, data) {
+            self.add_wanted(title, src);
+        }
+        self.report.drop(reason, data);
+    }
+    /// The missing page a dropped link was after, and the page it came from
+    /// (only if missing pages are being kept; like `resolve_link`, this only reads)
+    pub fn wanted_link(&self, reason: DropReason, data: &PageLinkRow) -> Option<(String, u32)> {
+        self.wanted.as_ref()?;
+        let (from, title) = match (reason, data) {
+            (DropReason::LinkUnknownTitle, &PageLinkRow::Title{ from, namespace, ref title }) =>
+                (from, namespace::key(namespace, title)),
+            // a target can have been a title in page.sql that turned out to only lead to a
+            //  broken redirect (or a loop); by title, those count as well
+            (DropReason::LinkUnknownTarget, &PageLinkRow::Target{ from, target }) => {
+                let title = self.wanted_targets.get(&target)
+                    .or_else(|| self.link_targets.get(&target))?;
+                (from, Cow::Borrowed(title.as_str()))
+            },
+            _ => return None,
+        };
+        // links from templates (or pages that aren't kept) don't count
+        let src = self.follow_redirects(from).unwrap_or(from);
+        if self.is_page(src) == false {
+            return None;
+        }
+        Some((title.into_owned(), src))
+    }
+    pub fn add_wanted(&mut self, title: String, src: u32) {
+        if let Some(ref mut wanted) = self.wanted {
+            wanted.entry(title).or_default().push(src);
+        }
+    }
+    pub fn begin_links(&mut self) {
+        // only add pagelinks after adding redirects (and link targets) is finished
+        // (an XML export can have no redirects at all)
+        assert!(self.state == State::AddPages ||
+                self.state == State::AddRedirects ||
+                self.state == State::AddLinkTargets ||
+                self.state == State::AddLinks,
                 "Tried to add a link in the `{:?}` stage", self.state);
-        if self.state == State::AddRedirects {
+        if self.state != State::AddLinks {
             self.tidy_entries();
             info!(self.log, "Entering the `AddLinks` stage");
             self.state = State::AddLinks;
         }
+    }
+    pub fn resolve_link(&self, data: &PageLinkRow) -> Result<Link, DropReason> {
+        // find the (src, dst) pages a link connects, following redirects on either end
+        // this only reads from the db, so many pagelinks can be resolved at once 
+        //  (see `parse::parse_pagelinks_parallel`); that's also why dropped links are 
+        //  returned rather than recorded here
+        assert_eq!(self.state, State::AddLinks, 
+                   "Tried to resolve a link in the `{:?}` stage", self.state);
 
-        let src_id: u32 = data.at(1).unwrap().parse().unwrap();
-        let dst_title: &str = data.at(2).unwrap();
-
+        let (src_id, dst_title, unknown) = match *data {
+            // legacy pagelinks layout: the destination is given by its title
+            //  this can be unknown if the dst article isn't in the same namespace, or has
+            //  been removed so it wasn't listed in page.sql
+            PageLinkRow::Title { from, namespace, ref title } => 
+                (from, namespace::key(namespace, title), DropReason::LinkUnknownTitle),
+            // linktarget pagelinks layout: the destination is given by a linktarget id
+            //  the target can be in another namespace or not be a page
+            PageLinkRow::Target { from, target } => match self.link_targets.get(&target) {
+                //lookup dst_title from target_id
+                Some(title) => 
+                    (from, Cow::Borrowed(title.as_str()), DropReason::LinkUnknownTarget),
+                None => return Err(DropReason::LinkUnknownTarget),
+            },
+        };
         //lookup dst_id from dst_title
-        let dst_id = match self.addresses.get(dst_title) {
+        let dst_id = match self.addresses.get(&*dst_title) {
             Some(&true_id) => true_id,
-            None => {
-                //this can happen if the dst article isn't in the same namespace, or has
-                // been removed so it wasn't listed in page.sql
-                //warn!(self.log, 
-                //      "A pagelink gave a destination title not in the db: `{}`", 
-                //      dst_title);
-                return false;
-            },
+            None => return Err(unknown),
         };
 
         let src_id_r = self.follow_redirects(src_id).unwrap_or(src_id);
         let dst_id_r = self.follow_redirects(dst_id).unwrap_or(dst_id);
 
-        //update entries[src_id] to have dst_id among its children
-        if let Some(&mut Entry::Page{ children: ref mut c, .. }) 
-                = self.entries.get_mut(&src_id_r) {
-            c.push(dst_id_r);                
-        } else {
+        if self.is_page(src_id_r) == false && self.is_template(src_id) == false {
             //this can happen if:
             // * a pagelink used an invalid source (it wasn't in page.sql because 
             //    the page was deleted or it's in the wrong namespace (nbd))
             // * we followed a redirect above which pointed at an invalid page (error)
-            return false;
+            return Err(DropReason::LinkMissingSource);
         }
-
-        //update entries[dst_id] to have src_id among its parents
-        if let Some(&mut Entry::Page{ parents: ref mut p, .. }) 
-                = self.entries.get_mut(&dst_id_r) {
-            p.push(src_id_r);
-        } else {
+        if self.is_page(dst_id_r) == false {
             //pagelink destinations can be redirects, so this is a potential code path
             //it is dealt with in self.tidy_entries to make sure the table isn't bloated
             error!(self.log, 
-                   "The dst_id_r {} (dst_id={}) given by addresses[dst=`{}`] wasn't a Page",
-                  dst_id_r, dst_id, dst_title);
-            return false;
+                   "The dst_id_r {} (dst_id={}) given by a pagelink wasn't a Page",
+                  dst_id_r, dst_id);
+            return Err(DropReason::LinkDestinationNotPage);
         }
-        true
+        // a link to a redirect to a section lands on that section
+        let section = self.section_titles.get(&*dst_title).cloned();
+        Ok(Link { src: src_id_r, dst: dst_id_r, section })
+    }
+    fn is_page(&self, id: u32) -> bool {
+        matches!(self.entries.get(&id), Some(&Entry::Page{..}))
+    }
+    fn is_template(&self, id: u32) -> bool {
+        self.templates.as_ref().is_some_and(|t| t.is_template(id))
+    }
+    /// Nothing counted yet for each page, to count their links with before they're added
+    pub fn degrees(&self) -> Degrees {
+        assert_eq!(self.state, State::AddLinks, 
+                   "Tried to count links in the `{:?}` stage", self.state);
+        let ids = self.entries.iter()
+            .filter(|&(_, e)| matches!(*e, Entry::Page{..}))
+            .map(|(&id, _)| id)
+            .collect();
+        Degrees::new(PageIndex::new(ids))
+    }
+    /// Add links to flat arrays sized from what `degrees` counted for each page, rather
+    /// than to each page's own `Vec`s, which would have to grow as they're added
+    pub fn reserve_links(&mut self, degrees: Degrees) {
+        assert_eq!(self.state, State::AddLinks, 
+                   "Tried to reserve links in the `{:?}` stage", self.state);
+        self.csr = Some(Csr::new(degrees));
+    }
+    pub fn add_link(&mut self, link: Link) {
+        self.add_section_link(&link);
+        self.push_link(link.src, link.dst);
     }
-    fn follow_redirects(&self, start_id: u32) -> Result<u32,()> {
+    /// Remember that `link` goes to a section (it's otherwise up to the caller to keep)
+    pub fn add_section_link(&mut self, link: &Link) {
+        if let Some(redirect) = link.section {
+            self.section_links.entry(redirect).or_default().push(link.src);
+        }
+    }
+    pub fn push_link(&mut self, src_id: u32, dst_id: u32) {
+        //add dst_id to entries[src_id].children and src_id to entries[dst_id].parents
+        //both should have been checked to be Pages by `resolve_link`
+        // (the order links are pushed in doesn't matter; they're sorted in `finalize`)
+        // links from templates are kept on the side (see `templates`)
+        if let Some(ref mut templates) = self.templates {
+            templates.push_link(src_id, dst_id);
+        }
+        // (which takes care of checking that they're pages)
+        if let Some(ref mut csr) = self.csr {
+            csr.push(src_id, dst_id);
+            return;
+        }
+        if let Some(&mut Entry::Page{ children: ref mut c, .. }) 
+                = self.entries.get_mut(&src_id) {
+            c.push(dst_id);                
+        } else {
+            return;
+        }
+        if let Some(&mut Entry::Page{ parents: ref mut p, .. }) 
+                = self.entries.get_mut(&dst_id) {
+            p.push(src_id);
+        }
+    }
+    fn subtract_template_links(&mut self) -> Vec<(u32,u32)> {
+        // remove each page's links to wherever its templates link to
+        // (children and parents have to be sorted already)
+        // returns the (src, dst) of each link that was removed, sorted
+        let templates = match self.templates.take() {
+            Some(t) => t,
+            None => return vec![],
+        };
+        // (src, dst) of each link that's left out
+        let mut removed: Vec<(u32,u32)> = vec![];
+        for (&id, entry) in &mut self.entries {
+            if let Entry::Page { children: ref mut c, .. } = *entry {
+                let from_templates = templates.links_of(id);
+                if from_templates.is_empty() {
+                    continue;
+                }
+                c.retain(|dst| {
+                    let keep = from_templates.binary_search(dst).is_err();
+                    if keep == false {
+                        removed.push((id, *dst));
+                    }
+                    keep
+                });
+            }
+        }
+        info!(self.log, "\tLeft out {} links that came from templates", removed.len());
+        // (sorted so the report's samples are the same from run to run)
+        removed.sort();
+        for &(src, dst) in &removed {
+            self.report.drop(DropReason::TemplateOnlyLink, &format_args!("{} → {}", src, dst));
+        }
+        // and the other half of each link
+        removed.sort_by_key(|&(src, dst)| (dst, src));
+        for group in removed.chunk_by(|a, b| a.1 == b.1) {
+            if let Some(&mut Entry::Page{ parents: ref mut p, .. }) 
+                    = self.entries.get_mut(&group[0].1) {
+                p.retain(|src| group.binary_search_by_key(src, |&(s,_)| s).is_err());
+            }
+        }
+        removed.sort();
+        removed
+    }
+    fn collect_sections(&mut self, template_links: &[(u32,u32)]) {
+        // `template_links` are the links `subtract_template_links` removed
+        // (this doesn't look at anyone's children, so it works just as well when the
+        //  links were kept somewhere else; see `update`)
+        self.section_titles = HashMap::new();
+        let section_links = ::std::mem::take(&mut self.section_links);
+        for (&id, entry) in &self.entries {
+            let (title, fragment) = match *entry {
+                Entry::Redirect { ref title, fragment: Some(ref f), .. } => (title, f),
+                _ => continue,
+            };
+            let target = match self.follow_redirects(id) {
+                Ok(target) => target,
+                Err(_) => continue,
+            };
+            // the links that are still there (see `subtract_template_links`)
+            let from = section_links.get(&id).map_or(vec![], |from| {
+                from.iter().cloned().filter(|&src| {
+                    self.is_page(src) && template_links.binary_search(&(src, target)).is_err()
+                }).collect()
+            });
+            self.sections.push(Section {
+                id,
+                title: title.clone(),
+                target,
+                fragment: fragment.clone(),
+                from,
+            });
+        }
+        info!(self.log, "\tKept {} redirects to sections", self.sections.len());
+    }
+    fn follow_redirects(&self, start_id: u32) -> Result<u32, DropReason> {
         // a pagelink can give a page_id that is a redirect as its source
         // it can also give a redirect's page title as its destination 
         // so we might need to follow a page_id through Redirects until we find a valid page
@@ -255,7 +762,7 @@ impl Database {
                 if let Some(target) = t {
                     if seen.contains(&target) {
                         //avoid loops
-                        return Err(());
+                        return Err(DropReason::RedirectLoop);
                     } else {
                         seen.insert(target);
                         cur_id = target;
@@ -264,7 +771,7 @@ impl Database {
                     error!(self.log, 
                            "Couldn't follow redirects from {}: got None-target Redirect ({})",
                            start_id, cur_id);
-                    return Err(());
+                    return Err(DropReason::RedirectDeadEnd);
                 }
             } else if let Some(&Entry::Page{..}) = entry {
                 return Ok(cur_id);
@@ -274,7 +781,7 @@ impl Database {
                 //error!(self.log,
                 //     "Couldn't follow redirects starting from {}: self.entries[{}] = None", 
                 //     start_id, cur_id);
-                return Err(());
+                return Err(DropReason::RedirectDeadEnd);
             }
         }
     }
@@ -282,38 +789,60 @@ impl Database {
         //delete any redirects in page.sql that didn't show up in redirects.sql
         // they'll be in self.entries of type Entry::Redirect { target=None }
         
-        assert_eq!(self.state, State::AddRedirects, 
+        // (or right after AddPages, for an XML export without any redirects)
+        assert!(self.state == State::AddPages || 
+                self.state == State::AddRedirects || self.state == State::AddLinkTargets,
                 "tidy_entries wasn't called after AddRedirects (but `{:?}` instead)",
                 self.state);
         self.state = State::TidyEntries;
 
-        let mut collector: HashSet<u32> = HashSet::new();
+        // take stock of the redirects before the broken ones are thrown out
+        let redirects = RedirectReport::new(&self.entries, &self.missing_targets);
+        info!(self.log, "Redirects: {} resolved ({} double), {} cycles, {} broken",
+              redirects.resolved(), redirects.double.len(), redirects.cycles.len(),
+              redirects.broken.len());
+        self.redirect_report = Some(redirects);
+        self.missing_targets = HashMap::new();
+
+        // (sorted so the report's samples are the same from run to run)
+        let mut collector: Vec<u32> = vec![];
         for (&addr,entry) in &self.entries {
             if let Entry::Redirect { target: None, .. } = *entry {
-                collector.insert(addr);
+                collector.push(addr);
             }
         }
+        collector.sort();
         info!(self.log, "Removing {} unresolved redirects from self.entries", collector.len());
         for c in collector {
-            self.entries.remove(&c);
+            if let Some(Entry::Redirect { title, .. }) = self.entries.remove(&c) {
+                self.report.drop(DropReason::UnresolvedRedirect, 
+                                 &format_args!("{} `{}`", c, title));
+            }
         }
         
         //delete any addresses that point at None-type objects
         //pretty sure this can happen if we have a chain of redirects that resolves to None
         // 5/5 random samplings show this only happening with pages from other namespaces
 
+        // remember which titles lead to sections before they lead straight to the page
+        for (&id, entry) in &self.entries {
+            if let Entry::Redirect { ref title, fragment: Some(_), .. } = *entry {
+                self.section_titles.insert(title.clone(), id);
+            }
+        }
+
         //go through the entries and make sure that addresses[redirect_title] points
         // to true_id, not redirect_id
         // we never need to retrieve the page_id of a redirect; it's just a cache miss
         
         let mut collector_update: HashMap<String,u32> = HashMap::new();
-        let mut collector_remove: HashSet<String> = HashSet::new();
+        let mut collector_remove: Vec<(String, DropReason)> = vec![];
         for (title,&addr) in &self.addresses {
             //info!(self.log, "following redirs: addresses[`{}`] = {}", title, addr);
             match self.follow_redirects(addr) {
                 Ok(a) if a == addr => {},
                 Ok(x)   => { collector_update.insert(title.to_owned(), x); },
-                Err(()) => { collector_remove.insert(title.to_owned()); },
+                Err(reason) => { collector_remove.push((title.to_owned(), reason)); },
             }
         }
         info!(self.log, 
@@ -321,8 +850,10 @@ impl Database {
               collector_update.len());
         info!(self.log, "Removing {} addresses that point to nonexistant redirects", 
               collector_remove.len());
-        for title in collector_remove {
+        collector_remove.sort_by(|a, b| a.0.cmp(&b.0));
+        for (title, reason) in collector_remove {
             self.addresses.remove(&title);
+            self.report.drop(reason, &title);
         }
         for (title,addr) in collector_update {
             self.addresses.insert(title,addr);
@@ -355,10 +886,24 @@ impl Database {
     }
     pub fn finalize(&mut self) {
         //modify the `State` so that no further modifications can be made
-        assert_eq!(self.state, State::AddLinks, 
+        assert!(self.state == State::AddLinks || self.state == State::AddTemplateLinks, 
                 "Tried to finalize in the `{:?}` stage", self.state);
         info!(self.log, "Entering the `Done` stage");
         self.state = State::Done;
+        // link targets are only needed to resolve pagelinks
+        self.link_targets = HashMap::new();
+        self.wanted_targets = HashMap::new();
+        // a low-memory build's links are moved into their pages (already sorted and deduped)
+        if let Some(csr) = self.csr.take() {
+            let entries = &mut self.entries;
+            csr.unpack(|id, children, parents| {
+                if let Some(&mut Entry::Page{ children: ref mut c, parents: ref mut p, .. }) 
+                        = entries.get_mut(&id) {
+                    *c = children;
+                    *p = parents;
+                }
+            });
+        }
         //clean up links
         for entry in self.entries.values_mut() {
             if let Entry::Page { 
@@ -377,6 +922,10 @@ impl Database {
                 p.shrink_to_fit();
             }
         }
+        // leave out links that (as far as we can tell) only come from templates
+        let template_links = self.subtract_template_links();
+        // keep the redirects to sections before they're gone
+        self.collect_sections(&template_links);
         // delete redirects in page.sql that didn't show up in redirects.sql
         //debug!(self.log, "Delete unconfirmed redirects...");
         //self.tidy_entries();
@@ -399,7 +948,11 @@ impl Database {
                asymmetric_references.len());
         self.pop_addresses(asymmetric_references);
                */
-        let empty_refs = self.incomplete_addresses();
+        let mut empty_refs = self.incomplete_addresses();
+        empty_refs.sort();
+        for title in &empty_refs {
+            self.report.drop(DropReason::IncompleteAddress, title);
+        }
         println!("Done collecting incomplete refs");
         info!(self.log, "\tDelete {} incomplete addrs (absent in Entries)...",
                empty_refs.len());
@@ -485,7 +1038,7 @@ impl Database {
                                         "Page `{}` has child {}, but child {} lacks parent {}",
                                         t, child, t_, id);
                             },
-                            Entry::Redirect { title: ref t_, target: ref x_ } => {
+                            Entry::Redirect { title: ref t_, target: ref x_, .. } => {
                                 panic!("Page `{}` pointed to child {}({}), a redir to {:?}",
                                        t, child, t_, x_);
                             }
@@ -502,14 +1055,14 @@ impl Database {
                                     "Page `{}` has parent {}, but parent {} lacks child {}",
                                         t, parent, t_, id);
                             },
-                            Entry::Redirect { title: ref t_, target: ref x_ } => {
+                            Entry::Redirect { title: ref t_, target: ref x_, .. } => {
                                 panic!("Page `{}` pointed to parent {}({}), a redir to {:?}",
                                        t, parent, t_, x_);
                             }
                         }
                     }
                 },
-                Entry::Redirect { title: ref _t, target: ref _x } => {
+                Entry::Redirect { .. } => {
                     redirects += 1;
                 }
             }
@@ -525,14 +1078,14 @@ impl Database {
 
     fn remove_redirects(&mut self) {
         //iterate through self.entries and remove redirects
-        //set self.addresses[title] to redirect dst, not src
+        // their titles already lead to their destination (see `tidy_entries`); pointing
+        //  them at their immediate target would lose double redirects along with the
+        //  redirect in the middle
  
         //create collection of redirects
         let mut redirects: Vec<u32> = vec![];
         for (&id,entry) in &self.entries {
-            if let Entry::Redirect { title: ref ti, target: ta } = *entry {
-                //change address of title to the destination
-                self.addresses.insert(ti.to_owned(), ta.unwrap());
+            if let Entry::Redirect { .. } = *entry {
                 redirects.push(id);
             }
         }
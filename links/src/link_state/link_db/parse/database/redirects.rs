@@ -0,0 +1,193 @@
+// Diagnostics about redirects (for wiki maintainers who fix broken ones)
+// Redirects are resolved and then thrown away while parsing, along with any that don't
+//  lead anywhere, so this is built from them first (in `tidy_entries`):
+//
+//      double redirects:   redirects to another redirect (which eventually reach a page)
+//      cycles:             redirects that lead back to themselves, with their members
+//      broken redirects:   redirects to a missing page, or to a redirect that doesn't
+//                          lead anywhere (a missing page or a cycle)
+//      chain lengths:      how many redirects had to be followed to reach a page
+//
+// Each is exported to its own CSV, with titles shown as on the wiki.
+
+use csv;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+
+use link_state::link_data::append_to_pathbuf;
+use link_state::namespace;
+use super::helpers::Entry;
+
+/// A redirect that goes through other redirects before reaching a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoubleRedirect {
+    pub id: u32,
+    pub title: String,
+    // the titles of the redirects after it, then the page
+    pub chain: Vec<String>,
+    pub page: u32,
+}
+
+/// Why a redirect doesn't lead to a page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Breakage {
+    // its target isn't in page.sql
+    MissingTarget,
+    // it's marked as a redirect in page.sql but has no (usable) row in redirect.sql
+    NoTarget,
+    // it leads to a redirect that's broken itself
+    DeadEnd,
+    // it leads into a cycle (that it isn't part of)
+    IntoCycle,
+}
+
+impl Breakage {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Breakage::MissingTarget => "missing_target",
+            Breakage::NoTarget      => "no_target",
+            Breakage::DeadEnd       => "dead_end",
+            Breakage::IntoCycle     => "into_cycle",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokenRedirect {
+    pub id: u32,
+    pub title: String,
+    // what it points at, if it points at anything
+    pub target: Option<String>,
+    pub reason: Breakage,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RedirectReport {
+    // (sorted by id)
+    pub double: Vec<DoubleRedirect>,
+    // members of each cycle, starting with the lowest id
+    pub cycles: Vec<Vec<(u32, String)>>,
+    pub broken: Vec<BrokenRedirect>,
+    // Redirects followed  →  Redirects
+    pub lengths: BTreeMap<usize, usize>,
+}
+
+// where following a redirect ended up
+enum Outcome {
+    // a page, after this many redirects
+    Page(usize),
+    // the redirect at this index of the chain leads back to itself
+    Cycle(usize),
+    // a redirect without a target
+    DeadEnd,
+}
+
+fn follow(entries: &HashMap<u32, Entry>, id: u32, chain: &mut Vec<u32>) -> Outcome {
+    chain.clear();
+    chain.push(id);
+    let mut cur = id;
+    loop {
+        match entries.get(&cur) {
+            Some(&Entry::Redirect{ target: Some(t), .. }) => {
+                if let Some(i) = chain.iter().position(|&c| c == t) {
+                    return Outcome::Cycle(i);
+                }
+                chain.push(t);
+                cur = t;
+            },
+            Some(&Entry::Page{..}) => return Outcome::Page(chain.len() - 1),
+            Some(&Entry::Redirect{ target: None, .. }) | None => return Outcome::DeadEnd,
+        }
+    }
+}
+
+impl RedirectReport {
+    /// Follow every redirect in `entries`
+    /// `missing` is the target (key) of each redirect whose target wasn't in page.sql
+    pub fn new(entries: &HashMap<u32, Entry>, missing: &HashMap<u32, String>)
+        -> RedirectReport
+    {
+        let title = |id: &u32| match entries.get(id) {
+            Some(&Entry::Page{ ref title, .. }) | Some(&Entry::Redirect{ ref title, .. }) =>
+                namespace::display(title).into_owned(),
+            None => String::new(),
+        };
+        let mut redirects: Vec<u32> = entries.iter()
+            .filter(|&(_, e)| matches!(*e, Entry::Redirect{..}))
+            .map(|(&id, _)| id)
+            .collect();
+        redirects.sort();
+
+        let mut report = RedirectReport::default();
+        let mut cycles: BTreeSet<Vec<u32>> = BTreeSet::new();
+        let mut chain = vec![];
+        for id in redirects {
+            let broken = |reason, target| BrokenRedirect { id, title: title(&id), target, reason };
+            match follow(entries, id, &mut chain) {
+                Outcome::Page(n) => {
+                    *report.lengths.entry(n).or_insert(0) += 1;
+                    if n > 1 {
+                        report.double.push(DoubleRedirect {
+                            id,
+                            title: title(&id),
+                            chain: chain[1..].iter().map(&title).collect(),
+                            page: chain[n],
+                        });
+                    }
+                },
+                Outcome::Cycle(0) => {
+                    // starting each cycle with its lowest id finds it only once
+                    let start = (0..chain.len()).min_by_key(|&i| chain[i]).unwrap_or(0);
+                    let mut members = chain[start..].to_vec();
+                    members.extend_from_slice(&chain[..start]);
+                    cycles.insert(members);
+                },
+                Outcome::Cycle(_) => report.broken.push(broken(Breakage::IntoCycle,
+                                                           chain.get(1).map(&title))),
+                Outcome::DeadEnd if chain.len() > 1 => report.broken.push(broken(Breakage::DeadEnd,
+                                                                     Some(title(&chain[1])))),
+                Outcome::DeadEnd => report.broken.push(match missing.get(&id) {
+                    Some(target) =>
+                        broken(Breakage::MissingTarget, Some(namespace::display(target).into())),
+                    None => broken(Breakage::NoTarget, None),
+                }),
+            }
+        }
+        report.cycles = cycles.into_iter()
+            .map(|c| c.iter().map(|id| (*id, title(id))).collect())
+            .collect();
+        report
+    }
+    /// Number of redirects that reach a page
+    pub fn resolved(&self) -> usize {
+        self.lengths.values().sum()
+    }
+    /// Write each part next to `dst`, e.g. `links_double_redirects.csv` for `links.json`
+    pub fn export(&self, dst: &PathBuf) -> Result<(), csv::Error> {
+        // (id, title, redirects followed, chain, page)
+        let mut w = csv::Writer::from_file(append_to_pathbuf(dst, "_double_redirects", "csv"))?;
+        for d in &self.double {
+            w.encode((d.id, &d.title, d.chain.len(), d.chain.join(" → "), d.page))?;
+        }
+        // (cycle, length, members)
+        let mut w = csv::Writer::from_file(append_to_pathbuf(dst, "_redirect_cycles", "csv"))?;
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            let members: Vec<String> = cycle.iter()
+                .map(|&(id, ref title)| format!("{} {}", id, title))
+                .collect();
+            w.encode((i, cycle.len(), members.join(" → ")))?;
+        }
+        // (id, title, reason, target)
+        let mut w = csv::Writer::from_file(append_to_pathbuf(dst, "_broken_redirects", "csv"))?;
+        for b in &self.broken {
+            w.encode((b.id, &b.title, b.reason.name(), b.target.as_ref().map_or("", |t| t)))?;
+        }
+        // (redirects followed, redirects)
+        let mut w = csv::Writer::from_file(append_to_pathbuf(dst, "_redirect_chains", "csv"))?;
+        for (length, count) in &self.lengths {
+            w.encode((length, count))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+// Links that come from templates (from templatelinks.sql)
+// A template's links (navboxes, infoboxes, etc.) show up in pagelinks.sql for every page
+//  that transcludes it, with nothing to tell them apart from the links written in the
+//  page itself. So each template's own links are collected along with which templates
+//  each page transcludes, and a page's link is taken to come from a template if one of
+//  its templates links to the same place.
+// There's no telling whether the article *also* links there itself, so those links
+//  are left out as well.
+
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct Templates {
+    // Title    →  Template (page id)
+    ids: HashMap<String, u32>,
+    // Target   →  Template
+    targets: HashMap<u32, u32>,
+    // Template →  Destinations of its links
+    links: HashMap<u32, Vec<u32>>,
+    // Page     →  Templates it transcludes
+    transclusions: HashMap<u32, Vec<u32>>,
+}
+
+impl Templates {
+    pub fn add_template(&mut self, id: u32, title: &str) {
+        self.ids.insert(String::from(title), id);
+        self.links.insert(id, vec![]);
+    }
+    pub fn add_target(&mut self, target: u32, title: &str) {
+        if let Some(&id) = self.ids.get(title) {
+            self.targets.insert(target, id);
+        }
+    }
+    pub fn by_title(&self, title: &str) -> Option<u32> {
+        self.ids.get(title).cloned()
+    }
+    pub fn by_target(&self, target: u32) -> Option<u32> {
+        self.targets.get(&target).cloned()
+    }
+    pub fn is_template(&self, id: u32) -> bool {
+        self.links.contains_key(&id)
+    }
+    pub fn push_link(&mut self, src_id: u32, dst_id: u32) {
+        if let Some(links) = self.links.get_mut(&src_id) {
+            links.push(dst_id);
+        }
+    }
+    pub fn transclude(&mut self, page_id: u32, template_id: u32) {
+        self.transclusions.entry(page_id).or_default().push(template_id);
+    }
+    /// Where `page_id`'s templates link to (sorted)
+    pub fn links_of(&self, page_id: u32) -> Vec<u32> {
+        let mut links: Vec<u32> = self.transclusions.get(&page_id).map_or(vec![], |ts| {
+            ts.iter().filter_map(|t| self.links.get(t)).flat_map(|l| l.iter().cloned())
+                .collect()
+        });
+        links.sort();
+        links.dedup();
+        links
+    }
+}
@@ -0,0 +1,105 @@
+// Errors encountered while reading the sql dumps
+// Every error carries where in which dump it happened, so a bad dump can be inspected
+//  (and skipped) without having to re-run the parse under a debugger.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::tokenizer::TokenError;
+
+/// What went wrong
+#[derive(Debug)]
+pub enum ErrorKind {
+    Io(io::Error),
+    /// The dump's data began (or it ended) before its `CREATE TABLE` header
+    MissingHeader,
+    /// The dump's table doesn't have a column we need
+    MissingColumn { table: String, column: String },
+    /// An `INSERT` statement couldn't be tokenized
+    Expected(&'static str),
+    /// A tuple's value didn't have the type we need (or the tuple was too short)
+    BadValue { column: usize, expected: &'static str },
+    /// The dump refers to its destinations by linktarget id, but there's no linktarget.sql
+    MissingLinkTargets { table: String },
+}
+
+/// What went wrong, and where
+#[derive(Debug)]
+pub struct ParseError {
+    pub file: PathBuf,
+    /// 1-based line in the (decompressed) dump; 0 if no line had been read
+    pub line: u64,
+    /// Byte offset into the (decompressed) dump
+    pub offset: u64,
+    pub kind: ErrorKind,
+}
+
+/// Where a reader is in a dump: the line last read and the offset of the next one
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Position {
+    pub line: u64,
+    pub offset: u64,
+}
+
+impl ParseError {
+    pub fn new(file: &Path, pos: Position, kind: ErrorKind) -> ParseError {
+        ParseError {
+            file: file.to_path_buf(),
+            line: pos.line,
+            offset: pos.offset,
+            kind,
+        }
+    }
+    /// An error that didn't happen at any particular point in the dump (e.g. opening it)
+    pub fn at_start(file: &Path, kind: ErrorKind) -> ParseError {
+        ParseError::new(file, Position::default(), kind)
+    }
+}
+
+impl From<io::Error> for ErrorKind {
+    fn from(e: io::Error) -> ErrorKind {
+        ErrorKind::Io(e)
+    }
+}
+
+impl ParseError {
+    pub fn from_token(file: &Path, e: TokenError) -> ParseError {
+        ParseError::new(file, e.position, e.kind)
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorKind::Io(ref e) => write!(f, "{}", e),
+            ErrorKind::MissingHeader =>
+                write!(f, "reached the data before finding a `CREATE TABLE` header"),
+            ErrorKind::MissingColumn { ref table, ref column } =>
+                write!(f, "the `{}` table has no `{}` column", table, column),
+            ErrorKind::Expected(expected) => write!(f, "expected {}", expected),
+            ErrorKind::BadValue { column, expected } =>
+                write!(f, "expected {} in column {} of the tuple", expected, column),
+            ErrorKind::MissingLinkTargets { ref table } =>
+                write!(f, "the `{}` table refers to link targets, \
+                           but no linktarget.sql was given", table),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{} (byte {}): {}",
+               self.file.display(), self.line, self.offset, self.kind)
+    }
+}
+
+impl error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.kind {
+            ErrorKind::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
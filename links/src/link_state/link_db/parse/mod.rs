@@ -1,95 +1,408 @@
-extern crate regex;
-
 use slog;
-use std::io::{BufRead, BufReader};
-use std::fs::File;
-use std::borrow::Cow;
+use std::io::{self, BufRead};
 use std::path::Path;
+use std::thread;
 
-mod regexes;
+pub mod error;
+pub mod report;
+pub mod reader;
+pub mod schema;
+pub mod tokenizer;
+pub mod tables;
 pub mod database;
 use self::database::*;
+pub use self::error::{ParseError, ErrorKind, Position};
+pub use self::report::{ParseReport, DropReason};
+use self::schema::SchemaReader;
+use self::tables::{Table, Pages, Redirects, LinkTargets, PageLinks, PageLinkColumns, 
+                   TemplateLinks, CategoryLinks, LangLinks, PageProps};
+use self::tokenizer::{Tokenizer, Tuple};
+use link_state::namespace::Namespaces;
+use super::Dumps;
+use super::memory;
 
 
 // Parsing Note:
 //  Most of the [u8] -> &str conversions involve potential errors in which the source
 //  might not be valid u8. This is not common but the case must be handled.
-//  I'm not sure how utf-16 works with rust regexes, and it might mean lots more memory.
 //  We handle this with String::from_utf8_lossy(), which replaces bad utf8 with '�'
 //  It also returns a Cow, which suits our purposes well.
+//  Only the fields that are actually used (i.e. titles) are converted.
 //
 
-// bytes in the buffer for reading one line at a time
-// problems may arise if this buffer fills up all the way: some data will not be read
-// to be safe, it is ~20% larger than the longest line in a dump ( 1,025,987 - 1,039,069 )
-const BUFFER_SIZE: usize = 1_250_000;
+// number of tuples each thread parses between merging its links into the db
+// (bounds the memory spent holding resolved-but-unmerged links)
+const TUPLES_PER_ROUND: u64 = 200_000;
+
 
+pub fn populate_db(dumps: &Dumps,
+                   namespaces: Namespaces,
+                   threads: usize,
+                   log: slog::Logger) -> Result<(Database, ParseReport), ParseError> {
+    // linktarget.sql is only present for newer dumps, in which pagelinks.sql refers to
+    //  its destinations by an id into linktarget.sql instead of by title
+    //  (a dump laid out that way is an error without it; see `read_columns`)
+    // pagelinks.sql is by far the largest, so it's split across `threads` if possible
+    // only pages (and links between pages) in `namespaces` are kept
+    // templatelinks.sql is optional; if it's given, links that only come from templates
+    //  are left out (see `database::templates`)
+    // categorylinks.sql is optional; its categories are taken with `take_categories`
+    // langlinks.sql is optional too; its langlinks are taken with `take_langlinks`
+    // page_props.sql is optional; it flags disambiguation pages in their entries
+    // links to missing pages are only kept if `dumps.wanted`; see `take_wanted`
+    // likewise page.sql's metadata columns with `dumps.metadata`; see `take_metadata`
+    // with `dumps.low_memory`, pagelinks.sql is read twice: once to count each page's 
+    //  links and again to add them to flat arrays sized from the counts (see `csr`)
 
-pub fn populate_db(page_sql:   &Path,
-                   redirs_sql: &Path,
-                   links_sql:  &Path,
-                   log: slog::Logger) -> Database {
+    let links_sql = dumps.pagelinks.as_path();
+    let mut db = populate_pages(dumps, namespaces, log)?;
+    // whether links are given by title or by target is decided by the dump's schema
+    let compression = reader::Compression::detect(links_sql)
+        .map_err(|e| ParseError::at_start(links_sql, e.into()))?;
+    let parallel = threads > 1 && compression == reader::Compression::Plain;
+    if dumps.low_memory {
+        let degrees = if parallel {
+            let mut degrees = db.degrees();
+            parse_pagelinks_parallel(links_sql, &mut db, threads, Some(&mut degrees))?;
+            degrees
+        } else {
+            count_pagelinks(links_sql, &db)?
+        };
+        db.reserve_links(degrees);
+        log_memory(&db, "counting pagelinks");
+    }
+    let links = if parallel {
+        parse_pagelinks_parallel(links_sql, &mut db, threads, None)?
+    } else {
+        parse_generic::<PageLinks>(links_sql, &mut db)?
+    };
+    db.log(format!("Number of pagelinks: {} / {}", links.0, links.1));
+    db.report().rows("pagelinks", links.0, links.1);
+    log_memory(&db, "pagelinks.sql");
+    if let Some(ref templates_sql) = dumps.templatelinks {
+        let templates = parse_generic::<TemplateLinks>(templates_sql, &mut db)?;
+        db.log(format!("Number of template links: {} / {}", templates.0, templates.1));
+        db.report().rows("templatelinks", templates.0, templates.1);
+    }
+    let report = finish_db(dumps, &mut db)?;
+    Ok((db, report))
+}
 
-    let mut db = Database::new(log);
-    let pages = parse_generic(page_sql,
-                              &regexes::pages_regex(),
-                              &mut db,
-                              |db: &mut Database, data: regex::Captures| {
-                                  db.add_page(&data)
-                              });
+/// Parse page.sql and redirect.sql (and linktarget.sql) into a db that's ready to
+/// resolve links
+pub fn populate_pages(dumps: &Dumps, namespaces: Namespaces, log: slog::Logger)
+    -> Result<Database, ParseError>
+{
+    let (page_sql, redirs_sql) = (dumps.page.as_path(), dumps.redirect.as_path());
+    let mut db = Database::new(log, namespaces);
+    if dumps.templatelinks.is_some() {
+        db.collect_templates();
+    }
+    if dumps.categorylinks.is_some() {
+        db.collect_categories();
+    }
+    if dumps.langlinks.is_some() {
+        db.collect_langlinks();
+    }
+    if dumps.page_props.is_some() {
+        db.collect_disambiguations();
+    }
+    if dumps.linktarget.is_some() {
+        db.collect_linktargets();
+    }
+    if dumps.wanted {
+        db.collect_wanted();
+    }
+    if dumps.metadata {
+        db.collect_metadata();
+    }
+    let pages = parse_generic::<Pages>(page_sql, &mut db)?;
     db.log(format!("Number of page entries: {} / {}", pages.0, pages.1));
-    let redirs = parse_generic(redirs_sql,
-                              &regexes::redirect_regex(),
-                              &mut db,
-                              |db: &mut Database, data: regex::Captures| {
-                                  db.add_redirect(&data)
-                              });
+    db.report().rows("page", pages.0, pages.1);
+    log_memory(&db, "page.sql");
+    let redirs = parse_generic::<Redirects>(redirs_sql, &mut db)?;
     db.log(format!("Number of redirects: {} / {}", redirs.0, redirs.1));
-    let links = parse_generic(links_sql,
-                              &regexes::pagelinks_regex(),
-                              &mut db,
-                              |db: &mut Database, data: regex::Captures| {
-                                  db.add_pagelink(&data)
-                              });
-    db.log(format!("Number of pagelinks: {} / {}", links.0, links.1));
+    db.report().rows("redirect", redirs.0, redirs.1);
+    log_memory(&db, "redirect.sql");
+    if let Some(ref targets_sql) = dumps.linktarget {
+        let targets = parse_generic::<LinkTargets>(targets_sql, &mut db)?;
+        db.log(format!("Number of link targets: {} / {}", targets.0, targets.1));
+        db.report().rows("linktarget", targets.0, targets.1);
+    }
+    db.begin_links();
+    Ok(db)
+}
+
+/// Resolve each of pagelinks.sql's links with `db`, but hand them to `action` instead 
+/// of adding them (dropped links and links to sections are still recorded in the db)
+pub fn resolve_pagelinks<F>(filename: &Path, db: &mut Database, mut action: F) 
+    -> Result<(u64,u64), ParseError>
+    where F: FnMut(Link)
+{
+    let mut reader = reader::open_dump(filename)
+        .map_err(|e| ParseError::at_start(filename, e.into()))?;
+    let (columns, pos) = read_columns::<PageLinks>(filename, &mut reader, db)?;
+    let mut tokens = Tokenizer::new(reader, pos);
+    let mut success = 0u64;
+    let mut attempts = 0u64;
+    for_each_tuple(filename, &mut tokens, None, |tuple| {
+        match PageLinks::row(db, &columns, tuple)? {
+            Some(row) => {
+                match db.resolve_link(&row) {
+                    Ok(link) => {
+                        db.add_section_link(&link);
+                        action(link);
+                        success += 1;
+                    },
+                    Err(reason) => db.drop_link(reason, &row),
+                }
+                attempts += 1;
+            },
+            None => PageLinks::other_namespace(db.report(), &columns, tuple),
+        }
+        Ok(())
+    })?;
+    db.log(format!("Number of pagelinks: {} / {}", success, attempts));
+    db.report().rows("pagelinks", success, attempts);
+    log_memory(db, "pagelinks.sql");
+    Ok((success, attempts))
+}
+
+/// Finalize a db whose links have all been added, then add the tables that aren't part 
+/// of the link graph; returns what was dropped along the way
+pub fn finish_db(dumps: &Dumps, db: &mut Database) -> Result<ParseReport, ParseError> {
     db.finalize();
-    db
+    log_memory(db, "finalizing");
+    if let Some(ref categories_sql) = dumps.categorylinks {
+        let categories = parse_generic::<CategoryLinks>(categories_sql, db)?;
+        db.log(format!("Number of category links: {} / {}", categories.0, categories.1));
+        db.report().rows("categorylinks", categories.0, categories.1);
+    }
+    if let Some(ref langlinks_sql) = dumps.langlinks {
+        let langlinks = parse_generic::<LangLinks>(langlinks_sql, db)?;
+        db.log(format!("Number of langlinks: {} / {}", langlinks.0, langlinks.1));
+        db.report().rows("langlinks", langlinks.0, langlinks.1);
+    }
+    if let Some(ref props_sql) = dumps.page_props {
+        let props = parse_generic::<PageProps>(props_sql, db)?;
+        db.log(format!("Number of disambiguation pages: {} / {}", props.0, props.1));
+        db.report().rows("page_props", props.0, props.1);
+    }
+    Ok(db.take_report())
+}
+
+fn log_memory(db: &Database, stage: &str) {
+    db.log(format!("Memory after {}: {} (peak {})", stage, 
+                   memory::display(memory::current()), memory::display(memory::peak())));
 }
 
-fn parse_generic<F>(filename: &Path, re: &str, db: &mut Database, action: F) -> (u64,u64)
-    where F: Fn(&mut Database, regex::Captures) -> bool 
+fn read_columns<T: Table>(filename: &Path, reader: &mut dyn BufRead, db: &Database) 
+    -> Result<(T::Columns, Position), ParseError>
 {
-    // parse a mysql dump from a custom regex
-    // use a closure to define how the database uses the results
+    // read the dump's header to find where the columns `T` needs are
+    // also returns where the header ended, so the data's position can be tracked
+    let mut schema = SchemaReader::default();
+    let mut buffer = Vec::<u8>::new();
+    let mut pos = Position::default();
+    loop {
+        buffer.clear();
+        let len = reader.read_until(b'\n', &mut buffer)
+            .map_err(|e| ParseError::new(filename, pos, e.into()))?;
+        pos.line += 1;
+        if len == 0 || buffer.starts_with(b"INSERT INTO") {
+            return Err(ParseError::new(filename, pos, ErrorKind::MissingHeader));
+        } else if let Some(sc) = schema.feed(&String::from_utf8_lossy(&buffer)) {
+            db.log(format!("Found `{}` columns: {:?}", sc.table, sc.columns()));
+            let columns = T::columns(&sc).map_err(|e| ParseError::new(filename, pos, e))?;
+            // every row would be dropped, so don't bother
+            if T::by_target(&columns) && db.uses_linktargets() == false {
+                let table = sc.table.clone();
+                return Err(ParseError::new(filename, pos, 
+                                           ErrorKind::MissingLinkTargets { table }));
+            }
+            pos.offset += len as u64;
+            return Ok((columns, pos));
+        }
+        pos.offset += len as u64;
+    }
+}
+
+fn for_each_tuple<R, F>(filename: &Path, tokens: &mut Tokenizer<R>, max_tuples: Option<u64>, 
+                        mut action: F) -> Result<bool, ParseError>
+    where R: BufRead, F: FnMut(&Tuple) -> Result<(), ErrorKind>
+{
+    // call `action` on every tuple of every `INSERT` statement in the dump
+    // if `max_tuples` is given, stop after that many; returns whether we're at eof
+    let mut count = 0;
+    while max_tuples != Some(count) {
+        match tokens.next_tuple().map_err(|e| ParseError::from_token(filename, e))? {
+            // errors point at the start of the offending tuple
+            Some(tuple) => action(&tuple)
+                .map_err(|e| ParseError::new(filename, tuple.position(), e))?,
+            None => return Ok(true),
+        }
+        count += 1;
+    }
+    Ok(false)
+}
+
+fn parse_generic<T: Table>(filename: &Path, db: &mut Database) -> Result<(u64,u64), ParseError> {
+    // parse a mysql dump one tuple at a time
+    // the columns `T` needs are found from the table's schema in the dump's header
+    // dumps may be plain text or gzip/bzip2 compressed; see `reader`
 
     println!("Opening `{:?}`", filename);
-    let f = File::open(filename).unwrap();
-    let mut reader = BufReader::new(f);
-    let re = regex::Regex::new(re).unwrap();
-    let mut buffer = Vec::<u8>::with_capacity(BUFFER_SIZE);
+    let mut reader = reader::open_dump(filename)
+        .map_err(|e| ParseError::at_start(filename, e.into()))?;
+    let (columns, pos) = read_columns::<T>(filename, &mut reader, db)?;
+    let mut tokens = Tokenizer::new(reader, pos);
     let mut success = 0u64;
     let mut attempts = 0u64;
 
-    loop {
-        let len = reader.read_until(b'\n', &mut buffer).unwrap();
-        if len == 0 {
-            // done (empty line has a length of 1)
-            break;
-        } else if len == BUFFER_SIZE {
-            // crash/complain w/ error message
-            panic!(format!("Input db line was longer than your buffer size; increase \
-                            BUFFER_SIZE to at least {}.",
-                           len));
-        } else {
-            let s: Cow<str> = String::from_utf8_lossy(&buffer);
-            for entry in re.captures_iter(&s) {
-                if action(db, entry) {
-                    success += 1;
+    for_each_tuple(filename, &mut tokens, None, |tuple| {
+        if let Some(added) = T::add(db, &columns, tuple)? {
+            if added {
+                success += 1;
+            }
+            attempts += 1;
+        }
+        Ok(())
+    })?;
+    Ok((success,attempts))
+}
+
+fn count_pagelinks(filename: &Path, db: &Database) -> Result<Degrees, ParseError> {
+    // the first pass of a low-memory build: resolve each link, but only count it
+    // (nothing is recorded in the report; that's left to the second pass)
+    let mut reader = reader::open_dump(filename)
+        .map_err(|e| ParseError::at_start(filename, e.into()))?;
+    let (columns, pos) = read_columns::<PageLinks>(filename, &mut reader, db)?;
+    let mut tokens = Tokenizer::new(reader, pos);
+    let mut degrees = db.degrees();
+    for_each_tuple(filename, &mut tokens, None, |tuple| {
+        if let Some(row) = PageLinks::row(db, &columns, tuple)? {
+            if let Ok(link) = db.resolve_link(&row) {
+                degrees.count(&link);
+            }
+        }
+        Ok(())
+    })?;
+    Ok(degrees)
+}
+
+fn parse_pagelinks_parallel(filename: &Path, db: &mut Database, threads: usize,
+                            mut degrees: Option<&mut Degrees>) 
+    -> Result<(u64,u64), ParseError> 
+{
+    // split pagelinks.sql into line-aligned chunks and parse each on its own thread
+    // resolving a link only reads from the db, so threads resolve links in rounds of
+    //  `TUPLES_PER_ROUND` tuples, after which their links are merged into the db.
+    // links are sorted and deduped in `finalize`, so the result is the same as parsing
+    //  serially regardless of how the rounds are interleaved.
+    // if `degrees` is given, links are only counted (see `count_pagelinks`)
+
+    println!("Opening `{:?}` in {} chunks", filename, threads);
+    let at_start = |e: io::Error| ParseError::at_start(filename, e.into());
+    let columns = {
+        let mut reader = reader::open_dump(filename).map_err(&at_start)?;
+        read_columns::<PageLinks>(filename, &mut reader, db)?.0
+    };
+
+    struct Chunk<R: BufRead> {
+        // lines are counted from the start of the chunk until there's an error
+        tokens: Tokenizer<R>,
+        start: u64,
+        done: bool,
+        links: Vec<Link>,
+        // links to missing pages: (Title, Source)
+        wanted: Vec<(String, u32)>,
+        report: ParseReport,
+        success: u64,
+        attempts: u64,
+        error: Option<ParseError>,
+    }
+    let mut chunks = Vec::with_capacity(threads);
+    for range in reader::line_aligned_chunks(filename, threads).map_err(&at_start)? {
+        chunks.push(Chunk {
+            tokens: Tokenizer::new(reader::open_range(filename, &range).map_err(&at_start)?,
+                                   Position { line: 0, offset: range.start }),
+            start: range.start,
+            done: false,
+            links: vec![],
+            wanted: vec![],
+            report: ParseReport::new(),
+            success: 0,
+            attempts: 0,
+            error: None,
+        });
+    }
+
+    while chunks.iter().any(|c| c.done == false) {
+        {
+            let db: &Database = db;
+            let columns: &PageLinkColumns = &columns;
+            thread::scope(|s| {
+                for chunk in chunks.iter_mut().filter(|c| c.done == false) {
+                    s.spawn(move || {
+                        let Chunk { ref mut tokens, ref mut links, ref mut wanted,
+                                    ref mut report, ref mut success, ref mut attempts, 
+                                    .. } = *chunk;
+                        let res = for_each_tuple(filename, tokens, Some(TUPLES_PER_ROUND), 
+                                                 |tuple| {
+                            match PageLinks::row(db, columns, tuple)? {
+                                Some(row) => {
+                                    match db.resolve_link(&row) {
+                                        Ok(link) => {
+                                            links.push(link);
+                                            *success += 1;
+                                        },
+                                        Err(reason) => {
+                                            wanted.extend(db.wanted_link(reason, &row));
+                                            report.drop(reason, &row);
+                                        },
+                                    }
+                                    *attempts += 1;
+                                },
+                                None => PageLinks::other_namespace(report, columns, tuple),
+                            }
+                            Ok(())
+                        });
+                        match res {
+                            Ok(done) => chunk.done = done,
+                            Err(e) => chunk.error = Some(e),
+                        }
+                    });
                 }
-                attempts += 1;
+            });
+        }
+        // report the error from the earliest chunk, with its line counted from the top
+        if let Some((start, mut e)) = chunks.iter_mut()
+                .filter_map(|c| c.error.take().map(|e| (c.start, e))).next() {
+            e.line += reader::count_lines(filename, start).map_err(&at_start)?;
+            return Err(e);
+        }
+        // merge in chunk order so the db (and report) is always built the same way
+        for chunk in &mut chunks {
+            let report = ::std::mem::replace(&mut chunk.report, ParseReport::new());
+            match degrees {
+                Some(ref mut degrees) => {
+                    for link in &chunk.links {
+                        degrees.count(link);
+                    }
+                    chunk.wanted.clear();
+                },
+                None => {
+                    for &link in &chunk.links {
+                        db.add_link(link);
+                    }
+                    for (title, src) in chunk.wanted.drain(..) {
+                        db.add_wanted(title, src);
+                    }
+                    db.report().merge(report);
+                },
             }
+            chunk.links.clear();
         }
-        buffer.clear();
     }
-    (success,attempts)
+    Ok(chunks.iter().fold((0,0), |(s,a), c| (s + c.success, a + c.attempts)))
 }
@@ -0,0 +1,139 @@
+// Open sql dumps whether or not they've been decompressed
+// Wikimedia publishes dumps as `.sql.gz` (and xml as `.bz2`); unzipping enwiki's
+//  pagelinks alone takes tens of GB of scratch space, so instead we decompress on the fly.
+// The compression is identified by magic bytes where possible, falling back on the
+//  file extension (e.g. for empty or truncated files)
+
+use flate2::read::MultiGzDecoder;
+use bzip2::read::MultiBzDecoder;
+
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::fs::File;
+use std::ops::Range;
+use std::path::Path;
+
+// large reads amortize the cost of the decompressors' bookkeeping
+const READ_BUFFER: usize = 1 << 20;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Plain,
+    Gzip,
+    Bzip2,
+}
+
+impl Compression {
+    fn from_magic(head: &[u8]) -> Option<Compression> {
+        if head.starts_with(GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if head.starts_with(BZIP2_MAGIC) {
+            Some(Compression::Bzip2)
+        } else {
+            None
+        }
+    }
+    fn from_extension(path: &Path) -> Compression {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("bz2") => Compression::Bzip2,
+            _ => Compression::Plain,
+        }
+    }
+    pub fn detect(path: &Path) -> io::Result<Compression> {
+        // peek at the first few bytes without consuming them
+        let mut head = [0u8; 3];
+        let mut f = File::open(path)?;
+        let mut len = 0;
+        while len < head.len() {
+            match f.read(&mut head[len..])? {
+                0 => break,
+                n => len += n,
+            }
+        }
+        Ok(Compression::from_magic(&head[..len])
+           .unwrap_or_else(|| Compression::from_extension(path)))
+    }
+}
+
+/// Open a dump for buffered reading, transparently decompressing `.gz` and `.bz2` files
+pub fn open_dump(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let compression = Compression::detect(path)?;
+    let f = File::open(path)?;
+    Ok(match compression {
+        Compression::Plain => 
+            Box::new(BufReader::with_capacity(READ_BUFFER, f)),
+        Compression::Gzip => 
+            Box::new(BufReader::with_capacity(READ_BUFFER, MultiGzDecoder::new(f))),
+        Compression::Bzip2 => 
+            Box::new(BufReader::with_capacity(READ_BUFFER, MultiBzDecoder::new(f))),
+    })
+}
+
+/// Split an uncompressed dump into `n` byte ranges which each begin at the start of a line
+/// (compressed dumps can't be seeked into, so they have to be read from the beginning)
+pub fn line_aligned_chunks(path: &Path, n: usize) -> io::Result<Vec<Range<u64>>> {
+    let len = File::open(path)?.metadata()?.len();
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut bounds = vec![0];
+    for i in 1..n as u64 {
+        let guess = len * i / n as u64;
+        if guess <= *bounds.last().unwrap() {
+            // the previous line ran past this guess
+            continue;
+        }
+        // move forward to the beginning of the next line
+        reader.seek(SeekFrom::Start(guess - 1))?;
+        let skipped = skip_line(&mut reader)?;
+        if guess - 1 + skipped < len {
+            bounds.push(guess - 1 + skipped);
+        }
+    }
+    bounds.push(len);
+    Ok(bounds.windows(2).map(|w| w[0] .. w[1]).collect())
+}
+
+fn skip_line<R: BufRead>(reader: &mut R) -> io::Result<u64> {
+    // like `read_until` but without keeping the (possibly enormous) line around
+    let mut skipped = 0;
+    loop {
+        let (len, done) = {
+            let buf = reader.fill_buf()?;
+            match buf.iter().position(|&b| b == b'\n') {
+                Some(i) => (i + 1, true),
+                None => (buf.len(), buf.is_empty()),
+            }
+        };
+        reader.consume(len);
+        skipped += len as u64;
+        if done {
+            return Ok(skipped);
+        }
+    }
+}
+
+/// Open an uncompressed dump for reading only the bytes in `range`
+pub fn open_range(path: &Path, range: &Range<u64>) -> io::Result<BufReader<io::Take<File>>> {
+    let mut f = File::open(path)?;
+    f.seek(SeekFrom::Start(range.start))?;
+    Ok(BufReader::with_capacity(READ_BUFFER, f.take(range.end - range.start)))
+}
+
+/// Number of lines in the first `len` bytes of an uncompressed dump
+pub fn count_lines(path: &Path, len: u64) -> io::Result<u64> {
+    let mut reader = BufReader::with_capacity(READ_BUFFER, File::open(path)?.take(len));
+    let mut count = 0;
+    loop {
+        let n = {
+            let buf = reader.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(count);
+            }
+            count += buf.iter().filter(|&&b| b == b'\n').count() as u64;
+            buf.len()
+        };
+        reader.consume(n);
+    }
+}
@@ -0,0 +1,145 @@
+// What was dropped while building the database, and why
+// Most of the reasons a row or address is dropped are expected (other namespaces, deleted
+//  pages, etc.), but a sudden change in how many are dropped between two monthly dumps
+//  usually means something changed in the dumps' format. So rather than logging each
+//  one, they're counted per reason, along with the first few offenders as examples.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+// number of examples kept for each reason
+const MAX_SAMPLES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    // rows outside of the main namespace
+    OtherNamespace,
+    // page.sql gave a page_id that was already taken
+    DuplicatePage,
+    // redirect.sql gave a source that wasn't in page.sql
+    RedirectMissingSource,
+    // redirect.sql gave a source that wasn't marked as a redirect in page.sql
+    RedirectSourceNotRedirect,
+    // redirect.sql gave a destination title that wasn't in page.sql
+    RedirectUnknownTarget,
+    // linktarget.sql gave a title that wasn't in page.sql
+    LinkTargetUnknownTitle,
+    // pagelinks.sql gave a destination title that wasn't in page.sql
+    LinkUnknownTitle,
+    // pagelinks.sql gave a linktarget id that wasn't in linktarget.sql (or wasn't a page)
+    LinkUnknownTarget,
+    // pagelinks.sql gave a source that wasn't a page (even after following redirects)
+    LinkMissingSource,
+    // a link's destination wasn't a page (even after following redirects)
+    LinkDestinationNotPage,
+    // page.sql marked a page as a redirect but redirect.sql didn't say where it went
+    UnresolvedRedirect,
+    // a title led to a chain of redirects that looped back on itself
+    RedirectLoop,
+    // a title led to a chain of redirects that didn't end at a page
+    RedirectDeadEnd,
+    // a title pointed at an id with no entry once redirects were removed
+    IncompleteAddress,
+    // templatelinks.sql gave a source that wasn't a page
+    TemplateLinkMissingSource,
+    // templatelinks.sql gave a template that wasn't in page.sql (or linktarget.sql)
+    TemplateLinkUnknownTemplate,
+    // a link was left out because one of its source's templates also links there
+    TemplateOnlyLink,
+    // categorylinks.sql gave a member that wasn't a page (or was a redirect)
+    CategoryMemberNotPage,
+    // categorylinks.sql gave a linktarget id that wasn't a category in linktarget.sql
+    CategoryUnknownTarget,
+    // langlinks.sql gave a source that wasn't a page (or was a redirect)
+    LangLinkMissingSource,
+    // page_props.sql gave a page that wasn't a page (or was a redirect)
+    PagePropMissingPage,
+}
+
+impl DropReason {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            DropReason::OtherNamespace              => "other_namespace",
+            DropReason::DuplicatePage               => "duplicate_page",
+            DropReason::RedirectMissingSource       => "redirect_missing_source",
+            DropReason::RedirectSourceNotRedirect   => "redirect_source_not_redirect",
+            DropReason::RedirectUnknownTarget       => "redirect_unknown_target",
+            DropReason::LinkTargetUnknownTitle      => "linktarget_unknown_title",
+            DropReason::LinkUnknownTitle            => "link_unknown_title",
+            DropReason::LinkUnknownTarget           => "link_unknown_target",
+            DropReason::LinkMissingSource           => "link_missing_source",
+            DropReason::LinkDestinationNotPage      => "link_destination_not_page",
+            DropReason::UnresolvedRedirect          => "unresolved_redirect",
+            DropReason::RedirectLoop                => "redirect_loop",
+            DropReason::RedirectDeadEnd             => "redirect_dead_end",
+            DropReason::IncompleteAddress           => "incomplete_address",
+            DropReason::TemplateLinkMissingSource   => "templatelink_missing_source",
+            DropReason::TemplateLinkUnknownTemplate => "templatelink_unknown_template",
+            DropReason::TemplateOnlyLink            => "template_only_link",
+            DropReason::CategoryMemberNotPage       => "category_member_not_page",
+            DropReason::CategoryUnknownTarget       => "category_unknown_target",
+            DropReason::LangLinkMissingSource       => "langlink_missing_source",
+            DropReason::PagePropMissingPage         => "page_prop_missing_page",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Drops {
+    pub count: u64,
+    pub samples: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Rows {
+    pub added: u64,
+    pub attempted: u64,
+}
+
+/// Per-table row counts and per-reason drop counts (with examples) from one parse
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParseReport {
+    // keyed by table name
+    pub rows: BTreeMap<String, Rows>,
+    // keyed by `DropReason::name`
+    pub drops: BTreeMap<String, Drops>,
+}
+
+impl ParseReport {
+    pub fn new() -> ParseReport {
+        ParseReport::default()
+    }
+    /// Record that something was dropped; `sample` is only formatted if it'll be kept
+    pub fn drop<T: Display + ?Sized>(&mut self, reason: DropReason, sample: &T) {
+        let name = reason.name();
+        if self.drops.contains_key(name) == false {
+            self.drops.insert(String::from(name), Drops::default());
+        }
+        let drops = self.drops.get_mut(name).unwrap();
+        drops.count += 1;
+        if drops.samples.len() < MAX_SAMPLES {
+            drops.samples.push(sample.to_string());
+        }
+    }
+    pub fn rows(&mut self, table: &str, added: u64, attempted: u64) {
+        let rows = self.rows.entry(String::from(table)).or_default();
+        rows.added += added;
+        rows.attempted += attempted;
+    }
+    /// Number of things dropped for `reason`
+    pub fn count(&self, reason: DropReason) -> u64 {
+        self.drops.get(reason.name()).map_or(0, |d| d.count)
+    }
+    /// Fold in a report from another part of the same parse (e.g. another thread)
+    pub fn merge(&mut self, other: ParseReport) {
+        for (table, rows) in other.rows {
+            self.rows(&table, rows.added, rows.attempted);
+        }
+        for (name, drops) in other.drops {
+            let mine = self.drops.entry(name).or_default();
+            mine.count += drops.count;
+            let room = MAX_SAMPLES.saturating_sub(mine.samples.len());
+            mine.samples.extend(drops.samples.into_iter().take(room));
+        }
+    }
+}
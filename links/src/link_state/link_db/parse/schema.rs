@@ -0,0 +1,84 @@
+// Column layout of a table, read from the `CREATE TABLE` statement at the top of its dump
+// The columns of `page` in particular change between wikis and dump vintages 
+//  (e.g. `page_counter` was dropped, `page_lang` was added, simplewiki had an extra
+//  column for a while), so rather than hard-coding their positions we look them up.
+//
+// The header looks something like this:
+//
+//  CREATE TABLE `redirect` (
+//    `rd_from` int(8) unsigned NOT NULL DEFAULT '0',
+//    `rd_namespace` int(11) NOT NULL DEFAULT '0',
+//    ...
+//    PRIMARY KEY (`rd_from`),
+//    KEY `rd_ns_title` (`rd_namespace`,`rd_title`,`rd_from`)
+//  ) ENGINE=InnoDB DEFAULT CHARSET=binary;
+
+use super::error::ErrorKind;
+
+const CREATE_TABLE: &str = "CREATE TABLE";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    pub table: String,
+    columns: Vec<String>,
+}
+
+/// Incrementally read a `CREATE TABLE` statement one line at a time
+#[derive(Debug, Default)]
+pub struct SchemaReader {
+    table: Option<String>,
+    columns: Vec<String>,
+}
+
+fn quoted(s: &str) -> Option<&str> {
+    // contents of the first `backtick-quoted` identifier in `s`
+    let start = s.find('`')? + 1;
+    let len = s[start..].find('`')?;
+    Some(&s[start..start+len])
+}
+
+impl SchemaReader {
+    /// Feed the next line of the dump; returns the schema once the statement is complete
+    pub fn feed(&mut self, line: &str) -> Option<Schema> {
+        let line = line.trim();
+        if self.table.is_none() {
+            if line.starts_with(CREATE_TABLE) {
+                self.table = quoted(line).map(String::from);
+            }
+            None
+        } else if line.starts_with(')') {
+            // end of the statement
+            Some(Schema {
+                table: self.table.take().unwrap(),
+                columns: self.columns.drain(..).collect(),
+            })
+        } else {
+            // column definitions start with their name; keys/indices don't
+            if line.starts_with('`') {
+                if let Some(col) = quoted(line) {
+                    self.columns.push(String::from(col));
+                }
+            }
+            None
+        }
+    }
+}
+
+impl Schema {
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+    pub fn position(&self, column: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c == column)
+    }
+    pub fn has(&self, column: &str) -> bool {
+        self.position(column).is_some()
+    }
+    /// Positions of each of `columns`, in the same order
+    pub fn require(&self, columns: &[&str]) -> Result<Vec<usize>, ErrorKind> {
+        columns.iter().map(|c| self.position(c).ok_or_else(|| ErrorKind::MissingColumn {
+            table: self.table.clone(),
+            column: String::from(*c),
+        })).collect()
+    }
+}
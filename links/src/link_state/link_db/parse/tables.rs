@@ -0,0 +1,507 @@
+// The tables we read out of the sql dumps
+// Each `Table` knows which of its columns it needs (looked up in the dump's `Schema`) and
+//  how to turn a tuple of tokenized `Field`s into a row for the `Database`.
+// Rows outside of the database's namespaces are skipped (see `link_state::namespace`);
+//  `add` returns `None` for those so they aren't counted as attempts (they're still
+//  counted in the `ParseReport`).
+// A value of the wrong type (or a tuple that's too short) means the dump isn't what we
+//  think it is, so that's an error rather than a skipped row.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use link_state::namespace;
+use link_state::page_metadata::PageMetadata;
+use super::database::Database;
+use super::error::ErrorKind;
+use super::report::{ParseReport, DropReason};
+use super::schema::Schema;
+use super::tokenizer::{Field, Tuple};
+
+pub trait Table {
+    /// Positions of the needed columns in each tuple
+    type Columns;
+    fn columns(schema: &Schema) -> Result<Self::Columns, ErrorKind>;
+    /// Whether rows refer to their destination by an id into linktarget.sql
+    fn by_target(_cols: &Self::Columns) -> bool {
+        false
+    }
+    /// Add one tuple to the database; `None` if the tuple was skipped
+    fn add(db: &mut Database, cols: &Self::Columns, fields: &Tuple) 
+        -> Result<Option<bool>, ErrorKind>;
+}
+
+pub struct PageRow<'a> {
+    pub id: u32,
+    pub namespace: i32,
+    pub title: Cow<'a, str>,
+    pub is_redirect: bool,
+    // only read if the db is keeping it (and the dump has it)
+    pub metadata: Option<PageMetadata>,
+}
+
+pub struct RedirectRow<'a> {
+    pub from: u32,
+    // of the destination
+    pub namespace: i32,
+    pub title: Cow<'a, str>,
+    // the section of the destination, if any (e.g. `Sky` in `USA_Today#Sky`)
+    pub fragment: Option<Cow<'a, str>>,
+}
+
+pub struct LinkTargetRow<'a> {
+    pub id: u32,
+    pub namespace: i32,
+    pub title: Cow<'a, str>,
+}
+
+pub enum PageLinkRow<'a> {
+    // legacy layout: the destination is given by its title
+    Title { from: u32, namespace: i32, title: Cow<'a, str> },
+    // linktarget layout: the destination is given by an id into linktarget.sql
+    Target { from: u32, target: u32 },
+}
+
+pub enum TemplateLinkRow<'a> {
+    // legacy layout: the template is given by its title
+    Title { from: u32, namespace: i32, title: Cow<'a, str> },
+    // linktarget layout: the template is given by an id into linktarget.sql
+    Target { from: u32, target: u32 },
+}
+
+pub struct PagePropRow<'a> {
+    pub page: u32,
+    pub name: Cow<'a, str>,
+}
+
+pub struct LangLinkRow<'a> {
+    pub from: u32,
+    pub lang: Cow<'a, str>,
+    pub title: Cow<'a, str>,
+}
+
+pub enum CategoryLinkRow<'a> {
+    // legacy layout: the category is given by its title
+    Title { from: u32, category: Cow<'a, str> },
+    // linktarget layout: the category is given by an id into linktarget.sql
+    Target { from: u32, target: u32 },
+}
+
+// the key of each row's title (see `namespace::key`)
+impl<'a> PageRow<'a> {
+    pub fn key(&self) -> Cow<'_, str> {
+        namespace::key(self.namespace, &self.title)
+    }
+}
+impl<'a> RedirectRow<'a> {
+    pub fn key(&self) -> Cow<'_, str> {
+        namespace::key(self.namespace, &self.title)
+    }
+}
+impl<'a> LinkTargetRow<'a> {
+    pub fn key(&self) -> Cow<'_, str> {
+        namespace::key(self.namespace, &self.title)
+    }
+}
+
+// rows are shown as samples in the `ParseReport`
+impl<'a> fmt::Display for PageRow<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} `{}`", self.id, namespace::display(&self.key()))
+    }
+}
+impl<'a> fmt::Display for RedirectRow<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} → `{}", self.from, namespace::display(&self.key()))?;
+        match self.fragment {
+            Some(ref fragment) => write!(f, "#{}`", fragment),
+            None => write!(f, "`"),
+        }
+    }
+}
+impl<'a> fmt::Display for LinkTargetRow<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} `{}`", self.id, namespace::display(&self.key()))
+    }
+}
+impl<'a> fmt::Display for PageLinkRow<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PageLinkRow::Title { from, namespace, ref title } => write!(f, "{} → `{}`", 
+                from, namespace::display(&namespace::key(namespace, title))),
+            PageLinkRow::Target { from, target } => write!(f, "{} → target {}", from, target),
+        }
+    }
+}
+impl<'a> fmt::Display for TemplateLinkRow<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TemplateLinkRow::Title { from, namespace, ref title } => write!(f, "{} ⊃ `{}`", 
+                from, namespace::display(&namespace::key(namespace, title))),
+            TemplateLinkRow::Target { from, target } => 
+                write!(f, "{} ⊃ target {}", from, target),
+        }
+    }
+}
+impl<'a> fmt::Display for LangLinkRow<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} → {}:`{}`", self.from, self.lang, self.title)
+    }
+}
+impl<'a> fmt::Display for PagePropRow<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} `{}`", self.page, self.name)
+    }
+}
+impl<'a> fmt::Display for CategoryLinkRow<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CategoryLinkRow::Title { from, ref category } => 
+                write!(f, "{} ∈ `{}`", from, category),
+            CategoryLinkRow::Target { from, target } => 
+                write!(f, "{} ∈ target {}", from, target),
+        }
+    }
+}
+
+pub struct Pages;
+pub struct Redirects;
+pub struct LinkTargets;
+pub struct PageLinks;
+pub struct TemplateLinks;
+pub struct CategoryLinks;
+pub struct LangLinks;
+pub struct PageProps;
+
+fn field<'a>(f: &Tuple<'a>, i: usize) -> Result<Field<'a>, ErrorKind> {
+    f.get(i).ok_or(ErrorKind::BadValue { column: i, expected: "a value" })
+}
+fn int(f: &Tuple, i: usize) -> Result<i64, ErrorKind> {
+    field(f, i)?.as_int().ok_or(ErrorKind::BadValue { column: i, expected: "an integer" })
+}
+fn id(f: &Tuple, i: usize) -> Result<u32, ErrorKind> {
+    field(f, i)?.as_u32().ok_or(ErrorKind::BadValue { column: i, expected: "an id" })
+}
+fn string<'a>(f: &Tuple<'a>, i: usize) -> Result<Cow<'a, str>, ErrorKind> {
+    field(f, i)?.as_str().ok_or(ErrorKind::BadValue { column: i, expected: "a string" })
+}
+fn title<'a>(f: &Tuple<'a>, i: usize) -> Result<Cow<'a, str>, ErrorKind> {
+    // kept byte-for-byte: the wiki's own rules made it (see `link_state::title`)
+    string(f, i)
+}
+fn timestamp(f: &Tuple, i: usize) -> Result<u64, ErrorKind> {
+    // mediawiki timestamps are strings of 14 digits (YYYYMMDDHHMMSS)
+    string(f, i)?.parse()
+        .map_err(|_| ErrorKind::BadValue { column: i, expected: "a timestamp" })
+}
+fn namespace(f: &Tuple, i: usize) -> Result<i32, ErrorKind> {
+    let ns = int(f, i)?;
+    if ns < i64::from(i32::MIN) || ns > i64::from(i32::MAX) {
+        return Err(ErrorKind::BadValue { column: i, expected: "a namespace" });
+    }
+    Ok(ns as i32)
+}
+fn other_namespace(db: &mut Database, f: &Tuple, id: usize) {
+    // the row's id is enough to find it again
+    let sample = f.get(id).and_then(|f| f.as_int()).unwrap_or(-1);
+    db.report().drop(DropReason::OtherNamespace, &sample);
+}
+
+pub struct PageColumns {
+    // page_id, page_namespace, page_title, page_is_redirect
+    page: Vec<usize>,
+    // page_len, page_latest, page_touched (if the dump has all of them)
+    metadata: Option<Vec<usize>>,
+}
+
+impl Table for Pages {
+    type Columns = PageColumns;
+    fn columns(schema: &Schema) -> Result<PageColumns, ErrorKind> {
+        Ok(PageColumns {
+            page: schema.require(
+                      &["page_id", "page_namespace", "page_title", "page_is_redirect"])?,
+            metadata: schema.require(&["page_len", "page_latest", "page_touched"]).ok(),
+        })
+    }
+    fn add(db: &mut Database, cols: &PageColumns, f: &Tuple) 
+        -> Result<Option<bool>, ErrorKind> 
+    {
+        let c = &cols.page;
+        let ns = namespace(f, c[1])?;
+        let page_id = id(f, c[0])?;
+        let metadata = match cols.metadata {
+            Some(ref m) if db.keeps_metadata() => Some(PageMetadata {
+                id:         page_id,
+                len:        id(f, m[0])?,
+                latest:     id(f, m[1])?,
+                touched:    timestamp(f, m[2])?,
+            }),
+            _ => None,
+        };
+        let row = PageRow {
+            id:             page_id,
+            namespace:      ns,
+            title:          title(f, c[2])?,
+            is_redirect:    int(f, c[3])? == 1,
+            metadata,
+        };
+        // templates are needed to find out which links come from them
+        if ns == namespace::TEMPLATE {
+            db.add_template(&row);
+        }
+        if db.includes(ns) == false {
+            other_namespace(db, f, c[0]);
+            return Ok(None);
+        }
+        Ok(Some(db.add_page(&row)))
+    }
+}
+
+impl Table for Redirects {
+    // from, namespace, title, and fragment (if the dump has it)
+    type Columns = Vec<usize>;
+    fn columns(schema: &Schema) -> Result<Vec<usize>, ErrorKind> {
+        let mut columns = schema.require(&["rd_from", "rd_namespace", "rd_title"])?;
+        columns.extend(schema.position("rd_fragment"));
+        Ok(columns)
+    }
+    fn add(db: &mut Database, c: &Vec<usize>, f: &Tuple) -> Result<Option<bool>, ErrorKind> {
+        let ns = namespace(f, c[1])?;
+        if db.includes(ns) == false {
+            other_namespace(db, f, c[0]);
+            return Ok(None);
+        }
+        let row = RedirectRow {
+            from:       id(f, c[0])?,
+            namespace:  ns,
+            title:      title(f, c[2])?,
+            // NULL or empty if the redirect is to the whole page
+            fragment:   c.get(3).and_then(|&i| f.get(i)).and_then(|v| v.as_str())
+                            .filter(|s| s.is_empty() == false),
+        };
+        Ok(Some(db.add_redirect(&row)))
+    }
+}
+
+impl Table for LinkTargets {
+    // id, namespace, title
+    type Columns = Vec<usize>;
+    fn columns(schema: &Schema) -> Result<Vec<usize>, ErrorKind> {
+        schema.require(&["lt_id", "lt_namespace", "lt_title"])
+    }
+    fn add(db: &mut Database, c: &Vec<usize>, f: &Tuple) -> Result<Option<bool>, ErrorKind> {
+        let ns = namespace(f, c[1])?;
+        let row = LinkTargetRow {
+            id:         id(f, c[0])?,
+            namespace:  ns,
+            title:      title(f, c[2])?,
+        };
+        // templatelinks.sql and categorylinks.sql can refer to theirs by linktarget too
+        if ns == namespace::TEMPLATE {
+            db.add_template_target(&row);
+        } else if ns == namespace::CATEGORY {
+            db.add_category_target(&row);
+        }
+        if db.includes(ns) == false {
+            other_namespace(db, f, c[0]);
+            return Ok(None);
+        }
+        Ok(Some(db.add_linktarget(&row)))
+    }
+}
+
+pub enum PageLinkColumns {
+    // pl_from, pl_from_namespace, pl_namespace, pl_title
+    Title(Vec<usize>),
+    // pl_from, pl_from_namespace, pl_target_id
+    Target(Vec<usize>),
+}
+
+impl Table for PageLinks {
+    // transitional dumps have both a title and a target; prefer the title
+    type Columns = PageLinkColumns;
+    fn columns(schema: &Schema) -> Result<PageLinkColumns, ErrorKind> {
+        Ok(if schema.has("pl_title") {
+            PageLinkColumns::Title(schema.require(
+                    &["pl_from", "pl_from_namespace", "pl_namespace", "pl_title"])?)
+        } else {
+            PageLinkColumns::Target(schema.require(
+                    &["pl_from", "pl_from_namespace", "pl_target_id"])?)
+        })
+    }
+    fn by_target(cols: &PageLinkColumns) -> bool {
+        matches!(*cols, PageLinkColumns::Target(_))
+    }
+    fn add(db: &mut Database, cols: &PageLinkColumns, f: &Tuple) 
+        -> Result<Option<bool>, ErrorKind> 
+    {
+        match PageLinks::row(db, cols, f)? {
+            Some(row) => Ok(Some(db.add_pagelink(&row))),
+            None => {
+                PageLinks::other_namespace(db.report(), cols, f);
+                Ok(None)
+            },
+        }
+    }
+}
+
+impl PageLinks {
+    /// Record a link that `row` skipped
+    pub fn other_namespace(report: &mut ParseReport, cols: &PageLinkColumns, f: &Tuple) {
+        let c = match *cols {
+            PageLinkColumns::Title(ref c) | PageLinkColumns::Target(ref c) => c,
+        };
+        let sample = f.get(c[0]).and_then(|f| f.as_int()).unwrap_or(-1);
+        report.drop(DropReason::OtherNamespace, &sample);
+    }
+    /// Links are also resolved outside of `add` when they're parsed in parallel
+    pub fn row<'a>(db: &Database, cols: &PageLinkColumns, f: &Tuple<'a>) 
+        -> Result<Option<PageLinkRow<'a>>, ErrorKind> 
+    {
+        // links are skipped if either end is outside of the db's namespaces
+        //  (though links from templates are kept if they're being collected)
+        // (with link targets, the destination's namespace was checked in `LinkTargets`)
+        Ok(match *cols {
+            PageLinkColumns::Title(ref c) => {
+                let ns = namespace(f, c[2])?;
+                if db.links_from(namespace(f, c[1])?) == false || db.includes(ns) == false {
+                    return Ok(None);
+                }
+                Some(PageLinkRow::Title {
+                    from:       id(f, c[0])?,
+                    namespace:  ns,
+                    title:      title(f, c[3])?,
+                })
+            },
+            PageLinkColumns::Target(ref c) => {
+                if db.links_from(namespace(f, c[1])?) == false {
+                    return Ok(None);
+                }
+                Some(PageLinkRow::Target {
+                    from:   id(f, c[0])?,
+                    target: id(f, c[2])?,
+                })
+            },
+        })
+    }
+}
+
+pub enum TemplateLinkColumns {
+    // tl_from, tl_from_namespace, tl_namespace, tl_title
+    Title(Vec<usize>),
+    // tl_from, tl_from_namespace, tl_target_id
+    Target(Vec<usize>),
+}
+
+impl Table for TemplateLinks {
+    // like pagelinks, newer dumps refer to the template by linktarget id
+    type Columns = TemplateLinkColumns;
+    fn columns(schema: &Schema) -> Result<TemplateLinkColumns, ErrorKind> {
+        Ok(if schema.has("tl_title") {
+            TemplateLinkColumns::Title(schema.require(
+                    &["tl_from", "tl_from_namespace", "tl_namespace", "tl_title"])?)
+        } else {
+            TemplateLinkColumns::Target(schema.require(
+                    &["tl_from", "tl_from_namespace", "tl_target_id"])?)
+        })
+    }
+    fn by_target(cols: &TemplateLinkColumns) -> bool {
+        matches!(*cols, TemplateLinkColumns::Target(_))
+    }
+    fn add(db: &mut Database, cols: &TemplateLinkColumns, f: &Tuple) 
+        -> Result<Option<bool>, ErrorKind> 
+    {
+        let c = match *cols {
+            TemplateLinkColumns::Title(ref c) | TemplateLinkColumns::Target(ref c) => c,
+        };
+        if db.includes(namespace(f, c[1])?) == false {
+            other_namespace(db, f, c[0]);
+            return Ok(None);
+        }
+        let row = match *cols {
+            TemplateLinkColumns::Title(ref c) => TemplateLinkRow::Title {
+                from:       id(f, c[0])?,
+                namespace:  namespace(f, c[2])?,
+                title:      title(f, c[3])?,
+            },
+            TemplateLinkColumns::Target(ref c) => TemplateLinkRow::Target {
+                from:       id(f, c[0])?,
+                target:     id(f, c[2])?,
+            },
+        };
+        Ok(Some(db.add_templatelink(&row)))
+    }
+}
+
+pub enum CategoryLinkColumns {
+    // cl_from, cl_to
+    Title(Vec<usize>),
+    // cl_from, cl_target_id
+    Target(Vec<usize>),
+}
+
+impl Table for CategoryLinks {
+    // like pagelinks, newer dumps refer to the category by linktarget id
+    type Columns = CategoryLinkColumns;
+    fn columns(schema: &Schema) -> Result<CategoryLinkColumns, ErrorKind> {
+        Ok(if schema.has("cl_to") {
+            CategoryLinkColumns::Title(schema.require(&["cl_from", "cl_to"])?)
+        } else {
+            CategoryLinkColumns::Target(schema.require(&["cl_from", "cl_target_id"])?)
+        })
+    }
+    fn by_target(cols: &CategoryLinkColumns) -> bool {
+        matches!(*cols, CategoryLinkColumns::Target(_))
+    }
+    fn add(db: &mut Database, cols: &CategoryLinkColumns, f: &Tuple) 
+        -> Result<Option<bool>, ErrorKind> 
+    {
+        let row = match *cols {
+            CategoryLinkColumns::Title(ref c) => CategoryLinkRow::Title {
+                from:       id(f, c[0])?,
+                category:   title(f, c[1])?,
+            },
+            CategoryLinkColumns::Target(ref c) => CategoryLinkRow::Target {
+                from:       id(f, c[0])?,
+                target:     id(f, c[1])?,
+            },
+        };
+        Ok(Some(db.add_categorylink(&row)))
+    }
+}
+
+impl Table for LangLinks {
+    // from, lang, title
+    type Columns = Vec<usize>;
+    fn columns(schema: &Schema) -> Result<Vec<usize>, ErrorKind> {
+        schema.require(&["ll_from", "ll_lang", "ll_title"])
+    }
+    fn add(db: &mut Database, c: &Vec<usize>, f: &Tuple) -> Result<Option<bool>, ErrorKind> {
+        let row = LangLinkRow {
+            from:   id(f, c[0])?,
+            lang:   string(f, c[1])?,
+            title:  string(f, c[2])?,
+        };
+        Ok(Some(db.add_langlink(&row)))
+    }
+}
+
+impl Table for PageProps {
+    // page, propname
+    type Columns = Vec<usize>;
+    fn columns(schema: &Schema) -> Result<Vec<usize>, ErrorKind> {
+        schema.require(&["pp_page", "pp_propname"])
+    }
+    fn add(db: &mut Database, c: &Vec<usize>, f: &Tuple) -> Result<Option<bool>, ErrorKind> {
+        let row = PagePropRow {
+            page:   id(f, c[0])?,
+            name:   string(f, c[1])?,
+        };
+        // only disambiguation pages are kept track of; the rest (wikidata ids etc.) are
+        //  most of the table, so they're skipped rather than reported as dropped
+        if row.name != "disambiguation" {
+            return Ok(None);
+        }
+        Ok(Some(db.add_disambiguation(&row)))
+    }
+}
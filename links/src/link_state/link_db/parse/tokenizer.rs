@@ -0,0 +1,396 @@
+// Byte-level tokenizer for the `INSERT INTO ... VALUES (...),(...);` statements in sql dumps
+// This replaces matching each line against a big backtracking regex: a tuple is scanned
+//  exactly once, no utf8 conversion is done for fields we don't look at, and the fields
+//  come out typed rather than as strings to be re-parsed.
+//
+// Tuples are read straight from the dump rather than a line at a time, so a statement
+//  can be any length: the buffer only ever has to hold the tuple being read (plus
+//  whatever was read along with it). When a tuple runs off the end of the buffer, the
+//  bytes before it are dropped, more of the dump is read in, and the tuple is re-read.
+// Lines that aren't `INSERT` statements (comments, the header, etc.) are skipped.
+//
+// Strings are yielded raw: the surrounding quotes are stripped but escape sequences
+//  (`\'`, `\\`, etc.) are left as they appear in the dump. They're only decoded (see
+//  `unescape`) when a field is read as a `str`, so titles match the real ones.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str;
+
+use super::error::{ErrorKind, Position};
+
+const INSERT_INTO: &[u8] = b"INSERT INTO ";
+const VALUES: &[u8] = b" VALUES ";
+const NULL: &[u8] = b"NULL";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field<'a> {
+    Null,
+    Int(i64),
+    Float(f64),
+    Str(&'a [u8]),
+}
+
+impl<'a> Field<'a> {
+    pub fn as_int(&self) -> Option<i64> {
+        match *self {
+            Field::Int(i) => Some(i),
+            _ => None,
+        }
+    }
+    pub fn as_u32(&self) -> Option<u32> {
+        match *self {
+            Field::Int(i) if i >= 0 && i <= i64::from(u32::MAX) => Some(i as u32),
+            _ => None,
+        }
+    }
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match *self {
+            Field::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<Cow<'a, str>> {
+        // invalid utf8 is replaced with '�' (see note in `parse`)
+        self.as_bytes().map(|s| match unescape(s) {
+            Cow::Borrowed(s) => String::from_utf8_lossy(s),
+            Cow::Owned(s) => Cow::Owned(String::from_utf8_lossy(&s).into_owned()),
+        })
+    }
+}
+
+/// Decode the escape sequences mysqldump writes in a string literal
+/// (https://dev.mysql.com/doc/refman/5.7/en/string-literals.html)
+/// Only allocates if there's something to decode
+pub fn unescape(raw: &[u8]) -> Cow<'_, [u8]> {
+    if raw.contains(&b'\\') == false {
+        return Cow::Borrowed(raw);
+    }
+    let mut s = Vec::with_capacity(raw.len());
+    let mut bytes = raw.iter();
+    while let Some(&b) = bytes.next() {
+        if b != b'\\' {
+            s.push(b);
+            continue;
+        }
+        match bytes.next() {
+            Some(&b'0') => s.push(0),
+            Some(&b'b') => s.push(0x08),
+            Some(&b'n') => s.push(b'\n'),
+            Some(&b'r') => s.push(b'\r'),
+            Some(&b't') => s.push(b'\t'),
+            Some(&b'Z') => s.push(0x1a),
+            // these keep their backslash (it's only special in `LIKE` patterns)
+            Some(&c) if c == b'%' || c == b'_' => s.extend_from_slice(&[b'\\', c]),
+            // `\\`, `\'`, `\"`, and anything else is just the character itself
+            Some(&c) => s.push(c),
+            // a trailing backslash can't be escaping anything
+            None => s.push(b'\\'),
+        }
+    }
+    Cow::Owned(s)
+}
+
+// a field as it sits in the buffer; strings are a range of the buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Null,
+    Int(i64),
+    Float(f64),
+    Str(usize, usize),
+}
+
+/// One tuple of an `INSERT` statement, borrowed from the tokenizer's buffer
+pub struct Tuple<'a> {
+    buf: &'a [u8],
+    tokens: &'a [Token],
+    position: Position,
+}
+
+impl<'a> Tuple<'a> {
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+    pub fn get(&self, i: usize) -> Option<Field<'a>> {
+        self.tokens.get(i).map(|t| match *t {
+            Token::Null => Field::Null,
+            Token::Int(i) => Field::Int(i),
+            Token::Float(f) => Field::Float(f),
+            Token::Str(start, end) => Field::Str(&self.buf[start..end]),
+        })
+    }
+    /// Where the tuple starts in the dump
+    pub fn position(&self) -> Position {
+        self.position
+    }
+}
+
+/// Where and why the dump couldn't be tokenized
+#[derive(Debug)]
+pub struct TokenError {
+    pub position: Position,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at byte {}", self.kind, self.position.offset)
+    }
+}
+
+// why a tuple couldn't be read out of the buffer
+enum Halt {
+    // the buffer ended first; read more and try again
+    Incomplete,
+    // something else was at this index of the buffer
+    Expected(usize, &'static str),
+}
+
+/// Yields the tuples of every `INSERT` statement in a dump
+pub struct Tokenizer<R: BufRead> {
+    reader: R,
+    buf: Vec<u8>,
+    // index of the first byte of `buf` that hasn't been tokenized
+    pos: usize,
+    // offset of `buf[0]` in the dump and the number of lines before `buf[pos]`
+    offset: u64,
+    line: u64,
+    eof: bool,
+    in_statement: bool,
+    table: Vec<u8>,
+    tokens: Vec<Token>,
+}
+
+impl<R: BufRead> Tokenizer<R> {
+    /// Tokenize the rest of `reader`, which is at `start` in the dump
+    pub fn new(reader: R, start: Position) -> Tokenizer<R> {
+        Tokenizer {
+            reader,
+            buf: vec![],
+            pos: 0,
+            offset: start.offset,
+            line: start.line,
+            eof: false,
+            in_statement: false,
+            table: vec![],
+            tokens: Vec::with_capacity(16),
+        }
+    }
+
+    /// Table of the statement being read
+    pub fn table(&self) -> &[u8] {
+        &self.table
+    }
+
+    /// Where the tokenizer is in the dump (`line` is 1-based)
+    pub fn position(&self) -> Position {
+        Position {
+            line: self.line + 1,
+            offset: self.offset + self.pos as u64,
+        }
+    }
+
+    /// Read the next tuple, or `None` at the end of the dump
+    pub fn next_tuple(&mut self) -> Result<Option<Tuple<'_>>, TokenError> {
+        let end = loop {
+            match self.step() {
+                Ok(Some(end)) => break end,
+                Ok(None) => return Ok(None),
+                Err(Halt::Incomplete) if self.eof =>
+                    return Err(self.error(self.buf.len(), "the rest of the statement")),
+                Err(Halt::Incomplete) => self.refill().map_err(|e| TokenError {
+                    position: self.position(),
+                    kind: ErrorKind::Io(e),
+                })?,
+                Err(Halt::Expected(at, expected)) => return Err(self.error(at, expected)),
+            }
+        };
+        // `step` leaves `pos` at the start of the tuple in case it has to be re-read
+        let position = self.position();
+        self.pos = end;
+        Ok(Some(Tuple {
+            buf: &self.buf,
+            tokens: &self.tokens,
+            position,
+        }))
+    }
+
+    fn error(&self, at: usize, expected: &'static str) -> TokenError {
+        TokenError {
+            position: Position {
+                line: self.line + 1,
+                offset: self.offset + at as u64,
+            },
+            kind: ErrorKind::Expected(expected),
+        }
+    }
+
+    fn refill(&mut self) -> io::Result<()> {
+        // drop what's been tokenized and append the next block of the dump
+        self.offset += self.pos as u64;
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+        let len = {
+            let block = self.reader.fill_buf()?;
+            self.buf.extend_from_slice(block);
+            block.len()
+        };
+        self.reader.consume(len);
+        self.eof = len == 0;
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<Option<usize>, Halt> {
+        // advance to the next tuple and read it into `tokens`, returning where it ends
+        // `None` at the end of the dump
+        loop {
+            let rest = &self.buf[self.pos..];
+            if self.in_statement {
+                // tuples are separated by `,` and the statement is terminated by `;`
+                match rest.first() {
+                    None => return Err(Halt::Incomplete),
+                    Some(&b',') => self.pos += 1,
+                    Some(&b';') => {
+                        // the rest of the line is skipped below
+                        self.pos += 1;
+                        self.in_statement = false;
+                    },
+                    Some(&b'(') => {
+                        self.tokens.clear();
+                        return tuple(&self.buf, self.pos, &mut self.tokens).map(Some);
+                    },
+                    Some(_) => return Err(Halt::Expected(self.pos, "`(`")),
+                }
+                continue;
+            }
+
+            // at the start of a line (or at the end of a statement)
+            let newline = rest.iter().position(|&b| b == b'\n');
+            if rest.starts_with(INSERT_INTO) {
+                let line = &rest[..newline.unwrap_or(rest.len())];
+                match find(line, VALUES) {
+                    Some(values) => {
+                        self.table = trim_backticks(&line[INSERT_INTO.len()..values]).to_vec();
+                        self.pos += values + VALUES.len();
+                        self.in_statement = true;
+                    },
+                    None if newline.is_some() || self.eof =>
+                        return Err(Halt::Expected(self.pos, "`VALUES`")),
+                    None => return Err(Halt::Incomplete),
+                }
+            } else if rest.len() < INSERT_INTO.len() && INSERT_INTO.starts_with(rest)
+                    && self.eof == false {
+                // this might still turn out to be a statement
+                return Err(Halt::Incomplete);
+            } else {
+                // skip the line without keeping it around
+                match newline {
+                    Some(i) => {
+                        self.pos += i + 1;
+                        self.line += 1;
+                    },
+                    None if self.eof => {
+                        self.pos = self.buf.len();
+                        return Ok(None);
+                    },
+                    None => {
+                        self.pos = self.buf.len();
+                        return Err(Halt::Incomplete);
+                    },
+                }
+            }
+        }
+    }
+}
+
+fn tuple(buf: &[u8], start: usize, out: &mut Vec<Token>) -> Result<usize, Halt> {
+    // read the tuple beginning with the `(` at `start`; returns the index after its `)`
+    let mut i = start + 1;
+    loop {
+        i = field(buf, i, out)?;
+        match buf.get(i) {
+            Some(&b',') => i += 1,
+            Some(&b')') => return Ok(i + 1),
+            Some(_) => return Err(Halt::Expected(i, "`,` or `)`")),
+            None => return Err(Halt::Incomplete),
+        }
+    }
+}
+
+fn field(buf: &[u8], i: usize, out: &mut Vec<Token>) -> Result<usize, Halt> {
+    let rest = &buf[i..];
+    match rest.first() {
+        Some(&b'\'') => string(buf, i, out),
+        Some(&b'N') if rest.starts_with(NULL) => {
+            out.push(Token::Null);
+            Ok(i + NULL.len())
+        },
+        Some(&b'N') if NULL.starts_with(rest) => Err(Halt::Incomplete),
+        Some(&c) if c == b'-' || c == b'.' || c.is_ascii_digit() => number(buf, i, out),
+        Some(_) => Err(Halt::Expected(i, "a value")),
+        None => Err(Halt::Incomplete),
+    }
+}
+
+fn string(buf: &[u8], i: usize, out: &mut Vec<Token>) -> Result<usize, Halt> {
+    // skip over escaped characters until the closing quote
+    let start = i + 1;
+    let mut j = start;
+    while j < buf.len() {
+        match buf[j] {
+            b'\\' => j += 2,
+            b'\'' => {
+                out.push(Token::Str(start, j));
+                return Ok(j + 1);
+            },
+            _ => j += 1,
+        }
+    }
+    Err(Halt::Incomplete)
+}
+
+fn number(buf: &[u8], i: usize, out: &mut Vec<Token>) -> Result<usize, Halt> {
+    let mut is_float = false;
+    let mut j = i;
+    while j < buf.len() {
+        match buf[j] {
+            b'0' ..= b'9' | b'-' | b'+' => {},
+            b'.' | b'e' | b'E' => is_float = true,
+            _ => break,
+        }
+        j += 1;
+    }
+    if j == buf.len() {
+        // there may be more digits still to be read
+        return Err(Halt::Incomplete);
+    }
+    // the characters are all ascii so this can't fail
+    let s = str::from_utf8(&buf[i..j]).unwrap();
+    let token = if is_float {
+        s.parse().map(Token::Float).ok()
+    } else {
+        s.parse().map(Token::Int).ok()
+    };
+    match token {
+        Some(t) => {
+            out.push(t);
+            Ok(j)
+        },
+        None => Err(Halt::Expected(i, "a number")),
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn trim_backticks(s: &[u8]) -> &[u8] {
+    match (s.first(), s.last()) {
+        (Some(&b'`'), Some(&b'`')) if s.len() >= 2 => &s[1..s.len()-1],
+        _ => s,
+    }
+}
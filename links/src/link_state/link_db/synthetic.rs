@@ -0,0 +1,774 @@
+// Made-up dumps (for tests and benchmarks that can't count on having the real ones)
+// A `Wiki` is a small scripted or random wiki, written out as page.sql, redirect.sql, and
+//  pagelinks.sql laid out like the real dumps (either the legacy layout, with each link's
+//  title in pagelinks.sql, or the newer one that refers to them in linktarget.sql). A random one has a bit of everything the
+//  parser has to deal with:
+//
+//      redirects:          links to (or from) them lead to (or from) their target
+//      redirect chains:    redirects to other redirects
+//      redirect loops:     redirects that lead back to themselves, so links to them are
+//                          dropped
+//      broken redirects:   to a page that isn't there, or without a row in redirect.sql
+//      deleted pages:      in pagelinks.sql (on either end) but not in page.sql
+//      escaped titles:     quotes, backslashes, commas, and parentheses
+//      other namespaces:   pages, redirects, and links outside of articles
+//
+// A scripted one can also redirect to sections (see `redirect_to_section`), transclude
+//  templates (see `transclude`), put pages in categories (see `categorize`), link to
+//  other language editions (see `langlink`), and flag disambiguation pages (see
+//  `disambiguation`), which add templatelinks.sql, categorylinks.sql, langlinks.sql, and
+//  page_props.sql to its dumps.
+// `expected` works out what parsing the dumps should end up with straight from the wiki
+//  (without any of the parser's code), to check the parser against.
+// Each dump is also written gzip and bzip2 compressed (see `compressed`), which should
+//  parse exactly the same as the plain one.
+// It can also be written as an XML export (see `write_xml`), with each page's links in its
+//  wikitext, which should give the same graph as its sql dumps.
+
+use bzip2::write::BzEncoder;
+use flate2::write::GzEncoder;
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, Write, BufWriter};
+use std::path::{Path, PathBuf};
+
+use link_state::namespace::{self, Namespaces};
+use link_state::title;
+use super::Dumps;
+
+// tuples per `INSERT` statement (the real dumps have thousands, but a few lines each
+//  gives the parallel parser something to split up)
+const ROWS_PER_INSERT: usize = 50;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntheticPage {
+    pub id: u32,
+    pub namespace: i32,
+    // as it is in page.sql (see `title`), before it's escaped
+    pub title: String,
+    // what a redirect leads to (`Some(None)` if it has no row in redirect.sql)
+    pub redirect: Option<Option<(i32, String)>>,
+    // the section it leads to, if any (`rd_fragment`)
+    pub fragment: Option<String>,
+    // links from it are still in pagelinks.sql, but it isn't in page.sql
+    pub deleted: bool,
+}
+
+impl SyntheticPage {
+    pub fn key(&self) -> String {
+        namespace::key(self.namespace, &self.title).into_owned()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Wiki {
+    // (sorted by id)
+    pages: Vec<SyntheticPage>,
+    // (from, namespace, title)
+    links: Vec<(u32, i32, String)>,
+    // (page, template)
+    transclusions: Vec<(u32, u32)>,
+    // (page, category title)
+    categories: Vec<(u32, String)>,
+    // (page, language, title there)
+    langlinks: Vec<(u32, String, String)>,
+    // (sorted)
+    disambiguations: BTreeSet<u32>,
+}
+
+/// A page as it should be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectedPage {
+    // (a `namespace::key`)
+    pub title: String,
+    pub children: BTreeSet<u32>,
+}
+
+/// What parsing a wiki's dumps should end up with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Expected {
+    pub pages: BTreeMap<u32, ExpectedPage>,
+    // every title that leads to a page (its own, or a redirect's), and the page
+    pub titles: BTreeMap<String, u32>,
+    // titles in page.sql that don't lead anywhere (broken redirects and loops)
+    pub dropped_titles: BTreeSet<String>,
+}
+
+impl Expected {
+    /// Each page's parents
+    pub fn parents(&self) -> BTreeMap<u32, BTreeSet<u32>> {
+        let mut parents: BTreeMap<u32, BTreeSet<u32>> =
+            self.pages.keys().map(|&id| (id, BTreeSet::new())).collect();
+        for (&id, page) in &self.pages {
+            for child in &page.children {
+                parents.entry(*child).or_default().insert(id);
+            }
+        }
+        parents
+    }
+    /// The fewest links it takes to get from `src` to `dst`, if it can be done
+    pub fn distance(&self, src: u32, dst: u32) -> Option<usize> {
+        self.distances(src).get(&dst).cloned()
+    }
+    /// How many links away from `src` each page it can reach is
+    pub fn distances(&self, src: u32) -> BTreeMap<u32, usize> {
+        let mut seen: BTreeMap<u32, usize> = BTreeMap::new();
+        let mut queue: VecDeque<u32> = VecDeque::new();
+        seen.insert(src, 0);
+        queue.push_back(src);
+        while let Some(id) = queue.pop_front() {
+            let d = seen[&id];
+            for &child in self.pages.get(&id).map(|p| &p.children).into_iter().flatten() {
+                if seen.contains_key(&child) == false {
+                    seen.insert(child, d + 1);
+                    queue.push_back(child);
+                }
+            }
+        }
+        seen
+    }
+}
+
+// mysqldump's escapes (see `tokenizer::unescape`)
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\'' => escaped.push_str("\\'"),
+            '"'  => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\0' => escaped.push_str("\\0"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// the characters that can't be written as-is in an XML export's text (or its attributes)
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// `create` followed by `INSERT`s of `rows`
+fn write_table<I: Iterator<Item=String>>(path: &Path, table: &str, create: &str, rows: I)
+    -> io::Result<()>
+{
+    let mut w = BufWriter::new(File::create(path)?);
+    writeln!(w, "-- MySQL dump 10.13")?;
+    writeln!(w, "DROP TABLE IF EXISTS `{}`;", table)?;
+    writeln!(w, "{}", create)?;
+    let rows: Vec<String> = rows.collect();
+    for chunk in rows.chunks(ROWS_PER_INSERT) {
+        writeln!(w, "INSERT INTO `{}` VALUES {};", table, chunk.join(","))?;
+    }
+    w.flush()?;
+    write_compressed(path)
+}
+
+// `path` with `extension` added on (`page.sql` → `page.sql.gz`)
+fn with_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+// gzip and bzip2 compressed copies of the dump at `path`
+fn write_compressed(path: &Path) -> io::Result<()> {
+    let plain = fs::read(path)?;
+    let gz = File::create(with_extension(path, "gz"))?;
+    let mut gz = GzEncoder::new(gz, ::flate2::Compression::default());
+    gz.write_all(&plain)?;
+    gz.finish()?;
+    let bz2 = File::create(with_extension(path, "bz2"))?;
+    let mut bz2 = BzEncoder::new(bz2, ::bzip2::Compression::default());
+    bz2.write_all(&plain)?;
+    bz2.finish()?;
+    Ok(())
+}
+
+/// The compressed copies of `dumps` that `Wiki::write` wrote next to them
+/// (`extension` is `gz` or `bz2`)
+pub fn compressed(dumps: &Dumps, extension: &str) -> Dumps {
+    let mut compressed = dumps.clone();
+    compressed.page = with_extension(&dumps.page, extension);
+    compressed.redirect = with_extension(&dumps.redirect, extension);
+    compressed.pagelinks = with_extension(&dumps.pagelinks, extension);
+    compressed.linktarget = dumps.linktarget.as_ref().map(|l| with_extension(l, extension));
+    compressed
+}
+
+const CREATE_PAGE: &str = "CREATE TABLE `page` (
+  `page_id` int(8) unsigned NOT NULL AUTO_INCREMENT,
+  `page_namespace` int(11) NOT NULL DEFAULT '0',
+  `page_title` varbinary(255) NOT NULL DEFAULT '',
+  `page_restrictions` tinyblob NOT NULL,
+  `page_is_redirect` tinyint(1) unsigned NOT NULL DEFAULT '0',
+  `page_is_new` tinyint(1) unsigned NOT NULL DEFAULT '0',
+  `page_random` double unsigned NOT NULL DEFAULT '0',
+  `page_touched` varbinary(14) NOT NULL DEFAULT '',
+  `page_links_updated` varbinary(14) DEFAULT NULL,
+  `page_latest` int(8) unsigned NOT NULL DEFAULT '0',
+  `page_len` int(8) unsigned NOT NULL DEFAULT '0',
+  `page_content_model` varbinary(32) DEFAULT NULL,
+  `page_lang` varbinary(35) DEFAULT NULL,
+  PRIMARY KEY (`page_id`)
+) ENGINE=InnoDB DEFAULT CHARSET=binary;";
+
+const CREATE_REDIRECT: &str = "CREATE TABLE `redirect` (
+  `rd_from` int(8) unsigned NOT NULL DEFAULT '0',
+  `rd_namespace` int(11) NOT NULL DEFAULT '0',
+  `rd_title` varbinary(255) NOT NULL DEFAULT '',
+  `rd_interwiki` varbinary(32) DEFAULT NULL,
+  `rd_fragment` varbinary(255) DEFAULT NULL,
+  PRIMARY KEY (`rd_from`)
+) ENGINE=InnoDB DEFAULT CHARSET=binary;";
+
+const CREATE_PAGELINKS: &str = "CREATE TABLE `pagelinks` (
+  `pl_from` int(8) unsigned NOT NULL DEFAULT '0',
+  `pl_namespace` int(11) NOT NULL DEFAULT '0',
+  `pl_title` varbinary(255) NOT NULL DEFAULT '',
+  `pl_from_namespace` int(11) NOT NULL DEFAULT '0',
+  UNIQUE KEY `pl_from` (`pl_from`,`pl_namespace`,`pl_title`)
+) ENGINE=InnoDB DEFAULT CHARSET=binary;";
+
+// the newer layout, which refers to each link's destination by its `lt_id`
+const CREATE_TEMPLATELINKS: &str = "CREATE TABLE `templatelinks` (
+  `tl_from` int(8) unsigned NOT NULL DEFAULT '0',
+  `tl_namespace` int(11) NOT NULL DEFAULT '0',
+  `tl_title` varbinary(255) NOT NULL DEFAULT '',
+  `tl_from_namespace` int(11) NOT NULL DEFAULT '0',
+  UNIQUE KEY `tl_from` (`tl_from`,`tl_namespace`,`tl_title`)
+) ENGINE=InnoDB DEFAULT CHARSET=binary;";
+
+const CREATE_CATEGORYLINKS: &str = "CREATE TABLE `categorylinks` (
+  `cl_from` int(8) unsigned NOT NULL DEFAULT '0',
+  `cl_to` varbinary(255) NOT NULL DEFAULT '',
+  `cl_sortkey` varbinary(230) NOT NULL DEFAULT '',
+  `cl_timestamp` timestamp NOT NULL,
+  `cl_sortkey_prefix` varbinary(255) NOT NULL DEFAULT '',
+  `cl_collation` varbinary(32) NOT NULL DEFAULT '',
+  `cl_type` enum('page','subcat','file') NOT NULL DEFAULT 'page',
+  UNIQUE KEY `cl_from` (`cl_from`,`cl_to`)
+) ENGINE=InnoDB DEFAULT CHARSET=binary;";
+
+const CREATE_PAGE_PROPS: &str = "CREATE TABLE `page_props` (
+  `pp_page` int(11) NOT NULL,
+  `pp_propname` varbinary(60) NOT NULL,
+  `pp_value` blob NOT NULL,
+  `pp_sortkey` float DEFAULT NULL,
+  PRIMARY KEY (`pp_page`,`pp_propname`)
+) ENGINE=InnoDB DEFAULT CHARSET=binary;";
+
+const CREATE_LANGLINKS: &str = "CREATE TABLE `langlinks` (
+  `ll_from` int(8) unsigned NOT NULL DEFAULT '0',
+  `ll_lang` varbinary(35) NOT NULL DEFAULT '',
+  `ll_title` varbinary(255) NOT NULL DEFAULT '',
+  UNIQUE KEY `ll_from` (`ll_from`,`ll_lang`)
+) ENGINE=InnoDB DEFAULT CHARSET=binary;";
+
+const CREATE_TARGET_PAGELINKS: &str = "CREATE TABLE `pagelinks` (
+  `pl_from` int(8) unsigned NOT NULL DEFAULT '0',
+  `pl_from_namespace` int(11) NOT NULL DEFAULT '0',
+  `pl_target_id` bigint(20) unsigned NOT NULL,
+  PRIMARY KEY (`pl_from`,`pl_target_id`)
+) ENGINE=InnoDB DEFAULT CHARSET=binary;";
+
+const CREATE_LINKTARGET: &str = "CREATE TABLE `linktarget` (
+  `lt_id` bigint(20) unsigned NOT NULL AUTO_INCREMENT,
+  `lt_namespace` int(11) NOT NULL,
+  `lt_title` varbinary(255) NOT NULL,
+  PRIMARY KEY (`lt_id`)
+) ENGINE=InnoDB DEFAULT CHARSET=binary;";
+
+// titles with something to escape (or to trip up the tokenizer), filled in with a number
+const TITLES: &[&str] = &[
+    "Page_#",
+    "Ender's_Game_#",
+    "It's_a_'quote'_#",
+    "Back\\slash_#",
+    "\"Quoted\"_#",
+    "Comma,_paren_(#)",
+    "Tuple'),(#",
+    "Caf\u{e9}_#",
+];
+
+fn chance<R: Rng>(rng: &mut R, p: f64) -> bool {
+    rng.gen::<f64>() < p
+}
+
+impl Wiki {
+    pub fn new() -> Wiki {
+        Wiki::default()
+    }
+    pub fn pages(&self) -> &[SyntheticPage] {
+        &self.pages
+    }
+    /// Each link's (from, namespace, title)
+    pub fn links(&self) -> &[(u32, i32, String)] {
+        &self.links
+    }
+    pub fn get(&self, id: u32) -> Option<&SyntheticPage> {
+        self.pages.binary_search_by_key(&id, |p| p.id).ok().map(|i| &self.pages[i])
+    }
+    fn get_mut(&mut self, id: u32) -> Option<&mut SyntheticPage> {
+        match self.pages.binary_search_by_key(&id, |p| p.id) {
+            Ok(i) => Some(&mut self.pages[i]),
+            Err(_) => None,
+        }
+    }
+    fn push(&mut self, namespace: i32, title: &str, redirect: Option<Option<(i32, String)>>)
+        -> u32
+    {
+        let id = self.pages.last().map_or(1, |p| p.id + 1);
+        let title = String::from(title);
+        self.pages.push(SyntheticPage { id, namespace, title, redirect, fragment: None,
+                                        deleted: false });
+        id
+    }
+    /// Add a page (with its title as it'd be in page.sql); returns its id
+    pub fn page(&mut self, namespace: i32, title: &str) -> u32 {
+        self.push(namespace, title, None)
+    }
+    /// Add a redirect to the page (or redirect) `target` in `target_ns`
+    pub fn redirect(&mut self, namespace: i32, title: &str, target_ns: i32, target: &str) -> u32 {
+        self.push(namespace, title, Some(Some((target_ns, String::from(target)))))
+    }
+    /// Add a redirect to the section `fragment` of `target` (e.g. `Sky` for `USA_Today#Sky`)
+    pub fn redirect_to_section(&mut self, namespace: i32, title: &str, target_ns: i32,
+                               target: &str, fragment: &str) -> u32
+    {
+        let id = self.redirect(namespace, title, target_ns, target);
+        self.pages.last_mut().unwrap().fragment = Some(String::from(fragment));
+        id
+    }
+    /// Add a page marked as a redirect, but without a row in redirect.sql
+    pub fn redirect_without_target(&mut self, namespace: i32, title: &str) -> u32 {
+        self.push(namespace, title, Some(None))
+    }
+    /// Leave `id` out of page.sql (and redirect.sql); links from it are still written
+    pub fn delete(&mut self, id: u32) {
+        if let Some(p) = self.get_mut(id) {
+            p.deleted = true;
+        }
+    }
+    /// Link from `from` (which doesn't have to be a page) to `title` in `namespace`
+    pub fn link(&mut self, from: u32, namespace: i32, title: &str) {
+        self.links.push((from, namespace, String::from(title)));
+    }
+    /// Link from `from` to the title of `to`
+    pub fn link_to(&mut self, from: u32, to: u32) {
+        let (ns, title) = {
+            let p = self.get(to).expect("Linked to a page that isn't in the wiki");
+            (p.namespace, p.title.clone())
+        };
+        self.link(from, ns, &title);
+    }
+    /// Give `id` a different title; links to the old one are left alone
+    pub fn rename(&mut self, id: u32, title: &str) {
+        if let Some(p) = self.get_mut(id) {
+            p.title = String::from(title);
+        }
+    }
+    /// Transclude `template` (a page in the template namespace) in `page`
+    /// Like on the real wiki, the template's links are written into pagelinks.sql as if
+    /// they were the page's own, and templatelinks.sql says which templates it has.
+    pub fn transclude(&mut self, page: u32, template: u32) {
+        self.transclusions.push((page, template));
+    }
+    /// Put `page` in the category `category` (its title, without the prefix)
+    pub fn categorize(&mut self, page: u32, category: &str) {
+        self.categories.push((page, String::from(category)));
+    }
+    /// Flag `page` as a disambiguation page
+    pub fn disambiguation(&mut self, page: u32) {
+        self.disambiguations.insert(page);
+    }
+    /// Say that `page` is `title` (as shown there, e.g. with spaces) in the `lang` edition
+    pub fn langlink(&mut self, page: u32, lang: &str, title: &str) {
+        self.langlinks.push((page, String::from(lang), String::from(title)));
+    }
+    /// Take out the links from `from` to `title` in `namespace`
+    pub fn unlink(&mut self, from: u32, namespace: i32, title: &str) {
+        self.links.retain(|&(f, ns, ref t)| (f, ns, t.as_str()) != (from, namespace, title));
+    }
+    /// Make random changes, like the ones between one month's dumps and the next: a few 
+    /// pages renamed, deleted, added, or turned into redirects, and links added and removed
+    pub fn edit(&mut self, seed: u32) {
+        let mut rng = XorShiftRng::from_seed([seed, 0x27D4_EB2F, 0x1656_67B1, 0x9E37_79B9]);
+        let n = self.pages.len();
+        for i in 0..n {
+            match rng.gen_range(0, 100) {
+                0 => {
+                    let (id, title) = (self.pages[i].id, self.pages[i].title.clone());
+                    self.rename(id, &format!("{}_(renamed)", title));
+                },
+                1 => self.pages[i].deleted = true,
+                2 => {
+                    let target = &self.pages[rng.gen_range(0, n)];
+                    let target = Some((target.namespace, target.title.clone()));
+                    self.pages[i].redirect = Some(target);
+                },
+                _ => {},
+            }
+        }
+        for i in 0..n / 50 {
+            let title = rng.choose(TITLES).unwrap().replace('#', &format!("new_{}", i));
+            self.page(0, &title);
+        }
+        let n = self.pages.len();
+        let links = self.links.len();
+        let mut removed: HashSet<usize> = HashSet::new();
+        for _ in 0..links / 100 {
+            removed.insert(rng.gen_range(0, links));
+        }
+        let mut i = 0;
+        self.links.retain(|_| { i += 1; removed.contains(&(i - 1)) == false });
+        for _ in 0..links / 100 {
+            let from = self.pages[rng.gen_range(0, n)].id;
+            let to = self.pages[rng.gen_range(0, n)].id;
+            self.link_to(from, to);
+        }
+    }
+    /// A random wiki of about `pages` pages (the same one for the same `seed`)
+    pub fn random(pages: usize, seed: u32) -> Wiki {
+        let mut rng = XorShiftRng::from_seed([seed, 0x9E37_79B9, 0x85EB_CA6B, 0xC2B2_AE35]);
+        let mut wiki = Wiki::new();
+        let others = [namespace::CATEGORY, namespace::PORTAL, 4];
+        for i in 0..pages {
+            let ns = if chance(&mut rng, 0.1) { *rng.choose(&others).unwrap() } else { 0 };
+            let title = rng.choose(TITLES).unwrap().replace('#', &i.to_string());
+            wiki.page(ns, &title);
+        }
+        let n = wiki.pages.len();
+        let key_of = |w: &Wiki, i: usize| (w.pages[i].namespace, w.pages[i].title.clone());
+        // redirects to anything (including other redirects, so there are chains and loops)
+        let mut redirects: Vec<usize> = vec![];
+        for i in 0..n {
+            if chance(&mut rng, 0.15) == false {
+                continue;
+            }
+            let target = match rng.gen_range(0, 20) {
+                0 => None,
+                1 => Some((0, format!("Missing_{}", i))),
+                2 => Some(key_of(&wiki, i)),
+                3..=8 if redirects.is_empty() == false => {
+                    let r = *rng.choose(&redirects).unwrap();
+                    Some(key_of(&wiki, r))
+                },
+                _ => Some(key_of(&wiki, rng.gen_range(0, n))),
+            };
+            wiki.pages[i].redirect = Some(target);
+            redirects.push(i);
+        }
+        for i in 0..n {
+            if chance(&mut rng, 0.05) {
+                wiki.pages[i].deleted = true;
+            }
+        }
+        // links from everything (including redirects, deleted pages, and ids that were
+        //  never pages) to mostly other pages
+        let mut seen: HashSet<(u32, i32, String)> = HashSet::new();
+        for i in 0..n + n / 20 {
+            let from = i as u32 + 1;
+            for _ in 0..rng.gen_range(0, 8) {
+                let (ns, title) = match rng.gen_range(0, 20) {
+                    0 => (0, format!("Missing_{}", rng.gen_range(0, n))),
+                    1 => (namespace::CATEGORY, key_of(&wiki, rng.gen_range(0, n)).1),
+                    _ => key_of(&wiki, rng.gen_range(0, n)),
+                };
+                if seen.insert((from, ns, title.clone())) {
+                    wiki.link(from, ns, &title);
+                }
+            }
+        }
+        wiki
+    }
+    /// Write page.sql, redirect.sql, and pagelinks.sql into `dir` (along with their
+    /// compressed copies; see `compressed`)
+    pub fn write(&self, dir: &Path) -> io::Result<Dumps> {
+        let mut dumps = self.write_pages(dir)?;
+        self.write_links(&dumps)?;
+        self.write_templates(&mut dumps)?;
+        self.write_categories(&mut dumps)?;
+        self.write_langlinks(&mut dumps)?;
+        self.write_page_props(&mut dumps)?;
+        Ok(dumps)
+    }
+    /// Like `write`, but in the newer layout: pagelinks.sql refers to each link's
+    /// destination by its id in linktarget.sql
+    pub fn write_linktargets(&self, dir: &Path) -> io::Result<Dumps> {
+        let mut dumps = self.write_pages(dir)?;
+        dumps.linktarget = Some(dir.join("linktarget.sql"));
+        self.write_link_targets(&dumps)?;
+        self.write_templates(&mut dumps)?;
+        self.write_categories(&mut dumps)?;
+        self.write_langlinks(&mut dumps)?;
+        self.write_page_props(&mut dumps)?;
+        Ok(dumps)
+    }
+    /// Write the wiki as an XML export (`pages-articles.xml`) into `dir`, along with its
+    /// compressed copies, and return its path
+    /// Each page's links are written into its text in the order they were added. An
+    /// export can't show links from a redirect (its text is only `#REDIRECT [[…]]`) or a
+    /// redirect without a target, so neither is written; leave them out of a wiki whose
+    /// export is compared with its sql dumps.
+    pub fn write_xml(&self, dir: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join("pages-articles.xml");
+        let mut links: HashMap<u32, Vec<(i32, &str)>> = HashMap::new();
+        for &(from, ns, ref title) in &self.links {
+            links.entry(from).or_default().push((ns, title));
+        }
+        // `[[Category:…]]` would put the page in the category rather than link to it
+        let link = |ns: i32, title: &str| {
+            let colon = if ns == namespace::CATEGORY { ":" } else { "" };
+            format!("[[{}{}]]", colon, escape_xml(&title::display(&namespace::key(ns, title))))
+        };
+        let mut w = BufWriter::new(File::create(&path)?);
+        writeln!(w, "<mediawiki xml:lang=\"en\">")?;
+        writeln!(w, "  <siteinfo>\n    <sitename>Synthetic</sitename>\n    <namespaces>")?;
+        let namespaces: BTreeSet<i32> = self.pages.iter().map(|p| p.namespace).collect();
+        for ns in namespaces.into_iter().filter(|&ns| ns != namespace::MAIN) {
+            let name = namespace::name(ns).expect("a namespace without a name");
+            writeln!(w, "      <namespace key=\"{}\" case=\"first-letter\">{}</namespace>",
+                     ns, name.replace('_', " "))?;
+        }
+        writeln!(w, "    </namespaces>\n  </siteinfo>")?;
+        for page in self.pages.iter().filter(|p| p.deleted == false && p.redirect != Some(None)) {
+            let display = escape_xml(&title::display(&page.key()));
+            writeln!(w, "  <page>\n    <title>{}</title>\n    <ns>{}</ns>\n    <id>{}</id>",
+                     display, page.namespace, page.id)?;
+            let text = match page.redirect {
+                Some(Some((ns, ref target))) => {
+                    let shown = title::display(&namespace::key(ns, target));
+                    writeln!(w, "    <redirect title=\"{}\" />", escape_xml(&shown))?;
+                    let target = match page.fragment {
+                        Some(ref f) => format!("{}#{}", target, f),
+                        None => target.clone(),
+                    };
+                    format!("#REDIRECT {}", link(ns, &target))
+                },
+                _ => {
+                    let links = links.get(&page.id).map_or(&[][..], |l| l.as_slice());
+                    let links: Vec<String> = links.iter().map(|&(ns, t)| link(ns, t)).collect();
+                    format!("'''{}''' links to {}.", display, links.join(", "))
+                },
+            };
+            writeln!(w, "    <revision>\n      <id>{}</id>", page.id)?;
+            writeln!(w, "      <text bytes=\"{}\" xml:space=\"preserve\">{}</text>",
+                     text.len(), text)?;
+            writeln!(w, "    </revision>\n  </page>")?;
+        }
+        writeln!(w, "</mediawiki>")?;
+        w.flush()?;
+        write_compressed(&path)?;
+        Ok(path)
+    }
+    // page.sql and redirect.sql
+    fn write_pages(&self, dir: &Path) -> io::Result<Dumps> {
+        fs::create_dir_all(dir)?;
+        let dumps = Dumps::new(dir.join("page.sql"), dir.join("redirect.sql"),
+                               dir.join("pagelinks.sql"));
+        let pages = self.pages.iter().filter(|p| p.deleted == false);
+        write_table(&dumps.page, "page", CREATE_PAGE, pages.clone().map(|p| {
+            format!("({},{},'{}','',{},0,0.5,'20170101000000',NULL,{},{},'wikitext',NULL)",
+                    p.id, p.namespace, escape(&p.title), p.redirect.is_some() as u8,
+                    p.id, p.title.len())
+        }))?;
+        let redirects = pages.filter_map(|p| match p.redirect {
+            Some(Some((ns, ref title))) => Some((p.id, ns, title, &p.fragment)),
+            _ => None,
+        });
+        write_table(&dumps.redirect, "redirect", CREATE_REDIRECT, redirects.map(|(id, ns, t, f)| {
+            let fragment = f.as_ref().map_or(String::from("NULL"), |f| format!("'{}'", escape(f)));
+            format!("({},{},'{}','',{})", id, ns, escape(t), fragment)
+        }))?;
+        Ok(dumps)
+    }
+    // every row of pagelinks.sql: each page's own links and its templates' (sorted)
+    fn pagelinks(&self) -> Vec<(u32, i32, &str)> {
+        let mut links: BTreeSet<(u32, i32, &str)> = self.links.iter()
+            .map(|&(from, ns, ref title)| (from, ns, title.as_str()))
+            .collect();
+        for &(page, template) in &self.transclusions {
+            links.extend(self.links.iter()
+                .filter(|l| l.0 == template)
+                .map(|&(_, ns, ref title)| (page, ns, title.as_str())));
+        }
+        links.into_iter().collect()
+    }
+    // pagelinks.sql in the legacy layout
+    fn write_links(&self, dumps: &Dumps) -> io::Result<()> {
+        let links = self.pagelinks();
+        write_table(&dumps.pagelinks, "pagelinks", CREATE_PAGELINKS, links.into_iter().map(|l| {
+            let from_ns = self.get(l.0).map_or(0, |p| p.namespace);
+            format!("({},{},'{}',{})", l.0, l.1, escape(l.2), from_ns)
+        }))
+    }
+    // templatelinks.sql (in the legacy layout), if any templates are transcluded
+    fn write_templates(&self, dumps: &mut Dumps) -> io::Result<()> {
+        if self.transclusions.is_empty() {
+            return Ok(());
+        }
+        let path = dumps.page.with_file_name("templatelinks.sql");
+        let mut rows: Vec<(u32, &SyntheticPage)> = self.transclusions.iter()
+            .map(|&(page, t)| (page, self.get(t).expect("Transcluded a page that isn't there")))
+            .collect();
+        rows.sort_by_key(|&(page, t)| (page, t.id));
+        write_table(&path, "templatelinks", CREATE_TEMPLATELINKS, 
+                    rows.into_iter().map(|(page, t)| {
+            let from_ns = self.get(page).map_or(0, |p| p.namespace);
+            format!("({},{},'{}',{})", page, t.namespace, escape(&t.title), from_ns)
+        }))?;
+        dumps.templatelinks = Some(path);
+        Ok(())
+    }
+    // categorylinks.sql (in the legacy layout), if any pages are in categories
+    fn write_categories(&self, dumps: &mut Dumps) -> io::Result<()> {
+        if self.categories.is_empty() {
+            return Ok(());
+        }
+        let path = dumps.page.with_file_name("categorylinks.sql");
+        let mut rows: Vec<&(u32, String)> = self.categories.iter().collect();
+        rows.sort();
+        write_table(&path, "categorylinks", CREATE_CATEGORYLINKS, rows.into_iter().map(|c| {
+            format!("({},'{}','{}','2017-01-01 00:00:00','','uppercase','page')",
+                    c.0, escape(&c.1), escape(&c.1.to_uppercase()))
+        }))?;
+        dumps.categorylinks = Some(path);
+        Ok(())
+    }
+    // page_props.sql, if any pages are disambiguation pages (every page has a wikidata
+    //  id too, like on the site)
+    fn write_page_props(&self, dumps: &mut Dumps) -> io::Result<()> {
+        if self.disambiguations.is_empty() {
+            return Ok(());
+        }
+        let path = dumps.page.with_file_name("page_props.sql");
+        let pages = self.pages.iter().filter(|p| p.deleted == false);
+        write_table(&path, "page_props", CREATE_PAGE_PROPS, pages.flat_map(|p| {
+            let mut props = vec![];
+            if self.disambiguations.contains(&p.id) {
+                props.push(format!("({},'disambiguation','',NULL)", p.id));
+            }
+            props.push(format!("({},'wikibase_item','Q{}',NULL)", p.id, p.id));
+            props
+        }))?;
+        dumps.page_props = Some(path);
+        Ok(())
+    }
+    // langlinks.sql, if any pages have langlinks
+    fn write_langlinks(&self, dumps: &mut Dumps) -> io::Result<()> {
+        if self.langlinks.is_empty() {
+            return Ok(());
+        }
+        let path = dumps.page.with_file_name("langlinks.sql");
+        let mut rows: Vec<&(u32, String, String)> = self.langlinks.iter().collect();
+        rows.sort();
+        write_table(&path, "langlinks", CREATE_LANGLINKS, rows.into_iter().map(|l| {
+            format!("({},'{}','{}')", l.0, escape(&l.1), escape(&l.2))
+        }))?;
+        dumps.langlinks = Some(path);
+        Ok(())
+    }
+    // pagelinks.sql and linktarget.sql in the newer layout
+    fn write_link_targets(&self, dumps: &Dumps) -> io::Result<()> {
+        // one target for each destination, numbered in order
+        let pagelinks = self.pagelinks();
+        let targets: BTreeSet<(i32, &str)> = pagelinks.iter()
+            .map(|&(_, ns, title)| (ns, title))
+            .collect();
+        let ids: HashMap<(i32, &str), usize> = targets.iter().enumerate()
+            .map(|(i, &target)| (target, i + 1))
+            .collect();
+        let linktarget = dumps.linktarget.as_ref().expect("no linktarget.sql to write");
+        write_table(linktarget, "linktarget", CREATE_LINKTARGET, 
+                    targets.iter().map(|&(ns, title)| {
+            format!("({},{},'{}')", ids[&(ns, title)], ns, escape(title))
+        }))?;
+        let mut links: Vec<(u32, usize)> = pagelinks.iter()
+            .map(|&(from, ns, title)| (from, ids[&(ns, title)]))
+            .collect();
+        links.sort();
+        write_table(&dumps.pagelinks, "pagelinks", CREATE_TARGET_PAGELINKS, 
+                    links.into_iter().map(|(from, target)| {
+            let from_ns = self.get(from).map_or(0, |p| p.namespace);
+            format!("({},{},{})", from, from_ns, target)
+        }))
+    }
+    /// What parsing this wiki's dumps (keeping `namespaces`) should end up with
+    pub fn expected(&self, namespaces: &Namespaces) -> Expected {
+        // what's in page.sql (and kept)
+        let kept: HashMap<u32, &SyntheticPage> = self.pages.iter()
+            .filter(|p| p.deleted == false && namespaces.contains(p.namespace))
+            .map(|p| (p.id, p))
+            .collect();
+        let addresses: HashMap<String, u32> = kept.values().map(|p| (p.key(), p.id)).collect();
+        // the page that following redirects from `id` ends up at
+        let resolve = |id: u32| -> Option<u32> {
+            let mut seen: HashSet<u32> = HashSet::new();
+            let mut cur = *kept.get(&id)?;
+            loop {
+                let next = match cur.redirect {
+                    None => return Some(cur.id),
+                    Some(Some((ns, ref title))) if namespaces.contains(ns) =>
+                        *addresses.get(&*namespace::key(ns, title))?,
+                    Some(_) => return None,
+                };
+                if seen.insert(next) == false {
+                    return None;
+                }
+                cur = kept[&next];
+            }
+        };
+
+        let mut expected = Expected::default();
+        for p in kept.values().filter(|p| p.redirect.is_none()) {
+            expected.pages.insert(p.id, ExpectedPage { title: p.key(), children: BTreeSet::new() });
+        }
+        for (key, &id) in &addresses {
+            match resolve(id) {
+                Some(page) => { expected.titles.insert(key.clone(), page); },
+                None => { expected.dropped_titles.insert(key.clone()); },
+            }
+        }
+        let dst = |ns: i32, title: &str| {
+            addresses.get(&*namespace::key(ns, title)).and_then(|&d| resolve(d))
+        };
+        for &(from, ns, title) in &self.pagelinks() {
+            if let (Some(src), Some(dst)) = (resolve(from), dst(ns, title)) {
+                if let Some(page) = expected.pages.get_mut(&src) {
+                    page.children.insert(dst);
+                }
+            }
+        }
+        // a page's links to anywhere its templates link to are left out, even if it
+        //  also links there itself (see `database::templates`)
+        for &(page, template) in &self.transclusions {
+            let is_template = self.get(template)
+                .is_some_and(|t| t.deleted == false && t.namespace == namespace::TEMPLATE);
+            if let (Some(page), true) = (expected.pages.get_mut(&page), is_template) {
+                for &(_, ns, ref title) in self.links.iter().filter(|l| l.0 == template) {
+                    if let Some(dst) = dst(ns, title) {
+                        page.children.remove(&dst);
+                    }
+                }
+            }
+        }
+        expected
+    }
+}
@@ -0,0 +1,176 @@
+// Building the database from the XML export (`pages-articles.xml`) instead of the sql dumps
+// The export has each page's wikitext, so its links are found in the text itself (see
+//  `wikitext`), which keeps the order they're written in. Otherwise it's put together the
+//  same way as from the sql dumps:
+//
+//      pass 1:     pages (its `<title>` and `<ns>`), then redirects (its `<redirect>`)
+//      pass 2:     links, resolved through redirects like pagelinks are
+//
+// The export is read twice rather than held in memory, since its text is most of it.
+// Links to categories and files (without a leading `:`) put the page in a category or
+//  show the file rather than linking to it, and links with an interwiki prefix (e.g.
+//  `[[wikt:physics]]` or `[[fr:Physique]]`) lead off the wiki, so both are skipped.
+
+use slog;
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+pub mod reader;
+pub mod wikitext;
+use self::reader::{XmlReader, XmlPage, FILE, MEDIA};
+use super::parse::ParseError;
+use super::parse::database::Database;
+use super::parse::report::{ParseReport, DropReason};
+use super::parse::tables::{PageRow, RedirectRow, PageLinkRow};
+use link_state::link_order::{LinkOrder, PageLinkOrder};
+use link_state::namespace::{self, Namespaces};
+use link_state::title;
+
+// a lowercase prefix that isn't a namespace is an interwiki or language link
+fn is_interwiki(target: &str) -> bool {
+    match target.trim_start().find(':') {
+        Some(i) => {
+            let prefix = target.trim_start()[..i].trim();
+            prefix.is_empty() == false
+                && prefix.chars().all(|c| c.is_ascii_lowercase() || c == '-')
+        },
+        None => false,
+    }
+}
+
+// the key of a link's target, or `None` if it isn't a link to a page
+fn link_key<R: ::std::io::BufRead>(xml: &XmlReader<R>, link: &wikitext::WikiLink)
+    -> Option<String>
+{
+    let target = reader::unescape(link.target);
+    let key = title::key_with(&target, |p| xml.namespace(p));
+    let (ns, _) = namespace::split(&key);
+    let is_interwiki = ns == namespace::MAIN && is_interwiki(target.trim_start_matches(':'));
+    let is_embed = link.colon == false &&
+        (ns == namespace::CATEGORY || ns == FILE || ns == MEDIA);
+    if is_interwiki || is_embed || key.is_empty() {
+        None
+    } else {
+        Some(key)
+    }
+}
+
+pub fn populate_db(xml: &Path, namespaces: Namespaces, log: slog::Logger)
+    -> Result<(Database, ParseReport, LinkOrder), ParseError>
+{
+    let mut db = Database::new(log, namespaces);
+
+    // pass 1: pages and redirects
+    let mut r = XmlReader::open(xml)?;
+    let (mut pages, mut attempted) = (0, 0);
+    // Redirect →  (Key of its target, Fragment)
+    let mut redirects: Vec<(u32, String, Option<String>)> = vec![];
+    while let Some(page) = r.next_page()? {
+        if db.includes(page.ns) == false {
+            db.report().drop(DropReason::OtherNamespace, &page.id);
+            continue;
+        }
+        let target = redirect_target(&r, &page);
+        let row = PageRow {
+            id:             page.id,
+            namespace:      page.ns,
+            // as in page.sql, apart from the spaces (see `link_state::title`)
+            title:          Cow::Owned(page.local_title().replace(' ', "_")),
+            is_redirect:    target.is_some(),
+            metadata:       None,
+        };
+        attempted += 1;
+        if db.add_page(&row) {
+            pages += 1;
+            if let Some((key, fragment)) = target {
+                redirects.push((page.id, key, fragment));
+            }
+        }
+    }
+    db.log(format!("Number of page entries: {} / {}", pages, attempted));
+    db.report().rows("page", pages, attempted);
+    let mut added = 0;
+    for &(from, ref key, ref fragment) in &redirects {
+        let (ns, title) = namespace::split(key);
+        let row = RedirectRow {
+            from,
+            namespace:  ns,
+            title:      Cow::Borrowed(title),
+            fragment:   fragment.as_ref().map(|f| Cow::Borrowed(f.as_str())),
+        };
+        if db.add_redirect(&row) {
+            added += 1;
+        }
+    }
+    db.log(format!("Number of redirects: {} / {}", added, redirects.len()));
+    db.report().rows("redirect", added, redirects.len() as u64);
+
+    // pass 2: links
+    let mut r = XmlReader::open(xml)?;
+    db.begin_links();
+    let (mut links, mut attempted) = (0, 0);
+    // Page  →  (Link, Position, In the body) in order (with repeats)
+    let mut order: HashMap<u32, Vec<(u32, u32, bool)>> = HashMap::new();
+    while let Some(page) = r.next_page()? {
+        // (a redirect's only link is to its target)
+        if db.includes(page.ns) == false || redirect_target(&r, &page).is_some() {
+            continue;
+        }
+        for link in wikitext::links(&page.text) {
+            let key = match link_key(&r, &link) {
+                Some(key) => key,
+                None => continue,
+            };
+            let (ns, title) = namespace::split(&key);
+            let row = PageLinkRow::Title { from: page.id, namespace: ns, title: title.into() };
+            attempted += 1;
+            if db.includes(ns) == false {
+                db.report().drop(DropReason::OtherNamespace, &row);
+                continue;
+            }
+            let (offset, body) = (link.offset as u32, link.body);
+            match db.resolve_link(&row) {
+                Ok(link) => {
+                    order.entry(link.src).or_default().push((link.dst, offset, body));
+                    db.add_link(link);
+                    links += 1;
+                },
+                Err(reason) => db.drop_link(reason, &row),
+            }
+        }
+    }
+    db.log(format!("Number of links: {} / {}", links, attempted));
+    db.report().rows("pagelinks", links, attempted);
+    db.finalize();
+
+    // only the first of each link counts (and a link to the page itself can't be first)
+    let order = order.into_iter().map(|(id, mut links)| {
+        let first = links.iter().find(|&&(l, _, body)| body && l != id).map(|&(l, _, _)| l);
+        let mut seen = HashSet::new();
+        links.retain(|&(l, _, _)| seen.insert(l));
+        PageLinkOrder {
+            id,
+            links: links.iter().map(|&(l, _, _)| l).collect(),
+            positions: links.iter().map(|&(_, p, _)| p).collect(),
+            first,
+        }
+    }).collect();
+    let report = db.take_report();
+    Ok((db, report, LinkOrder::new(order)))
+}
+
+// the key of the page's redirect target and its fragment, if it's a redirect
+fn redirect_target<R: ::std::io::BufRead>(xml: &XmlReader<R>, page: &XmlPage)
+    -> Option<(String, Option<String>)>
+{
+    let from_text = wikitext::redirect(&page.text);
+    let (target, fragment) = match (page.redirect.as_ref(), from_text) {
+        (Some(t), f) => (Cow::Borrowed(t.as_str()), f.and_then(|(_, f)| f)),
+        (None, Some((t, f))) => (reader::unescape(t), f),
+        (None, None) => return None,
+    };
+    Some((title::key_with(&target, |p| xml.namespace(p)),
+          fragment.map(|f| reader::unescape(f).into_owned())))
+}
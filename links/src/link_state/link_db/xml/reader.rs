@@ -0,0 +1,254 @@
+// Streaming the pages out of a MediaWiki XML export (https://www.mediawiki.org/xml/)
+// Only the few elements we need are picked out; everything else is skipped over:
+//
+//      <siteinfo> … <namespace key="14" case="first-letter">Category</namespace> …
+//      <page>
+//          <title>Category:Physics</title>  <ns>14</ns>  <id>123</id>
+//          <redirect title="…" />                      (only for redirects)
+//          <revision> <id>…</id> … <text …>wikitext</text> </revision>
+//      </page>
+//
+// Text never contains a `<` (it's escaped as `&lt;`), so the dump is read as alternating
+//  runs of text and tags. With more than one revision per page, the last one's text is kept.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use link_state::link_db::parse::{ParseError, ErrorKind, Position};
+use link_state::link_db::parse::reader;
+use link_state::namespace;
+
+// namespaces that aren't in `namespace` but are linked to differently
+pub const MEDIA: i32 = -2;
+pub const FILE: i32 = 6;
+
+/// A page as it appears in the dump
+#[derive(Debug, Clone, Default)]
+pub struct XmlPage {
+    pub id: u32,
+    pub ns: i32,
+    // as shown on the wiki, e.g. `Category:Physics`
+    pub title: String,
+    // the title given by `<redirect>`, if it's a redirect
+    pub redirect: Option<String>,
+    pub text: String,
+}
+
+impl XmlPage {
+    /// The title without its namespace prefix
+    pub fn local_title(&self) -> &str {
+        match (self.ns, self.title.find(':')) {
+            (namespace::MAIN, _) | (_, None) => &self.title,
+            (_, Some(i)) => &self.title[i+1..],
+        }
+    }
+}
+
+// the elements whose text we keep
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Title,
+    Ns,
+    Id,
+    Text,
+    // a namespace's name in <siteinfo>, with its id
+    Namespace(i32),
+}
+
+pub struct XmlReader<R: BufRead> {
+    file: PathBuf,
+    r: R,
+    // where the next byte will be read from
+    pos: Position,
+    buf: Vec<u8>,
+    // Name (lower case, with underscores) → Namespace, from <siteinfo>
+    namespaces: HashMap<String, i32>,
+}
+
+impl XmlReader<Box<dyn BufRead>> {
+    /// Open an export for reading, transparently decompressing `.gz` and `.bz2` files
+    pub fn open(path: &Path) -> Result<XmlReader<Box<dyn BufRead>>, ParseError> {
+        let r = reader::open_dump(path).map_err(|e| ParseError::at_start(path, e.into()))?;
+        Ok(XmlReader::new(path, r))
+    }
+}
+
+impl<R: BufRead> XmlReader<R> {
+    pub fn new(path: &Path, r: R) -> XmlReader<R> {
+        XmlReader {
+            file: path.to_path_buf(),
+            r,
+            pos: Position { line: 1, offset: 0 },
+            buf: vec![],
+            namespaces: HashMap::new(),
+        }
+    }
+    /// The namespace called `prefix` on this wiki (or one of the canonical names)
+    pub fn namespace(&self, prefix: &str) -> Option<i32> {
+        let name = prefix.trim().replace(' ', "_").to_lowercase();
+        match self.namespaces.get(&name) {
+            Some(&ns) => Some(ns),
+            None if name == "image" => Some(FILE),
+            None if name == "media" => Some(MEDIA),
+            None => namespace::from_name(&name),
+        }
+    }
+    fn error(&self, kind: ErrorKind) -> ParseError {
+        ParseError::new(&self.file, self.pos, kind)
+    }
+    fn read_until(&mut self, delim: u8) -> Result<bool, ParseError> {
+        // read up to `delim` into `buf` (without it); false if the dump ended first
+        self.buf.clear();
+        let len = self.r.read_until(delim, &mut self.buf)
+            .map_err(|e| ParseError::new(&self.file, self.pos, e.into()))?;
+        self.pos.offset += len as u64;
+        self.pos.line += self.buf.iter().filter(|&&b| b == b'\n').count() as u64;
+        if self.buf.last() == Some(&delim) {
+            self.buf.pop();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+    /// The next page in the dump, or `None` at the end of it
+    pub fn next_page(&mut self) -> Result<Option<XmlPage>, ParseError> {
+        let mut page: Option<XmlPage> = None;
+        let mut in_revision = false;
+        let mut field: Option<Field> = None;
+        let mut value: Vec<u8> = vec![];
+        loop {
+            // the text before the next tag belongs to whichever element is open
+            let more = self.read_until(b'<')?;
+            if field.is_some() {
+                value.extend_from_slice(&self.buf);
+            }
+            if more == false {
+                return match page {
+                    Some(_) => Err(self.error(ErrorKind::Expected("`</page>`"))),
+                    None => Ok(None),
+                };
+            }
+            if self.read_until(b'>')? == false {
+                return Err(self.error(ErrorKind::Expected("`>`")));
+            }
+            let tag = String::from_utf8_lossy(&self.buf).into_owned();
+            // comments, declarations, etc.
+            if tag.starts_with('?') || tag.starts_with('!') {
+                continue;
+            }
+            let (closing, empty) = (tag.starts_with('/'), tag.ends_with('/'));
+            let tag = tag.trim_start_matches('/').trim_end_matches('/');
+            let (name, attrs) = match tag.find(char::is_whitespace) {
+                Some(i) => (&tag[..i], &tag[i..]),
+                None => (tag, ""),
+            };
+
+            if closing {
+                let done = match (field, name) {
+                    (Some(Field::Title), "title") | (Some(Field::Ns), "ns") |
+                    (Some(Field::Id), "id") | (Some(Field::Text), "text") |
+                    (Some(Field::Namespace(_)), "namespace") => field.take(),
+                    _ => None,
+                };
+                let text = unescape(&String::from_utf8_lossy(&value)).into_owned();
+                match (done, page.as_mut()) {
+                    (Some(Field::Namespace(ns)), _) => {
+                        self.namespaces.insert(text.replace(' ', "_").to_lowercase(), ns);
+                    },
+                    (Some(Field::Title), Some(p)) => p.title = text,
+                    (Some(Field::Ns), Some(p)) => p.ns = text.trim().parse()
+                        .map_err(|_| self.error(ErrorKind::Expected("a namespace in `<ns>`")))?,
+                    (Some(Field::Id), Some(p)) => p.id = text.trim().parse()
+                        .map_err(|_| self.error(ErrorKind::Expected("a page id in `<id>`")))?,
+                    (Some(Field::Text), Some(p)) => p.text = text,
+                    _ => {},
+                }
+                match name {
+                    "revision" => in_revision = false,
+                    "page" => match page {
+                        Some(p) => return Ok(Some(p)),
+                        None => return Err(self.error(ErrorKind::Expected("`<page>`"))),
+                    },
+                    _ => {},
+                }
+                continue;
+            }
+
+            value.clear();
+            match (name, page.as_mut()) {
+                ("page", None) => page = Some(XmlPage::default()),
+                ("page", Some(_)) => return Err(self.error(ErrorKind::Expected("`</page>`"))),
+                ("revision", Some(_)) => in_revision = true,
+                ("redirect", Some(p)) =>
+                    p.redirect = Some(attr(attrs, "title").unwrap_or_default().into_owned()),
+                ("title", Some(_)) => field = Some(Field::Title),
+                ("ns", Some(_)) => field = Some(Field::Ns),
+                // revisions (and their contributors) have ids too
+                ("id", Some(_)) if in_revision == false => field = Some(Field::Id),
+                ("text", Some(p)) => {
+                    p.text.clear();
+                    field = Some(Field::Text);
+                },
+                ("namespace", None) => {
+                    field = attr(attrs, "key").and_then(|k| k.parse().ok()).map(Field::Namespace);
+                },
+                _ => {},
+            }
+            // e.g. `<text bytes="0" />` for a page whose text was removed
+            if empty {
+                field = None;
+            }
+        }
+    }
+}
+
+fn attr<'a>(attrs: &'a str, name: &str) -> Option<Cow<'a, str>> {
+    // the value of ` name="value"`
+    let start = attrs.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let len = attrs[start..].find('"')?;
+    Some(unescape(&attrs[start..start+len]))
+}
+
+/// Decode XML/HTML character references, e.g. `&lt;` → `<`, `&#39;` → `'`
+/// (wikitext can have them in link targets too)
+pub fn unescape(s: &str) -> Cow<'_, str> {
+    if s.contains('&') == false {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let c = match &rest[1..end] {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "amp" => Some('&'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                e if e.starts_with("#x") || e.starts_with("#X") =>
+                    u32::from_str_radix(&e[2..], 16).ok().and_then(::std::char::from_u32),
+                e if e.starts_with('#') =>
+                    e[1..].parse().ok().and_then(::std::char::from_u32),
+                _ => None,
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end+1..];
+            },
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
@@ -0,0 +1,181 @@
+// Links in a page's wikitext (https://en.wikipedia.org/wiki/Help:Link)
+// Only `[[Target|caption]]`s written into the page itself are found; links that come
+//  from templates aren't in its wikitext at all (so there's nothing to leave out, unlike
+//  with pagelinks.sql). Parts of the text that aren't rendered as wikitext (comments,
+//  `<nowiki>`, and `<pre>`) are skipped.
+// Links are returned in the order they appear in the text, including links within
+//  captions (e.g. `[[File:X.png|thumb|A [[map]] of …]]`).
+// Each link also says whether it's in the body of the article: outside of templates
+//  (infoboxes, hatnotes, …), tables, references, captions of other links, and parentheses.
+//  That's what "the first link" means in https://en.wikipedia.org/wiki/Wikipedia:Getting_to_Philosophy
+//  (which also leaves out italics; they aren't tracked here).
+
+/// A `[[…]]` as written in the text
+#[derive(Debug, Clone, PartialEq)]
+pub struct WikiLink<'a> {
+    // before any `#` (not yet a key; see `title::key_with`)
+    pub target: &'a str,
+    // after the `#`, if any
+    pub fragment: Option<&'a str>,
+    // byte offset of the `[[` in the text
+    pub offset: usize,
+    // `[[:Category:X]]` links to a category rather than adding the page to it
+    pub colon: bool,
+    // in the article's prose (see above)
+    pub body: bool,
+}
+
+// what the text being scanned is inside of
+#[derive(Debug, Clone, Copy, Default)]
+struct Depths {
+    templates: u32,
+    tables: u32,
+    refs: u32,
+    // links whose caption hasn't been closed
+    links: u32,
+    parens: u32,
+}
+
+impl Depths {
+    fn is_body(&self) -> bool {
+        self.templates == 0 && self.tables == 0 && self.refs == 0 
+            && self.links == 0 && self.parens == 0
+    }
+}
+
+// characters that can't be in a title (so the brackets weren't a link)
+fn is_link_target(target: &str) -> bool {
+    target.trim().is_empty() == false
+        && target.contains(['[', ']', '{', '}', '<', '>', '\n']) == false
+}
+
+fn split_target(target: &str) -> (&str, Option<&str>) {
+    match target.find('#') {
+        Some(i) => (&target[..i], Some(target[i+1..].trim()).filter(|f| f.is_empty() == false)),
+        None => (target, None),
+    }
+}
+
+// (open, close) of the parts of the text that aren't wikitext
+const SKIPPED: &[(&str, &str)] = &[
+    ("<!--", "-->"),
+    ("<nowiki>", "</nowiki>"),
+    ("<pre>", "</pre>"),
+    ("<pre ", "</pre>"),
+];
+
+/// Every link in `text`, in order
+pub fn links(text: &str) -> Vec<WikiLink<'_>> {
+    let mut links = vec![];
+    let mut depths = Depths::default();
+    let mut i = 0;
+    while let Some(j) = text[i..].find(['[', ']', '<', '{', '}', '|', '(', ')', '\n']) {
+        i += j;
+        let rest = &text[i..];
+        // (only the parentheses in prose count, and they end with the paragraph)
+        let prose = depths.templates == 0 && depths.links == 0;
+        // tables open and close at the start of a line (`|}` can end a template's argument)
+        let line_start = i == 0 || text.as_bytes()[i-1] == b'\n';
+        let step = match rest.as_bytes()[0] {
+            b'{' if rest.starts_with("{{") => { depths.templates += 1; 2 },
+            b'}' if rest.starts_with("}}") => { sub(&mut depths.templates); 2 },
+            b'{' if line_start && rest.starts_with("{|") => { depths.tables += 1; 2 },
+            b'|' if line_start && rest.starts_with("|}") => { sub(&mut depths.tables); 2 },
+            b']' if rest.starts_with("]]") => { sub(&mut depths.links); 2 },
+            b'(' if prose => { depths.parens += 1; 1 },
+            b')' if prose => { sub(&mut depths.parens); 1 },
+            b'\n' if rest.starts_with("\n\n") => { depths.parens = 0; 1 },
+            b'<' if is_tag(rest, "<ref") => {
+                // `<ref name="x" />` reuses a reference without opening one
+                match rest.find('>') {
+                    Some(k) if rest[..k].ends_with('/') => {},
+                    _ => depths.refs += 1,
+                }
+                4
+            },
+            b'<' if is_tag(rest, "</ref") => { sub(&mut depths.refs); 5 },
+            b'<' | b'[' => 0,
+            _ => 1,
+        };
+        if step > 0 {
+            i += step;
+            continue;
+        }
+        if rest.starts_with('<') {
+            let skipped = SKIPPED.iter().find(|&&(open, _)| starts_with_ignore_case(rest, open));
+            i += match skipped {
+                // an unclosed comment hides the rest of the page
+                Some(&(open, close)) => match find_ignore_case(&rest[open.len()..], close) {
+                    Some(k) => open.len() + k + close.len(),
+                    None => rest.len(),
+                },
+                None => 1,
+            };
+            continue;
+        }
+        if rest.starts_with("[[") == false {
+            i += 1;
+            continue;
+        }
+        // the target ends at the caption or the closing brackets
+        let inner = &rest[2..];
+        let end = match inner.find("]]") {
+            Some(b) => inner[..b].find('|').unwrap_or(b),
+            None => break,
+        };
+        let target = &inner[..end];
+        if is_link_target(target) {
+            let colon = target.trim_start().starts_with(':');
+            let (target, fragment) = split_target(target);
+            // `[[#Section]]` is a link within the same page
+            if target.trim().is_empty() == false {
+                let body = depths.is_body();
+                links.push(WikiLink { target, fragment, offset: i, colon, body });
+            }
+            if inner.as_bytes()[end] == b'|' {
+                // its caption can have links (and parentheses) of its own
+                depths.links += 1;
+                i += 2 + end + 1;
+            } else {
+                i += 2 + end + 2;
+            }
+        } else {
+            // e.g. `[[[x]]]`: look for a link from the next bracket
+            i += 1;
+        }
+    }
+    links
+}
+
+fn sub(depth: &mut u32) {
+    *depth = depth.saturating_sub(1);
+}
+
+fn starts_with_ignore_case(s: &str, prefix: &str) -> bool {
+    s.len() >= prefix.len() && s.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+}
+
+// e.g. `<ref>` or `<ref name="x">`, but not `<references />`
+fn is_tag(s: &str, name: &str) -> bool {
+    starts_with_ignore_case(s, name) 
+        && s[name.len()..].starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace())
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack.as_bytes().windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// The target (and fragment) of a redirect page, e.g. `#REDIRECT [[Target#Section]]`
+/// (the dump's `<redirect title=…>` doesn't keep the fragment)
+pub fn redirect(text: &str) -> Option<(&str, Option<&str>)> {
+    let text = text.trim_start();
+    const MAGIC: &str = "#redirect";
+    if starts_with_ignore_case(text, MAGIC) == false {
+        return None;
+    }
+    let rest = text[MAGIC.len()..].trim_start();
+    let rest = rest.strip_prefix(':').unwrap_or(rest).trim_start();
+    let link = links(rest).into_iter().next().filter(|l| l.offset == 0)?;
+    Some((link.target, link.fragment))
+}
@@ -0,0 +1,58 @@
+// The order of each page's links (from the XML export)
+// Entries keep their children in no particular order, which loses where each link was in
+//  the article. When the links come from the wikitext itself (see `link_db::xml`), each
+//  page's links are also kept on the side in the order they first appear, along with
+//  where they are and which one is the first link in the article's body (see
+//  `wikitext::WikiLink.body` and `first_links`).
+
+/// A page's links in the order they first appear in its text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageLinkOrder {
+    pub id: u32,
+    pub links: Vec<u32>,
+    // byte offset of each link's first appearance in the wikitext
+    pub positions: Vec<u32>,
+    // the first link in the article's prose, if it has one
+    pub first: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LinkOrder {
+    // sorted by id
+    pages: Vec<PageLinkOrder>,
+}
+
+impl LinkOrder {
+    pub fn new(mut pages: Vec<PageLinkOrder>) -> LinkOrder {
+        pages.sort_by_key(|p| p.id);
+        pages.dedup_by_key(|p| p.id);
+        LinkOrder { pages }
+    }
+    /// Number of pages with links
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+    /// Every page's links (sorted by id)
+    pub fn iter(&self) -> ::std::slice::Iter<'_, PageLinkOrder> {
+        self.pages.iter()
+    }
+    pub fn get(&self, id: u32) -> Option<&PageLinkOrder> {
+        self.pages.binary_search_by_key(&id, |p| p.id).ok().map(|i| &self.pages[i])
+    }
+    /// The links of `id`, in the order they appear
+    pub fn links_of(&self, id: u32) -> &[u32] {
+        self.get(id).map_or(&[], |p| &p.links)
+    }
+    /// The links of `id` with where they first appear in its wikitext
+    pub fn positions_of(&self, id: u32) -> Vec<(u32, u32)> {
+        self.get(id).map_or(vec![], |p| p.links.iter().cloned()
+                                          .zip(p.positions.iter().cloned()).collect())
+    }
+    /// The first link in the body of `id`
+    pub fn first_link(&self, id: u32) -> Option<u32> {
+        self.get(id).and_then(|p| p.first)
+    }
+}
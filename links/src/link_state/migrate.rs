@@ -0,0 +1,109 @@
+// Bringing snapshots exported by older versions up to date
+// Each manifest records the `VERSION` of the format it was written in (manifests from
+//  before there was one are version 0). Older snapshots are migrated as they're imported,
+//  so re-exporting one (`--import old.json --output new.json`) saves the migrated copy.
+//
+//  0 → 1:  titles were stored as they appear in the sql dumps, i.e. with mysql's escapes
+//          (`Ender\'s_Game`); they're now decoded (`Ender's_Game`). This applies to
+//          entries, the title fst, and the category and langlink tables.
+
+use fst::{self, Streamer};
+use slog::Logger;
+
+use std::collections::HashMap;
+
+use super::categories::Categories;
+use super::langlinks::{LangLinks, PageLangLinks, LangLink};
+use super::link_data::IndexedEntry;
+use super::link_db::parse::tokenizer;
+use super::Extras;
+
+pub const VERSION: u32 = 1;
+
+// number of problems to log individually
+const SAMPLES: usize = 10;
+
+fn unescape(s: &str) -> String {
+    String::from_utf8_lossy(&tokenizer::unescape(s.as_bytes())).into_owned()
+}
+
+/// Migrate a snapshot written in format `version`; returns the new title fst
+pub fn migrate(version: u32, log: &Logger, dumps: &mut [Vec<IndexedEntry>],
+               titles: Vec<u8>, extras: &mut Extras) -> Vec<u8>
+{
+    if version >= VERSION {
+        return titles;
+    }
+    warn!(log, "Migrating snapshot from format {} to {}", version, VERSION);
+    // 0 → 1
+    let mut renamed = 0usize;
+    for entry in dumps.iter_mut().flat_map(|d| d.iter_mut()) {
+        let title = unescape(&entry.title);
+        if title != entry.title {
+            entry.title = title;
+            renamed += 1;
+        }
+    }
+    info!(log, "Decoded the titles of {} entries", renamed);
+    let titles = unescape_fst(log, titles);
+    if let Some(categories) = extras.categories.take() {
+        let mut members: HashMap<String, Vec<u32>> = HashMap::new();
+        for c in categories.iter() {
+            members.entry(unescape(&c.title)).or_default().extend_from_slice(&c.members);
+        }
+        extras.categories = Some(Categories::from_members(members));
+    }
+    if let Some(langlinks) = extras.langlinks.take() {
+        extras.langlinks = Some(LangLinks::new(langlinks.pages().into_iter().map(|p| {
+            PageLangLinks {
+                id: p.id,
+                links: p.links.into_iter().map(|l| LangLink {
+                    title: unescape(&l.title),
+                    lang: l.lang,
+                }).collect(),
+            }
+        })));
+    }
+    titles
+}
+
+fn unescape_fst(log: &Logger, titles: Vec<u8>) -> Vec<u8> {
+    // keys can sort differently once they're decoded, so the fst has to be rebuilt
+    let map = fst::Map::from_bytes(titles).expect("invalid fst bytes");
+    let mut keys: Vec<(String, u64)> = Vec::with_capacity(map.len());
+    let mut stream = map.stream();
+    while let Some((k, v)) = stream.next() {
+        let k = String::from_utf8_lossy(k);
+        keys.push((unescape(&k), v));
+    }
+    keys.sort();
+    // two titles that only differed by their escapes would now be the same title
+    let mut collisions = 0usize;
+    keys.dedup_by(|b, a| {
+        if a.0 != b.0 {
+            return false;
+        }
+        if a.1 != b.1 {
+            if collisions < SAMPLES {
+                warn!(log, "Title `{}` is now ambiguous: {} or {}", a.0, a.1, b.1);
+            }
+            collisions += 1;
+        }
+        true
+    });
+    if collisions > 0 {
+        warn!(log, "{} titles are now ambiguous; kept the lowest id of each", collisions);
+    }
+    let mut mb = fst::MapBuilder::memory();
+    mb.extend_iter(keys).expect("fst population");
+    mb.into_inner().expect("fst finalize")
+}
+
+/// Titles of entries that don't resolve to themselves in the title fst
+pub fn validate(dumps: &[Vec<IndexedEntry>], titles: &[u8]) -> Vec<String> {
+    let map = fst::Map::from_bytes(titles.to_vec()).expect("invalid fst bytes");
+    dumps.iter().flat_map(|d| d.iter())
+        .filter(|e| map.get(&e.title) != Some(u64::from(e.id)))
+        .map(|e| format!("{} `{}`", e.id, e.title))
+        .collect()
+}
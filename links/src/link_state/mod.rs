@@ -6,8 +6,8 @@ use clap;
 use slog_term;
 use slog::{Logger, DrainExt};
 
-use std::path::{Path as FsPath, PathBuf};
-use std::collections::HashMap;
+use std::path::PathBuf;
+use std::{error, fmt, io};
 
 pub mod link_db;
 pub mod link_data;
@@ -16,6 +16,30 @@ pub mod hash_links;
 
 pub mod bfs;
 pub mod entry;
+pub mod namespace;
+pub mod title;
+pub mod categories;
+pub use self::categories::Categories;
+pub mod langlinks;
+pub use self::langlinks::LangLinks;
+pub mod sections;
+pub use self::sections::Sections;
+pub mod wanted;
+pub use self::wanted::Wanted;
+pub mod page_metadata;
+pub use self::page_metadata::Metadata;
+pub mod link_order;
+pub use self::link_order::LinkOrder;
+pub mod first_links;
+pub use self::first_links::FirstLinks;
+pub mod extras;
+pub use self::extras::Extras;
+pub mod bilingual;
+pub mod update;
+pub use self::update::UpdateSummary;
+pub mod diff;
+pub use self::diff::SnapshotDiff;
+mod migrate;
 pub use self::entry::Entry;
 pub use self::bfs::path::Path;
 
@@ -47,7 +71,10 @@ pub fn new_logger() -> Logger {
 pub struct LinkDb {
     /// Store parsed collection of links from sql dumps
     db: link_db::parse::database::Database,
-    titles: HashMap<String,u32>,
+    // byte repr of the title fst
+    titles: Vec<u8>,
+    report: link_db::parse::ParseReport,
+    extras: Extras,
 }
 
 pub struct LinkData {
@@ -58,12 +85,17 @@ pub struct LinkData {
     //titles: HashMap<String,u32>,
     //titles: fst::Map,
     titles: Vec<u8>, // byte repr of fst
+    // only present if the data was parsed from sql dumps (or exported with it)
+    report: Option<link_db::parse::ParseReport>,
+    // whichever optional tables were parsed (or exported with it)
+    extras: Extras,
 }
 
 pub struct ProcData {
     /// Store easily searchable link and pagerank data
     /// Pagerank data can be read from, dumped to, or exported to disk 
     links: fnv::FnvHashMap<u32,Entry>,
+    extras: Extras,
 }
 
 pub struct HashLinks {
@@ -72,32 +104,171 @@ pub struct HashLinks {
     links: fnv::FnvHashMap<u32,Entry>,
     //_titles: HashMap<u64,u32>,
     titles: fst::Map,
+    extras: Extras,
 }
 
 //  ---------- ARGS ----------
 
+/// Why link data couldn't be loaded from the command line arguments
+#[derive(Debug)]
+pub enum LoadError {
+    /// Neither a manifest nor the sql dumps were given
+    NoInput,
+    /// An argument couldn't be understood
+    Args(String),
+    Parse(link_db::parse::ParseError),
+    Io(io::Error),
+}
+
+impl From<link_db::parse::ParseError> for LoadError {
+    fn from(e: link_db::parse::ParseError) -> LoadError {
+        LoadError::Parse(e)
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> LoadError {
+        LoadError::Io(e)
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::NoInput => write!(f, "The data has to come from somewhere; \
+                                             supply a manifest, 3 sql dumps, \
+                                             or an XML export"),
+            LoadError::Args(ref e) => write!(f, "Bad argument: {}", e),
+            LoadError::Parse(ref e) => write!(f, "Couldn't parse the dumps: {}", e),
+            LoadError::Io(ref e) => write!(f, "Couldn't read or write link data: {}", e),
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            LoadError::NoInput | LoadError::Args(_) => None,
+            LoadError::Parse(ref e) => Some(e),
+            LoadError::Io(ref e) => Some(e),
+        }
+    }
+}
+
+// the namespaces whose pages `args` keeps
+fn namespaces_arg(args: &clap::ArgMatches) -> Result<namespace::Namespaces, LoadError> {
+    match args.value_of("namespaces") {
+        Some(n) => namespace::Namespaces::parse(n).map_err(LoadError::Args),
+        None => Ok(namespace::Namespaces::main()),
+    }
+}
+
+// the sql dumps given by `args`, if any
+fn dumps_arg(args: &clap::ArgMatches) -> Result<Option<link_db::Dumps>, LoadError> {
+    let (p, r, l) = match (args.value_of("page.sql"), 
+                           args.value_of("redirect.sql"), 
+                           args.value_of("pagelinks.sql")) {
+        (Some(p), Some(r), Some(l)) => (p, r, l),
+        _ => return Ok(None),
+    };
+    let mut dumps = link_db::Dumps::new(p, r, l);
+    dumps.linktarget = args.value_of("linktarget.sql").map(PathBuf::from);
+    dumps.templatelinks = args.value_of("templatelinks.sql").map(PathBuf::from);
+    dumps.categorylinks = args.value_of("categorylinks.sql").map(PathBuf::from);
+    dumps.langlinks = args.value_of("langlinks.sql").map(PathBuf::from);
+    dumps.page_props = args.value_of("page_props.sql").map(PathBuf::from);
+    dumps.wanted = args.is_present("wanted");
+    dumps.metadata = args.is_present("metadata");
+    dumps.low_memory = args.is_present("low_memory");
+    if let Some(t) = args.value_of("threads") {
+        dumps.threads = match t.parse() {
+            Ok(threads) if threads > 0 => threads,
+            _ => return Err(LoadError::Args(format!("Not a number of threads: `{}`", t))),
+        };
+    }
+    Ok(Some(dumps))
+}
+
+// parse the dumps (or XML export) given by `args`, if any
+fn parse_args(args: &clap::ArgMatches) -> Result<Option<LinkState<LinkDb>>, LoadError> {
+    let ns = namespaces_arg(args)?;
+    if let Some(dumps) = dumps_arg(args)? {
+        Ok(Some(LinkState::<LinkDb>::new(&dumps, ns)?))
+    } else if let Some(x) = args.value_of("xml") {
+        Ok(Some(LinkState::<LinkDb>::from_xml(&PathBuf::from(x), ns)?))
+    } else {
+        Ok(None)
+    }
+}
+
 // only allow T s.t. can be `from` LinkState<LinkData>
 impl<T: State> LinkState<T> where LinkState<T>: From<LinkState<LinkData>> {
-    pub fn from_args(args: &clap::ArgMatches) -> LinkState<T> {
+    pub fn from_args(args: &clap::ArgMatches) -> Result<LinkState<T>, LoadError> {
         //first, decide whether to build links from source sql or previous backup
-        let ls_dt: LinkState<LinkData> = if let (Some(p), Some(r), Some(l)) = 
-            (args.value_of("page.sql"), 
-             args.value_of("redirect.sql"), 
-             args.value_of("pagelinks.sql")) 
+        let ls_dt: LinkState<LinkData> = if let (Some(m), Some(dumps)) = 
+            (args.value_of("update"), dumps_arg(args)?)
         {
-            LinkState::new(FsPath::new(p), FsPath::new(r), FsPath::new(l))
-                .into()
+            // only rebuild the entries that changed since an earlier snapshot
+            let old = LinkState::<LinkData>::import(PathBuf::from(m), new_logger())?;
+            let (updated, summary) = old.update(&dumps, namespaces_arg(args)?)?;
+            println!("Updated `{}`: {}", m, summary);
+            if args.is_present("verify_update") {
+                let full: LinkState<LinkData> = LinkState::<LinkDb>::new(&dumps, 
+                                                                        namespaces_arg(args)?)?
+                    .into();
+                let differences = updated.differences(&full);
+                for d in differences.iter().take(20) {
+                    println!("Differs from a full parse: {}", d);
+                }
+                println!("{} differences from a full parse", differences.len());
+            }
+            updated
+        } else if let Some(ls_db) = parse_args(args)? {
+            if let (Some(p), Some(redirects)) = 
+                (args.value_of("redirect_report"), ls_db.redirect_report()) 
+            {
+                redirects.export(&PathBuf::from(p)).map_err(io::Error::other)?;
+            }
+            ls_db.into()
         } else if let Some(m) = args.value_of("import") {
-            LinkState::<LinkData>::import(PathBuf::from(m), new_logger()).unwrap()
+            LinkState::<LinkData>::import(PathBuf::from(m), new_logger())?
         } else {
-            panic!("The data has to come from somewhere; {}", 
-                   "supply either a manifest or 3 sql dumps");
+            return Err(LoadError::NoInput);
         };
 
+        if args.is_present("validate") {
+            let problems = ls_dt.validate();
+            for p in problems.iter().take(20) {
+                println!("Doesn't resolve to itself: {}", p);
+            }
+            println!("{} entries with problems", problems.len());
+        }
+        if let Some(m) = args.value_of("diff_with") {
+            let newer = LinkState::<LinkData>::import(PathBuf::from(m), new_logger())?;
+            let diff = ls_dt.diff(&newer);
+            println!("Compared with `{}`: {} pages added, {} deleted, {} renamed; \
+                      {} links added, {} removed (in {} pages)",
+                     m, diff.added.len(), diff.deleted.len(), diff.renamed.len(),
+                     diff.links_added(), diff.links_removed(), diff.links.len());
+            if let Some(n) = args.value_of("most_changed") {
+                let n = n.parse().map_err(|_| LoadError::Args(format!("Not a number: `{}`", n)))?;
+                for l in diff.most_changed(n) {
+                    println!("+{}\t-{}\t{}", l.added.len(), l.removed.len(), l.title);
+                }
+            }
+            // (as csvs if it's named like one)
+            if let Some(p) = args.value_of("diff_output").map(PathBuf::from) {
+                if p.extension().is_some_and(|e| e == "csv") {
+                    diff.export_csv(&p).map_err(io::Error::other)?;
+                } else {
+                    diff.export_json(&p)?;
+                }
+            }
+        }
         if let Some(p) = args.value_of("export") {
-            ls_dt.export(PathBuf::from(p)).unwrap();
+            ls_dt.export(PathBuf::from(p))?;
         }
-        ls_dt.into()
+        Ok(ls_dt.into())
     }
 
     /*
@@ -0,0 +1,132 @@
+// Wiki namespaces (https://www.mediawiki.org/wiki/Manual:Namespace)
+// Only articles (the main namespace) are included by default, but others (e.g. categories
+//  or portals) can be included as nodes too. Pages in different namespaces can share a
+//  title, so a page's key (its title in `Database.addresses`, the title fst, and
+//  `Entry.title`) includes its namespace:
+//
+//      main namespace:     `Physics`           (unchanged, so old snapshots still work)
+//      other namespaces:   `14|Physics`        (`|` can't appear in a title)
+//
+// Keys are shown to (and accepted from) users with the canonical names instead,
+//  e.g. `Category:Physics` (see `title`).
+
+use std::borrow::Cow;
+use std::fmt;
+
+pub const MAIN: i32 = 0;
+pub const TEMPLATE: i32 = 10;
+pub const HELP: i32 = 12;
+pub const CATEGORY: i32 = 14;
+pub const PORTAL: i32 = 100;
+
+const SEPARATOR: char = '|';
+
+// canonical (english) names; prefixes are matched case-insensitively like MediaWiki does
+const NAMES: &[(i32, &str)] = &[
+    (1,   "Talk"),
+    (2,   "User"),
+    (3,   "User_talk"),
+    (4,   "Wikipedia"),
+    (4,   "Project"),
+    (5,   "Wikipedia_talk"),
+    (6,   "File"),
+    (7,   "File_talk"),
+    (8,   "MediaWiki"),
+    (9,   "MediaWiki_talk"),
+    (10,  "Template"),
+    (11,  "Template_talk"),
+    (12,  "Help"),
+    (13,  "Help_talk"),
+    (14,  "Category"),
+    (15,  "Category_talk"),
+    (100, "Portal"),
+    (101, "Portal_talk"),
+    (118, "Draft"),
+    (119, "Draft_talk"),
+];
+
+pub fn name(ns: i32) -> Option<&'static str> {
+    NAMES.iter().find(|&&(i,_)| i == ns).map(|&(_,n)| n)
+}
+
+pub fn from_name(name: &str) -> Option<i32> {
+    NAMES.iter().find(|&&(_,n)| n.eq_ignore_ascii_case(name)).map(|&(i,_)| i)
+}
+
+/// The key of the page `title` in namespace `ns`
+pub fn key(ns: i32, title: &str) -> Cow<'_, str> {
+    if ns == MAIN {
+        Cow::Borrowed(title)
+    } else {
+        Cow::Owned(format!("{}{}{}", ns, SEPARATOR, title))
+    }
+}
+
+/// The namespace and title of a key
+pub fn split(key: &str) -> (i32, &str) {
+    if let Some(i) = key.find(SEPARATOR) {
+        if let Ok(ns) = key[..i].parse() {
+            return (ns, &key[i+1..]);
+        }
+    }
+    (MAIN, key)
+}
+
+/// A key as it'd be written on the wiki, e.g. `Category:Physics`
+pub fn display(key: &str) -> Cow<'_, str> {
+    match split(key) {
+        (MAIN, _) => Cow::Borrowed(key),
+        (ns, title) => match name(ns) {
+            Some(n) => Cow::Owned(format!("{}:{}", n, title)),
+            None => Cow::Borrowed(key),
+        },
+    }
+}
+
+/// Which namespaces to include; articles are always included
+#[derive(Debug, Clone, PartialEq)]
+pub struct Namespaces {
+    ids: Vec<i32>,
+}
+
+impl Default for Namespaces {
+    fn default() -> Namespaces {
+        Namespaces::main()
+    }
+}
+
+impl Namespaces {
+    /// Only articles
+    pub fn main() -> Namespaces {
+        Namespaces { ids: vec![MAIN] }
+    }
+    /// Articles and `others`
+    pub fn with(others: &[i32]) -> Namespaces {
+        let mut ids = vec![MAIN];
+        ids.extend_from_slice(others);
+        ids.sort();
+        ids.dedup();
+        Namespaces { ids }
+    }
+    /// Parse a comma-separated list of namespace ids or names, e.g. `14,Portal,12`
+    pub fn parse(list: &str) -> Result<Namespaces, String> {
+        let ids = list.split(',').map(str::trim).filter(|s| s.is_empty() == false)
+            .map(|s| s.parse().ok().or_else(|| from_name(s))
+                 .ok_or_else(|| format!("Unknown namespace: `{}`", s)))
+            .collect::<Result<Vec<i32>,String>>()?;
+        Ok(Namespaces::with(&ids))
+    }
+    pub fn contains(&self, ns: i32) -> bool {
+        self.ids.contains(&ns)
+    }
+    pub fn ids(&self) -> &[i32] {
+        &self.ids
+    }
+}
+
+impl fmt::Display for Namespaces {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ids: Vec<String> = self.ids.iter().map(|i| i.to_string()).collect();
+        write!(f, "{}", ids.join(","))
+    }
+}
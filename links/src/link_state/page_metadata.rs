@@ -0,0 +1,53 @@
+// Page metadata (from page.sql)
+// page.sql has a few columns about each page besides its title that aren't part of the
+//  link graph; if they're asked for (`Dumps.metadata`), they're kept on the side for each
+//  article, e.g. to leave stubs out of a search or to compare pagerank with length.
+
+/// What page.sql says about an article
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PageMetadata {
+    pub id: u32,
+    // `page_len`: size of the latest revision's wikitext, in bytes
+    pub len: u32,
+    // `page_latest`: id of the latest revision
+    pub latest: u32,
+    // `page_touched`: when the page (or anything it transcludes) last changed,
+    //  as YYYYMMDDHHMMSS
+    pub touched: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    // sorted by id
+    pages: Vec<PageMetadata>,
+}
+
+impl Metadata {
+    pub fn new(mut pages: Vec<PageMetadata>) -> Metadata {
+        pages.sort_by_key(|p| p.id);
+        pages.dedup_by_key(|p| p.id);
+        Metadata { pages }
+    }
+    /// Number of pages with metadata
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+    /// Every page's metadata (sorted by id)
+    pub fn iter(&self) -> ::std::slice::Iter<'_, PageMetadata> {
+        self.pages.iter()
+    }
+    pub fn get(&self, id: u32) -> Option<&PageMetadata> {
+        self.pages.binary_search_by_key(&id, |p| p.id).ok().map(|i| &self.pages[i])
+    }
+    /// Length of `id` in bytes
+    pub fn len_of(&self, id: u32) -> Option<u32> {
+        self.get(id).map(|p| p.len)
+    }
+    /// Pages that are at least `len` bytes long (sorted)
+    pub fn at_least(&self, len: u32) -> Vec<u32> {
+        self.pages.iter().filter(|p| p.len >= len).map(|p| p.id).collect()
+    }
+}
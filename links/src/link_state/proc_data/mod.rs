@@ -4,7 +4,7 @@ use std::path::{self, PathBuf};
 use std::cmp::Ordering;
 use std::{f64, u64};
 
-use super::{LinkState, LinkData, ProcData};
+use super::{LinkState, LinkData, ProcData, Categories};
 use super::{bfs, Path};
 use self::bfs::BFS;
 
@@ -16,13 +16,14 @@ impl From<LinkState<LinkData>> for LinkState<ProcData> {
         // move addrs and entries from LinkData and compute pageranks
         // single threaded population for now
         let (threads, size) = (old.threads, old.size);
-        let (links, log, _) = old.break_down();
+        let (links, log, _, extras) = old.break_down();
         LinkState {
             threads:    threads,
             size:       size,
             log:        log,
             state:      ProcData {
                 links:  links,
+                extras,
             }
         }
     }
@@ -51,6 +52,37 @@ impl LinkState<ProcData> {
         Ok(())
     }
 
+    /// Write some statistics about each category to a csv:
+    ///  (title, members, links between members, links out of it, links into it)
+    /// Nothing is written if categorylinks.sql wasn't parsed
+    pub fn category_stats(&self, path: &PathBuf) -> Result<(), csv::Error> {
+        let categories = match self.state.extras.categories {
+            Some(ref c) => c,
+            None => return Ok(()),
+        };
+        let mut csv_w = csv::Writer::from_file(path)?;
+        for category in categories.iter() {
+            let members = &category.members;
+            let (mut within, mut out, mut into) = (0u64, 0u64, 0u64);
+            for entry in members.iter().filter_map(|i| self.state.links.get(i)) {
+                for child in entry.get_children() {
+                    if members.binary_search(child).is_ok() {
+                        within += 1;
+                    } else {
+                        out += 1;
+                    }
+                }
+                into += entry.get_parents().iter()
+                    .filter(|p| members.binary_search(p).is_err()).count() as u64;
+            }
+            csv_w.encode((&category.title, members.len(), within, out, into))?;
+        }
+        Ok(())
+    }
+    pub fn categories(&self) -> Option<&Categories> {
+        self.state.extras.categories.as_ref()
+    }
+
     pub fn longest_path(&self, dst: u32) -> u8 {
         self.state.longest_path(dst)
     }
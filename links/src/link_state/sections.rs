@@ -0,0 +1,69 @@
+// Redirects to a section of a page (e.g. `USA_Today_Sky` → `USA_Today#Sky`)
+// Redirects are resolved while parsing, so all that's left of one is its title in the
+//  title fst, which leads to the whole page. The ones with a fragment (`rd_fragment` in
+//  redirect.sql) are kept on the side, along with the pages that link through them, so a
+//  looked-up title can tell which section it resolved to and a hop in a path can tell
+//  which section it lands on.
+
+use fnv::FnvHashMap;
+
+/// A redirect to a section of its target
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Section {
+    // the redirect's own page id and key
+    pub id: u32,
+    pub title: String,
+    pub target: u32,
+    // e.g. `Sky`, as it'd follow the `#`
+    pub fragment: String,
+    // pages that link to the redirect (sorted)
+    pub from: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Sections {
+    // sorted by title
+    sections: Vec<Section>,
+    // (Source, Target)  →  index into `sections`
+    hops: FnvHashMap<(u32, u32), u32>,
+}
+
+impl Sections {
+    pub fn new(mut sections: Vec<Section>) -> Sections {
+        sections.sort_by(|a,b| a.title.cmp(&b.title));
+        let mut hops = FnvHashMap::default();
+        for (i, section) in sections.iter_mut().enumerate() {
+            section.from.sort();
+            section.from.dedup();
+            for &src in &section.from {
+                // a page linking to several sections of the same target keeps the first
+                hops.entry((src, section.target)).or_insert(i as u32);
+            }
+        }
+        Sections { sections, hops }
+    }
+    /// Number of section redirects
+    pub fn len(&self) -> usize {
+        self.sections.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+    /// Every section redirect (sorted by title)
+    pub fn iter(&self) -> ::std::slice::Iter<'_, Section> {
+        self.sections.iter()
+    }
+    /// The section redirect with the key `title`
+    pub fn by_title(&self, title: &str) -> Option<&Section> {
+        self.sections.binary_search_by(|s| s.title.as_str().cmp(title)).ok()
+            .map(|i| &self.sections[i])
+    }
+    /// The section the link from `src` to `dst` lands on (if it goes through a redirect)
+    pub fn hop(&self, src: u32, dst: u32) -> Option<&Section> {
+        self.hops.get(&(src, dst)).map(|&i| &self.sections[i as usize])
+    }
+    /// The section each hop of `path` lands on, if any
+    pub fn along(&self, path: &[u32]) -> Vec<Option<&Section>> {
+        path.windows(2).map(|w| self.hop(w[0], w[1])).collect()
+    }
+}
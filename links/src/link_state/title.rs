@@ -0,0 +1,130 @@
+// Page titles, normalized the way MediaWiki does it
+// (https://www.mediawiki.org/wiki/Manual:Page_title)
+// Titles are stored (in `Database.addresses`, `Entry.title`, and the title fst) exactly
+//  as they are in the dumps, since only the wiki knows how it normalizes them: most
+//  capitalize the first letter, but case-sensitive ones (like Wiktionary) can have both
+//  `Apple` and `apple`. User input like ` category:physics ` or `ender's  game` can be
+//  anything, so it's normalized when it's looked up:
+//
+//      Unicode NFC
+//      underscores for spaces, with runs of whitespace collapsed and the ends trimmed
+//      the namespace (if any) as part of the key rather than a prefix (see `namespace`)
+//
+// The site doesn't give up on a title that isn't there as typed: it tries a few other
+//  casings first (see `variants`), which is how `ender's game` finds `Ender's_Game`.
+
+use unicode_normalization::UnicodeNormalization;
+
+use link_state::namespace;
+
+// whitespace MediaWiki turns into `_` (besides `_` itself)
+fn is_space(c: char) -> bool {
+    c == '_' || c.is_whitespace()
+}
+
+// invisible characters MediaWiki strips out of titles (directional marks)
+fn is_ignored(c: char) -> bool {
+    matches!(c, '\u{200E}' | '\u{200F}' | '\u{202A}' ..= '\u{202E}')
+}
+
+fn ucfirst(title: &str) -> String {
+    let mut chars = title.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn tidy(title: &str) -> String {
+    // normalize and trim whitespace; separate words with a single `_`
+    let title: String = title.nfc().filter(|&c| is_ignored(c) == false).collect();
+    let words: Vec<&str> = title.split(is_space).filter(|w| w.is_empty() == false).collect();
+    words.join("_")
+}
+
+/// The key of a title as it'd be typed in, e.g. `category: physics` → `14|Physics`
+/// Prefixes that aren't a namespace (e.g. `Star Wars: Episode IV`) are part of the title
+pub fn key(input: &str) -> String {
+    key_with(input, namespace::from_name)
+}
+
+/// Like `key`, with `namespace` telling which prefixes are namespaces (e.g. a wiki's own)
+pub fn key_with<F: Fn(&str) -> Option<i32>>(input: &str, namespace: F) -> String {
+    key_cased(input, namespace, ucfirst)
+}
+
+/// Like `key`, but keeping the case of the first letter (for case-sensitive wikis)
+pub fn key_as_typed(input: &str) -> String {
+    key_cased(input, namespace::from_name, |t: &str| String::from(t))
+}
+
+fn key_cased<F, C>(input: &str, namespace: F, case: C) -> String
+    where F: Fn(&str) -> Option<i32>, C: Fn(&str) -> String
+{
+    let title = tidy(input);
+    // a leading `:` only means "not a category/file link"
+    let title = title.trim_start_matches(':').trim_start_matches('_');
+    if let Some(i) = title.find(':') {
+        let prefix = title[..i].trim_end_matches('_');
+        if let Some(ns) = namespace(prefix) {
+            let rest = title[i+1..].trim_start_matches('_');
+            return namespace::key(ns, &case(rest)).into_owned();
+        }
+    }
+    case(title)
+}
+
+/// A key as it'd be shown on the site, e.g. `14|Physics_(disambiguation)` →
+/// `Category:Physics (disambiguation)`
+pub fn display(key: &str) -> String {
+    namespace::display(key).replace('_', " ")
+}
+
+/// The keys worth trying for a title as it'd be typed in, in order
+/// After the title as typed (then with its first letter capitalized), MediaWiki's "near
+///  match" tries it in lower case, with each word capitalized, after each word break, and
+///  in upper case
+/// (On a case-sensitive wiki a title can start in lower case, so the title as typed and
+///  in lower case are also tried as they are)
+pub fn variants(input: &str) -> Vec<String> {
+    let lower = input.to_lowercase();
+    let mut keys = vec![
+        key_as_typed(input),
+        key(input),
+        key_as_typed(&lower),
+        key(&lower),
+        key(&capitalize_after(&lower, is_space)),
+        key(&capitalize_after(input, is_space)),
+        key(&capitalize_after(&lower, is_word_break)),
+        key(&input.to_uppercase()),
+    ];
+    // keep the first of each
+    let mut seen = Vec::with_capacity(keys.len());
+    keys.retain(|k| {
+        let new = seen.contains(k) == false;
+        if new {
+            seen.push(k.clone());
+        }
+        new
+    });
+    keys
+}
+
+fn is_word_break(c: char) -> bool {
+    is_space(c) || c == '-' || c == '(' || c == ')'
+}
+
+fn capitalize_after<F: Fn(char) -> bool>(title: &str, brk: F) -> String {
+    // capitalize the first letter and each letter following a `brk`
+    let mut s = String::with_capacity(title.len());
+    let mut capitalize = true;
+    for c in title.chars() {
+        if capitalize {
+            s.extend(c.to_uppercase());
+        } else {
+            s.push(c);
+        }
+        capitalize = brk(c);
+    }
+    s
+}
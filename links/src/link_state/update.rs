@@ -0,0 +1,232 @@
+// Updating a snapshot from newer dumps
+// From one month's dumps to the next most pages (and their links) don't change, so the
+//  newer dumps are applied to the snapshot rather than built into a whole new one:
+//
+//      pages:          page.sql and redirect.sql (and the optional tables) are parsed as
+//                      usual, since a link's destination depends on every title and
+//                      redirect; they're small next to pagelinks.sql
+//      links:          each of pagelinks.sql's links is resolved and checked off against
+//                      the snapshot's links instead of being added to the db; the links
+//                      that weren't checked off are gone, the ones that weren't there
+//                      are new
+//      entries:        only pages that gained or lost a link (or were renamed, added, or
+//                      flagged differently) are rebuilt with `IndexedEntry::from`; the
+//                      rest are kept as they are (in the same entry file)
+//
+// The title fst and the optional tables come from the newer dumps. Links that only come
+//  from templates can't be left out (that needs every page's links at once), so
+//  templatelinks.sql can't be used. `differences` compares the result with a snapshot
+//  made from scratch (see `--verify-update`), which should find nothing.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use super::{LinkState, LinkData, LoadError, Extras, new_logger};
+use super::link_data::IndexedEntry;
+use super::link_db::Dumps;
+use super::link_db::parse;
+use super::namespace::{self, Namespaces};
+
+/// What changed between a snapshot and the dumps it was updated from
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UpdateSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub renamed: usize,
+    // entries built with `IndexedEntry::from` (including added ones)
+    pub rebuilt: usize,
+    // entries kept as they were
+    pub reused: usize,
+    pub links_added: usize,
+    pub links_removed: usize,
+}
+
+impl fmt::Display for UpdateSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} pages added, {} removed, {} renamed; {} links added, {} removed; \
+                   {} entries rebuilt, {} reused",
+               self.added, self.removed, self.renamed, self.links_added, self.links_removed,
+               self.rebuilt, self.reused)
+    }
+}
+
+fn sorted(ids: &[u32]) -> Vec<u32> {
+    let mut ids = ids.to_vec();
+    ids.sort();
+    ids
+}
+
+// the links one page gained and lost, as children or as parents
+#[derive(Debug, Default)]
+struct Changes {
+    children: (Vec<u32>, Vec<u32>),
+    parents: (Vec<u32>, Vec<u32>),
+}
+
+// `ids` with `changes` (added, removed) applied
+fn apply(ids: &[u32], changes: &(Vec<u32>, Vec<u32>)) -> Vec<u32> {
+    let removed: HashSet<&u32> = changes.1.iter().collect();
+    let mut ids: Vec<u32> = ids.iter().filter(|i| removed.contains(i) == false)
+        .chain(changes.0.iter())
+        .cloned().collect();
+    ids.sort();
+    ids
+}
+
+impl LinkState<LinkData> {
+    /// Bring the snapshot up to date with newer dumps (without templatelinks.sql)
+    pub fn update(self, dumps: &Dumps, namespaces: Namespaces)
+        -> Result<(LinkState<LinkData>, UpdateSummary), LoadError>
+    {
+        if dumps.templatelinks.is_some() {
+            return Err(LoadError::Args(String::from(
+                "templatelinks.sql can't be used to update a snapshot")));
+        }
+        let mut summary = UpdateSummary::default();
+        let log = new_logger();
+        let db_log = log.new(o!("update" => format!("{}", dumps.pagelinks.display())));
+        let mut db = parse::populate_pages(dumps, namespaces, db_log)?;
+
+        // Page  →  (Entry file, Old entry)
+        let mut old: HashMap<u32, (usize, IndexedEntry)> = HashMap::with_capacity(self.size);
+        let buckets = self.state.dumps.len();
+        for (i, bucket) in self.state.dumps.into_iter().enumerate() {
+            old.extend(bucket.into_iter().map(|ie| (ie.id, (i, ie))));
+        }
+        // Page  →  Old children (sorted), and whether the newer dumps still have them
+        let mut seen: HashMap<u32, (Vec<u32>, Vec<bool>)> = old.values()
+            .map(|(_, ie)| (ie.id, (sorted(ie.children()), vec![false; ie.children().len()])))
+            .collect();
+        let mut added: Vec<(u32,u32)> = vec![];
+        parse::resolve_pagelinks(&dumps.pagelinks, &mut db, |link| {
+            let kept = seen.get_mut(&link.src).and_then(|&mut (ref children, ref mut kept)| {
+                children.binary_search(&link.dst).ok().map(move |i| &mut kept[i])
+            });
+            match kept {
+                Some(kept) => *kept = true,
+                None => added.push((link.src, link.dst)),
+            }
+        })?;
+        added.sort();
+        added.dedup();
+        let report = parse::finish_db(dumps, &mut db)?;
+
+        // Page  →  Changes to its links
+        let mut changes: HashMap<u32, Changes> = HashMap::new();
+        for &(src, dst) in &added {
+            changes.entry(src).or_default().children.0.push(dst);
+            changes.entry(dst).or_default().parents.0.push(src);
+        }
+        for (src, (children, kept)) in seen {
+            for (dst, _) in children.into_iter().zip(kept).filter(|&(_, k)| k == false) {
+                changes.entry(src).or_default().children.1.push(dst);
+                changes.entry(dst).or_default().parents.1.push(src);
+                summary.links_removed += 1;
+            }
+        }
+        summary.links_added = added.len();
+
+        let mut dumps: Vec<Vec<IndexedEntry>> = (0..buckets).map(|_| vec![]).collect();
+        let mut new: Vec<IndexedEntry> = vec![];
+        for (id, title, _, _, disambiguation) in db.pages() {
+            let change = changes.get(&id);
+            match old.remove(&id) {
+                Some((i, ie)) => {
+                    if ie.title == title && ie.disambiguation == disambiguation
+                        && change.is_none()
+                    {
+                        summary.reused += 1;
+                        dumps[i].push(ie);
+                        continue;
+                    }
+                    if ie.title != title {
+                        summary.renamed += 1;
+                    }
+                    let none = Changes::default();
+                    let change = change.unwrap_or(&none);
+                    let (parents, children) = (apply(ie.parents(), &change.parents),
+                                               apply(ie.children(), &change.children));
+                    let mut ie = IndexedEntry::from(id, title.to_string(), parents, children);
+                    ie.disambiguation = disambiguation;
+                    summary.rebuilt += 1;
+                    dumps[i].push(ie);
+                },
+                None => {
+                    let (parents, children) = match change {
+                        Some(c) => (sorted(&c.parents.0), sorted(&c.children.0)),
+                        None => (vec![], vec![]),
+                    };
+                    let mut ie = IndexedEntry::from(id, title.to_string(), parents, children);
+                    ie.disambiguation = disambiguation;
+                    summary.added += 1;
+                    summary.rebuilt += 1;
+                    new.push(ie);
+                },
+            }
+        }
+        summary.removed = old.len();
+        // new pages go wherever there's the most room (only `threads` files are exported)
+        let files = self.threads.min(dumps.len());
+        for ie in new {
+            if let Some(smallest) = dumps[..files].iter_mut().min_by_key(|d| d.len()) {
+                smallest.push(ie);
+            }
+        }
+        let size = dumps.iter().map(|d| d.len()).sum();
+        let titles = db.title_fst();
+        let extras = Extras {
+            categories: db.take_categories(),
+            langlinks:  db.take_langlinks(),
+            sections:   Some(db.take_sections()),
+            wanted:     db.take_wanted(),
+            metadata:   db.take_metadata(),
+            link_order: None,
+        };
+        Ok((LinkState {
+            threads:    self.threads,
+            size,
+            log:        self.log,
+            state:      LinkData {
+                dumps,
+                titles,
+                report: Some(report),
+                extras,
+            },
+        }, summary))
+    }
+    pub(super) fn entries_by_id(&self) -> HashMap<u32, &IndexedEntry> {
+        self.state.dumps.iter().flat_map(|d| d.iter()).map(|ie| (ie.id, ie)).collect()
+    }
+    /// How this differs from `other` (e.g. an updated snapshot from one made from scratch):
+    /// pages that are missing from either, or whose title, flags, or links differ
+    pub fn differences(&self, other: &LinkState<LinkData>) -> Vec<String> {
+        let (a, b) = (self.entries_by_id(), other.entries_by_id());
+        let mut ids: Vec<u32> = a.keys().chain(b.keys()).cloned().collect();
+        ids.sort();
+        ids.dedup();
+        let mut differences = vec![];
+        for id in ids {
+            let problem = match (a.get(&id), b.get(&id)) {
+                (Some(_), None) => Some(String::from("only in the first")),
+                (None, Some(_)) => Some(String::from("only in the second")),
+                (Some(x), Some(y)) if x.title != y.title =>
+                    Some(format!("titled `{}` and `{}`", x.title, y.title)),
+                (Some(x), Some(y)) if x.disambiguation != y.disambiguation =>
+                    Some(String::from("disambiguation flags differ")),
+                (Some(x), Some(y)) if sorted(x.children()) != sorted(y.children()) =>
+                    Some(String::from("children differ")),
+                (Some(x), Some(y)) if sorted(x.parents()) != sorted(y.parents()) =>
+                    Some(String::from("parents differ")),
+                _ => None,
+            };
+            if let Some(p) = problem {
+                let title = a.get(&id).or_else(|| b.get(&id)).map_or("", |ie| &ie.title);
+                differences.push(format!("{} `{}`: {}", id, namespace::display(title), p));
+            }
+        }
+        if self.state.titles != other.state.titles {
+            differences.push(String::from("the title fsts differ"));
+        }
+        differences
+    }
+}
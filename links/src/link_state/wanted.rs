@@ -0,0 +1,65 @@
+// Red links ("wanted pages"): titles that are linked to but aren't pages
+// A pagelink to a title that isn't in page.sql is dropped from the link graph; if it's
+//  asked for (`Dumps.wanted`), the title is kept on the side along with the pages that
+//  link to it, so the most wanted missing articles can be ranked and a missing title
+//  can be looked up.
+// Titles are keys (see `namespace::key`), and only links into the db's namespaces count.
+
+use std::collections::HashMap;
+
+/// A missing page and the ids of the pages that link to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WantedPage {
+    pub title: String,
+    // sorted
+    pub from: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Wanted {
+    // sorted by title
+    pages: Vec<WantedPage>,
+}
+
+impl Wanted {
+    pub fn new(mut pages: Vec<WantedPage>) -> Wanted {
+        pages.sort_by(|a,b| a.title.cmp(&b.title));
+        for page in &mut pages {
+            page.from.sort();
+            page.from.dedup();
+        }
+        Wanted { pages }
+    }
+    pub fn from_links(links: HashMap<String, Vec<u32>>) -> Wanted {
+        Wanted::new(links.into_iter()
+                    .map(|(title, from)| WantedPage { title, from })
+                    .collect())
+    }
+    /// Number of missing pages
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+    /// Every missing page (sorted by title)
+    pub fn iter(&self) -> ::std::slice::Iter<'_, WantedPage> {
+        self.pages.iter()
+    }
+    /// The missing page with the key `title`
+    pub fn get(&self, title: &str) -> Option<&WantedPage> {
+        self.pages.binary_search_by(|p| p.title.as_str().cmp(title)).ok()
+            .map(|i| &self.pages[i])
+    }
+    /// Pages that link to the missing page `title`
+    pub fn linking_to(&self, title: &str) -> &[u32] {
+        self.get(title).map_or(&[], |p| &p.from)
+    }
+    /// The `n` most wanted missing pages (most linked to first)
+    pub fn ranked(&self, n: usize) -> Vec<&WantedPage> {
+        let mut ranked: Vec<&WantedPage> = self.pages.iter().collect();
+        ranked.sort_by(|a,b| b.from.len().cmp(&a.from.len()).then(a.title.cmp(&b.title)));
+        ranked.truncate(n);
+        ranked
+    }
+}
@@ -7,14 +7,15 @@ use alloc_system::System;
 #[global_allocator] static A: System = System;
 */
 
-// NOTE: when running on simple wiki, use `--features=simple` flag
-
 #[macro_use]
 extern crate clap;
 extern crate links;
 extern crate rand;
 
 use links::link_state::{self, LinkState};
+use links::link_state::namespace::Namespaces;
+use links::link_state::title;
+use links::link_state::bilingual::{Bilingual, Edition};
 
 use clap::Arg;
 fn argv<'a>() -> clap::ArgMatches<'a> {
@@ -32,6 +33,9 @@ fn argv<'a>() -> clap::ArgMatches<'a> {
              .takes_value(true)
              .help("Export link and title data to manifest and dumps"))
 
+        .arg(Arg::with_name("validate")
+             .long("validate")
+             .help("Check that each article's title leads back to it"))
         .arg(Arg::with_name("compute_ranks")
              .long("compute-ranks")
              .takes_value(true)
@@ -42,6 +46,16 @@ fn argv<'a>() -> clap::ArgMatches<'a> {
              .takes_value(true)
              .conflicts_with("web_server")
              .help("Find the maximum links required to get from any link to the given one"))
+        .arg(Arg::with_name("src")
+             .long("src")
+             .takes_value(true)
+             .requires("dst")
+             .help("Title of the article to search from"))
+        .arg(Arg::with_name("dst")
+             .long("dst")
+             .takes_value(true)
+             .requires("src")
+             .help("Title of the article to search to"))
         .arg(Arg::with_name("cli-bfs")
              .long("cli-bfs")
              .help("Command-line bfs"))
@@ -52,6 +66,7 @@ fn argv<'a>() -> clap::ArgMatches<'a> {
         .arg(Arg::with_name("page.sql")
              .short("p")
              .takes_value(true)
+             .conflicts_with("xml")
              .requires("redirect.sql")
              .requires("pagelinks.sql")
              .help("Pages db from wikipedia dump"))
@@ -67,6 +82,130 @@ fn argv<'a>() -> clap::ArgMatches<'a> {
              .requires("page.sql")
              .requires("redirect.sql")
              .help("Internal links db from wikipedia dump"))
+        .arg(Arg::with_name("xml")
+             .short("x")
+             .long("xml")
+             .takes_value(true)
+             .help("XML export (pages-articles.xml) to read pages and links from instead"))
+        .arg(Arg::with_name("linktarget.sql")
+             .short("t")
+             .takes_value(true)
+             .requires("pagelinks.sql")
+             .help("Link target db from newer wikipedia dumps (pagelinks by target id)"))
+        .arg(Arg::with_name("templatelinks.sql")
+             .long("templatelinks.sql")
+             .takes_value(true)
+             .requires("page.sql")
+             .help("Template transclusion db from wikipedia dump; \
+                    links that only come from templates are left out"))
+        .arg(Arg::with_name("categorylinks.sql")
+             .short("c")
+             .long("categorylinks.sql")
+             .takes_value(true)
+             .requires("page.sql")
+             .help("Category membership db from wikipedia dump (optional)"))
+        .arg(Arg::with_name("langlinks.sql")
+             .long("langlinks.sql")
+             .takes_value(true)
+             .requires("page.sql")
+             .help("Language links db from wikipedia dump (optional)"))
+        .arg(Arg::with_name("page_props.sql")
+             .long("page_props.sql")
+             .takes_value(true)
+             .requires("page.sql")
+             .help("Page properties db from wikipedia dump, to flag disambiguation pages"))
+        .arg(Arg::with_name("compare_with")
+             .long("compare-with")
+             .takes_value(true)
+             .requires_all(&["src", "dst", "lang", "compare_lang"])
+             .help("Manifest of another language edition to run the same search in"))
+        .arg(Arg::with_name("lang")
+             .long("lang")
+             .takes_value(true)
+             .help("Language code of the main data (e.g. `en`)"))
+        .arg(Arg::with_name("compare_lang")
+             .long("compare-lang")
+             .takes_value(true)
+             .help("Language code of the data given by --compare-with (e.g. `simple`)"))
+        .arg(Arg::with_name("redirect_report")
+             .long("redirect-report")
+             .takes_value(true)
+             .help("Write double, circular, and broken redirects to csvs named after this"))
+        .arg(Arg::with_name("wanted")
+             .long("wanted")
+             .requires("page.sql")
+             .help("Keep the titles of missing pages that are linked to (red links)"))
+        .arg(Arg::with_name("metadata")
+             .long("page-metadata")
+             .requires("page.sql")
+             .help("Keep each page's length, latest revision, and touched time"))
+        .arg(Arg::with_name("most_wanted")
+             .long("most-wanted")
+             .takes_value(true)
+             .help("List this many of the most linked-to missing pages"))
+        .arg(Arg::with_name("linking_to")
+             .long("linking-to")
+             .takes_value(true)
+             .help("List the pages that link to this missing page"))
+        .arg(Arg::with_name("update")
+             .long("update")
+             .takes_value(true)
+             .conflicts_with("import")
+             .requires("page.sql")
+             .conflicts_with("templatelinks.sql")
+             .conflicts_with("redirect_report")
+             .help("Manifest of an earlier snapshot to update from the dumps, \
+                    only rebuilding the entries that changed"))
+        .arg(Arg::with_name("verify_update")
+             .long("verify-update")
+             .requires("update")
+             .help("Check the updated snapshot against a full parse of the dumps"))
+        .arg(Arg::with_name("diff_with")
+             .long("diff-with")
+             .takes_value(true)
+             .help("Manifest of a newer snapshot to compare the data with"))
+        .arg(Arg::with_name("diff_output")
+             .long("diff-output")
+             .takes_value(true)
+             .requires("diff_with")
+             .help("Write the differences to this json (or to csvs named after it, \
+                    if it ends in .csv)"))
+        .arg(Arg::with_name("most_changed")
+             .long("most-changed")
+             .takes_value(true)
+             .requires("diff_with")
+             .help("List this many of the pages whose links changed the most"))
+        .arg(Arg::with_name("first_link_report")
+             .long("first-link-report")
+             .takes_value(true)
+             .help("Write the cycles that following first links ends in, and where each \
+                    page ends up, to csvs named after this"))
+        .arg(Arg::with_name("follow_first_links")
+             .long("follow-first-links")
+             .takes_value(true)
+             .help("Follow the first link of each article from this one until it repeats"))
+        .arg(Arg::with_name("low_memory")
+             .long("low-memory")
+             .requires("page.sql")
+             .help("Read pagelinks.sql twice to add links to arrays of their final size"))
+        .arg(Arg::with_name("threads")
+             .long("threads")
+             .takes_value(true)
+             .requires("page.sql")
+             .help("Threads to parse pagelinks.sql with, and files to export entries to \
+                    (default 4)"))
+        .arg(Arg::with_name("namespaces")
+             .long("namespaces")
+             .takes_value(true)
+             .help("Namespaces to include besides articles, e.g. `14,Portal,Help`"))
+        .arg(Arg::with_name("search_namespaces")
+             .long("search-namespaces")
+             .takes_value(true)
+             .help("Only search through pages in these namespaces (and articles)"))
+
+        .arg(Arg::with_name("no_disambiguation")
+             .long("no-disambiguation")
+             .help("Don't search through disambiguation pages"))
 
         .get_matches()
 }
@@ -156,22 +295,142 @@ fn main() {
     }
     */
 
-    let ls: LinkState<link_state::HashLinks> = LinkState::from_args(&argv);
-    let (src,dst) = if cfg!(feature="simple") { 
-        (152_629, 454_989) 
-    } else { 
-        (1_684_129, 52_186_157) 
+    let ls: LinkState<link_state::HashLinks> = match LinkState::from_args(&argv) {
+        Ok(ls) => ls,
+        Err(e) => {
+            eprintln!("{}", e);
+            ::std::process::exit(1);
+        },
     };
 
-    // 172504K
-    // 172500K
+    // e.g. simplewiki: `Macclenny,_Florida` → `Saltsjöbaden_Agreement` (152_629 → 454_989)
+    //      enwiki: `Jack_Tatum` → `Caulophryne_bacescui` (1_684_129 → 52_186_157)
+    if let (Some(s), Some(d)) = (argv.value_of("src"), argv.value_of("dst")) {
+        match (ls.resolve_section(s), ls.resolve_section(d)) {
+            (Some((src, src_section)), Some((dst, dst_section))) => {
+                for section in src_section.iter().chain(dst_section.iter()) {
+                    println!("`{}` is a section of `{}`: #{}", title::display(&section.title),
+                             ls.title_of(section.target).unwrap_or_default(), section.fragment);
+                }
+                // 172504K
+                // 172500K
+
+                // none         293,810,837 bytes
+                // bfs          301,149,929 bytes
+                // bfs2 (.50)   300,101,737 bytes
+
+                let ns = argv.value_of("search_namespaces").map(|n| {
+                    Namespaces::parse(n).unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        ::std::process::exit(1);
+                    })
+                });
+                let path = match (ns, argv.is_present("no_disambiguation")) {
+                    (ns, true) => ls.bfs_without_disambiguation(src, dst, ns.as_ref()),
+                    (Some(ns), false) => ls.bfs_in(src, dst, &ns),
+                    (None, false) => ls.bfs(src, dst),
+                };
+                println!("{:?}", path);
+                for (category, n) in ls.path_categories(&path) {
+                    println!("\t{}\tCategory:{}", n, category);
+                }
+                if let Ok(ref ids) = path.path {
+                    for (hop, section) in ids.windows(2).zip(ls.path_sections(&path)) {
+                        if let Some(section) = section {
+                            println!("\t{} → {}#{}\t(via `{}`)", hop[0], hop[1], 
+                                     section.fragment, title::display(&section.title));
+                        }
+                    }
+                }
+            },
+            (None, _) => println!("No such title: `{}`", s),
+            (_, None) => println!("No such title: `{}`", d),
+        }
+    }
+
+    // red links
+    if argv.is_present("most_wanted") || argv.is_present("linking_to") {
+        match ls.wanted() {
+            Some(wanted) => {
+                if let Some(n) = argv.value_of("most_wanted") {
+                    let n = n.parse().unwrap_or_else(|_| {
+                        eprintln!("Not a number: `{}`", n);
+                        ::std::process::exit(1);
+                    });
+                    for page in wanted.ranked(n) {
+                        println!("{}\t{}", page.from.len(), title::display(&page.title));
+                    }
+                }
+                if let Some(t) = argv.value_of("linking_to") {
+                    match ls.resolve_wanted(t) {
+                        Some(page) => for &id in &page.from {
+                            println!("{}\t{}", id, ls.title_of(id).unwrap_or_default());
+                        },
+                        None => println!("Nothing links to a missing page `{}`", t),
+                    }
+                }
+            },
+            None => println!("Missing pages weren't kept (parse with --wanted)"),
+        }
+    }
 
-    // none         293,810,837 bytes
-    // bfs          301,149,929 bytes
-    // bfs2 (.50)   300,101,737 bytes
+    // getting to philosophy
+    if argv.is_present("first_link_report") || argv.is_present("follow_first_links") {
+        match ls.first_links() {
+            Some(first_links) => {
+                if let Some(p) = argv.value_of("first_link_report") {
+                    let title = |id| ls.title_of(id).unwrap_or_default();
+                    if let Err(e) = first_links.export(&p.into(), title) {
+                        eprintln!("Couldn't write the first link report: {}", e);
+                    }
+                    for (i, cycle) in first_links.cycles().iter().enumerate().take(10) {
+                        let members: Vec<String> = cycle.members.iter().map(|&id| title(id))
+                            .collect();
+                        println!("{}\t{} pages\t{}", i, cycle.basin, members.join(" → "));
+                    }
+                }
+                if let Some(t) = argv.value_of("follow_first_links") {
+                    match ls.resolve_title(t) {
+                        Some(id) => for (i, id) in first_links.follow(id).into_iter().enumerate() {
+                            println!("{}\t{}\t{}", i, id, ls.title_of(id).unwrap_or_default());
+                        },
+                        None => println!("No such title: `{}`", t),
+                    }
+                }
+            },
+            None => println!("The order of links wasn't kept (parse with --xml)"),
+        }
+    }
 
-    let path = ls.bfs(src, dst);
-    println!("{:?}", path);
+    // run the same search in another language edition
+    if let (Some(m), Some(s), Some(d)) = 
+        (argv.value_of("compare_with"), argv.value_of("src"), argv.value_of("dst")) 
+    {
+        let other = match LinkState::<link_state::LinkData>::import(m.into(), 
+                                                                    link_state::new_logger()) {
+            Ok(ld) => ld.into(),
+            Err(e) => {
+                eprintln!("Couldn't import `{}`: {}", m, e);
+                ::std::process::exit(1);
+            },
+        };
+        let (src, dst) = (ls.resolve_title(s), ls.resolve_title(d));
+        let bilingual = Bilingual::new(
+            Edition { lang: argv.value_of("lang").unwrap().to_string(), links: ls },
+            Edition { lang: argv.value_of("compare_lang").unwrap().to_string(), links: other });
+        match (src, dst) {
+            (Some(src), Some(dst)) => match bilingual.compare(src, dst) {
+                Some(c) => {
+                    println!("{}: {:?}", bilingual.a().lang, c.a);
+                    println!("{}: {:?}", bilingual.b().lang, c.b);
+                },
+                None => println!("No equivalent of `{}` or `{}` in `{}`", 
+                                 s, d, bilingual.b().lang),
+            },
+            (None, _) => println!("No such title: `{}`", s),
+            (_, None) => println!("No such title: `{}`", d),
+        }
+    }
 
     //println!("\n\n\nMEMORY USED:\n");
     //::std::process::Command::new("/usr/bin/pmap")
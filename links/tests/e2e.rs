@@ -0,0 +1,789 @@
+// End to end: made-up dumps (see `link_db::synthetic`) parsed all the way into a
+//  `HashLinks`, and checked against what the wiki they came from says should be there
+
+extern crate links;
+
+use links::link_state::{LinkState, LinkDb, LinkData, HashLinks, new_logger};
+use links::link_state::link_db::Dumps;
+use links::link_state::link_db::parse::{ErrorKind, DropReason};
+use links::link_state::link_db::synthetic::{self, Wiki, Expected};
+use links::link_state::namespace::{self, Namespaces};
+use links::link_state::first_links::{Destination, Reach};
+use links::link_state::bilingual::{Bilingual, Edition};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process;
+
+// an empty directory for one test's dumps
+fn scratch(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("links_e2e_{}_{}", process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn parse(dumps: &Dumps, namespaces: Namespaces) -> LinkState<LinkData> {
+    LinkState::<LinkDb>::new(dumps, namespaces).expect("couldn't parse the dumps").into()
+}
+
+fn set(ids: &[u32]) -> BTreeSet<u32> {
+    ids.iter().cloned().collect()
+}
+
+// every page with its title, children, and parents; and every title
+fn check(hl: &LinkState<HashLinks>, expected: &Expected) {
+    let links = hl.get_links();
+    let ids: BTreeSet<u32> = links.keys().cloned().collect();
+    assert_eq!(ids, expected.pages.keys().cloned().collect());
+    let parents = expected.parents();
+    for (id, page) in &expected.pages {
+        let entry = &links[id];
+        assert_eq!(entry.title, page.title);
+        assert_eq!(set(entry.get_children()), page.children, "children of `{}`", page.title);
+        assert_eq!(set(entry.get_parents()), parents[id], "parents of `{}`", page.title);
+    }
+    for (key, &id) in &expected.titles {
+        assert_eq!(hl.resolve_title(&namespace::display(key)), Some(id), "`{}`", key);
+    }
+    for key in &expected.dropped_titles {
+        assert_eq!(hl.resolve_title(&namespace::display(key)), None, "`{}`", key);
+    }
+}
+
+// searches (with both `BFS` and `BFS2`) between some pairs of pages
+fn check_paths(hl: &LinkState<HashLinks>, expected: &Expected) {
+    let ids: Vec<u32> = expected.pages.keys().cloned().collect();
+    for &src in ids.iter().step_by(17) {
+        let distances = expected.distances(src);
+        for &dst in ids.iter().step_by(13) {
+            for path in vec![hl.bfs(src, dst), hl.bfs2(src, dst)] {
+                match (distances.get(&dst), path.path) {
+                    (None, found) => assert!(found.is_err(), "{} → {}: {:?}", src, dst, found),
+                    (Some(&d), Ok(found)) => {
+                        assert_eq!(found.len(), d + 1, "{} → {}: {:?}", src, dst, found);
+                        assert_eq!((found[0], found[found.len()-1]), (src, dst));
+                        for hop in found.windows(2) {
+                            assert!(expected.pages[&hop[0]].children.contains(&hop[1]),
+                                    "{} → {}: no link {} → {}", src, dst, hop[0], hop[1]);
+                        }
+                    },
+                    (Some(d), Err(e)) => panic!("{} → {}: {:?} ({} apart)", src, dst, e, d),
+                }
+            }
+        }
+    }
+}
+
+// a bit of everything, with ids to check by hand
+struct Scripted {
+    wiki: Wiki,
+    alpha: u32,
+    ender: u32,
+    slash: u32,
+    comma: u32,
+    category: u32,
+    chain: u32,
+    broken: Vec<u32>,
+}
+
+fn scripted() -> Scripted {
+    let mut wiki = Wiki::new();
+    let alpha = wiki.page(0, "Alpha");
+    let ender = wiki.page(0, "Ender's_Game");
+    let slash = wiki.page(0, "Back\\slash");
+    let comma = wiki.page(0, "Comma,_paren_(thing)");
+    let category = wiki.page(namespace::CATEGORY, "Alpha");
+    let gone = wiki.page(0, "Gone");
+    wiki.delete(gone);
+    let r1 = wiki.redirect(0, "R1", 0, "Alpha");
+    let chain = wiki.redirect(0, "R2", 0, "R1");
+    wiki.redirect(0, "Loop_1", 0, "Loop_2");
+    wiki.redirect(0, "Loop_2", 0, "Loop_1");
+    wiki.redirect(0, "Self", 0, "Self");
+    let broken = wiki.redirect(0, "Broken", 0, "Nowhere");
+    let no_target = wiki.redirect_without_target(0, "No_target");
+    let to_category = wiki.redirect(0, "To_category", namespace::CATEGORY, "Alpha");
+
+    wiki.link_to(ender, chain);
+    wiki.link(ender, 0, "Loop_1");
+    wiki.link_to(ender, gone);
+    wiki.link_to(gone, alpha);
+    wiki.link_to(slash, broken);
+    wiki.link_to(slash, category);
+    wiki.link_to(r1, slash);
+    wiki.link_to(comma, ender);
+    wiki.link(comma, 0, "Nowhere");
+    wiki.link(999, 0, "Alpha");
+    wiki.link_to(category, alpha);
+    wiki.link_to(alpha, to_category);
+
+    let broken = vec![broken, no_target, to_category];
+    Scripted { wiki, alpha, ender, slash, comma, category, chain, broken }
+}
+
+#[test]
+fn scripted_wiki() {
+    let s = scripted();
+    let dir = scratch("scripted");
+    let dumps = s.wiki.write(&dir).unwrap();
+    let expected = s.wiki.expected(&Namespaces::main());
+    let children: BTreeMap<u32, BTreeSet<u32>> = expected.pages.iter()
+        .map(|(&id, p)| (id, p.children.clone()))
+        .collect();
+    let by_hand: BTreeMap<u32, BTreeSet<u32>> = vec![
+        (s.alpha, set(&[s.slash])),
+        (s.ender, set(&[s.alpha])),
+        (s.slash, set(&[])),
+        (s.comma, set(&[s.ender])),
+    ].into_iter().collect();
+    assert_eq!(children, by_hand);
+
+    let ls_db = LinkState::<LinkDb>::new(&dumps, Namespaces::main()).unwrap();
+    {
+        let redirects = ls_db.redirect_report().unwrap();
+        assert_eq!(redirects.cycles.len(), 2);
+        assert_eq!(redirects.double.iter().map(|d| d.id).collect::<Vec<_>>(), vec![s.chain]);
+        assert_eq!(redirects.broken.iter().map(|b| b.id).collect::<Vec<_>>(), s.broken);
+    }
+    let ls_dt: LinkState<LinkData> = ls_db.into();
+    let hl: LinkState<HashLinks> = ls_dt.into();
+    check(&hl, &expected);
+    assert_eq!(hl.resolve_title("ender's game"), Some(s.ender));
+    assert_eq!(hl.resolve_title("R2"), Some(s.alpha));
+    assert_eq!(hl.resolve_title("Category:Alpha"), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn scripted_wiki_with_categories() {
+    let s = scripted();
+    let dir = scratch("scripted_categories");
+    let dumps = s.wiki.write(&dir).unwrap();
+    let namespaces = Namespaces::with(&[namespace::CATEGORY]);
+    let expected = s.wiki.expected(&namespaces);
+    assert_eq!(expected.pages[&s.category].children, set(&[s.alpha]));
+    assert_eq!(expected.pages[&s.alpha].children, set(&[s.slash, s.category]));
+
+    let hl: LinkState<HashLinks> = parse(&dumps, namespaces).into();
+    check(&hl, &expected);
+    assert_eq!(hl.resolve_title("Category:Alpha"), Some(s.category));
+    assert_eq!(hl.resolve_title("Alpha"), Some(s.alpha));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn titles_that_only_differ_in_case() {
+    // on a case-sensitive wiki (like Wiktionary) these are different pages
+    let mut wiki = Wiki::new();
+    let company = wiki.page(0, "Apple");
+    let fruit = wiki.page(0, "apple");
+    let ipod = wiki.page(0, "iPod");
+    wiki.redirect(0, "apples", 0, "apple");
+    wiki.link_to(company, ipod);
+    wiki.link(ipod, 0, "apples");
+
+    let dir = scratch("case");
+    let dumps = wiki.write(&dir).unwrap();
+    let expected = wiki.expected(&Namespaces::main());
+    assert_eq!(expected.pages[&ipod].children, set(&[fruit]));
+    let hl: LinkState<HashLinks> = parse(&dumps, Namespaces::main()).into();
+    check(&hl, &expected);
+    assert_eq!(hl.get_links()[&fruit].title, "apple");
+    assert_eq!(hl.resolve_title("apple"), Some(fruit));
+    assert_eq!(hl.resolve_title(" Apple"), Some(company));
+    assert_eq!(hl.resolve_title("iPod"), Some(ipod));
+    // input that isn't there as typed still finds the page it would on the site
+    assert_eq!(hl.resolve_title("Apples"), Some(fruit));
+    assert_eq!(hl.resolve_title("IPOD"), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn page_metadata() {
+    let mut wiki = Wiki::new();
+    let physics = wiki.page(0, "Physics");
+    let qm = wiki.page(0, "Quantum_mechanics");
+    let redirect = wiki.redirect(0, "QM", 0, "Quantum_mechanics");
+    let portal = wiki.page(namespace::PORTAL, "Physics");
+    wiki.link_to(physics, redirect);
+
+    let dir = scratch("metadata");
+    let mut dumps = wiki.write(&dir).unwrap();
+    let hl: LinkState<HashLinks> = parse(&dumps, Namespaces::main()).into();
+    assert!(hl.metadata().is_none());
+
+    dumps.metadata = true;
+    let ls_dt = parse(&dumps, Namespaces::main());
+    // and the same from the command line, which keeps it alongside the manifest
+    let manifest = dir.join("links.json");
+    let cli = process::Command::new(env!("CARGO_BIN_EXE_links"))
+        .arg("-p").arg(&dumps.page).arg("-r").arg(&dumps.redirect)
+        .arg("-l").arg(&dumps.pagelinks)
+        .arg("--page-metadata").arg("--output").arg(&manifest)
+        .output().unwrap();
+    assert!(cli.status.success(), "{}", String::from_utf8_lossy(&cli.stderr));
+    let imported = LinkState::<LinkData>::import(manifest, new_logger()).unwrap();
+    for ls_dt in vec![ls_dt, imported] {
+        let hl: LinkState<HashLinks> = ls_dt.into();
+        // synthetic page.sql has each page's title length as its length, and its id as
+        //  its latest revision
+        let metadata = hl.metadata().expect("no metadata");
+        assert_eq!(metadata.iter().map(|m| m.id).collect::<Vec<u32>>(), vec![physics, qm]);
+        let m = hl.metadata_of(qm).unwrap();
+        assert_eq!((m.len, m.latest, m.touched), (17, qm, 20170101000000));
+        assert_eq!(metadata.len_of(physics), Some(7));
+        // only for articles: not redirects, or pages in namespaces that were left out
+        assert_eq!(hl.metadata_of(redirect), None);
+        assert_eq!(hl.metadata_of(portal), None);
+        assert_eq!(metadata.at_least(10), vec![qm]);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn searching_around_disambiguation_pages() {
+    let mut wiki = Wiki::new();
+    let queen = wiki.page(0, "Queen_(band)");
+    let mercury = wiki.page(0, "Mercury");
+    let planet = wiki.page(0, "Mercury_(planet)");
+    let element = wiki.page(0, "Mercury_(element)");
+    let freddie = wiki.page(0, "Freddie_Mercury");
+    let astronomy = wiki.page(0, "Astronomy");
+    wiki.disambiguation(mercury);
+    // the only shortest path from the band to the planet is through `Mercury`
+    wiki.link_to(queen, mercury);
+    wiki.link_to(mercury, planet);
+    wiki.link_to(mercury, element);
+    wiki.link_to(queen, freddie);
+    wiki.link_to(freddie, astronomy);
+    wiki.link_to(astronomy, planet);
+
+    let dir = scratch("disambiguation");
+    let dumps = wiki.write(&dir).unwrap();
+    assert!(dumps.page_props.is_some());
+    let ls_dt = parse(&dumps, Namespaces::main());
+    // only the disambiguation page's row is kept; the wikidata ids are skipped, not dropped
+    let rows = &ls_dt.report().unwrap().rows["page_props"];
+    assert_eq!((rows.added, rows.attempted), (1, 1));
+    assert!(ls_dt.report().unwrap().drops.is_empty());
+    let hl: LinkState<HashLinks> = ls_dt.into();
+    check(&hl, &wiki.expected(&Namespaces::main()));
+    assert!(hl.is_disambiguation(mercury));
+    assert_eq!(hl.get_links().keys().filter(|&&id| hl.is_disambiguation(id)).count(), 1);
+
+    assert_eq!(hl.bfs(queen, planet).path.unwrap(), vec![queen, mercury, planet]);
+    let around = hl.bfs_without_disambiguation(queen, planet, None);
+    assert_eq!(around.path.unwrap(), vec![queen, freddie, astronomy, planet]);
+    // and there's no way to the element without it
+    assert!(hl.bfs_without_disambiguation(queen, element, None).path.is_err());
+    let main = Namespaces::main();
+    assert!(hl.bfs_without_disambiguation(queen, element, Some(&main)).path.is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn template_links_are_left_out() {
+    let mut wiki = Wiki::new();
+    let physics = wiki.page(0, "Physics");
+    let biology = wiki.page(0, "Biology");
+    let chemistry = wiki.page(0, "Chemistry");
+    let quantum = wiki.page(0, "Quantum_mechanics");
+    let planck = wiki.page(0, "Max_Planck");
+    let bio = wiki.redirect(0, "Bio", 0, "Biology");
+    let navbox = wiki.page(namespace::TEMPLATE, "Natural_sciences");
+    wiki.link_to(navbox, physics);
+    wiki.link_to(navbox, bio);
+    wiki.link(navbox, 0, "Astronomy");
+    wiki.transclude(quantum, navbox);
+    wiki.transclude(chemistry, navbox);
+    wiki.link_to(quantum, planck);
+    wiki.link_to(planck, physics);
+    // pagelinks.sql can't tell this from the navbox's link, so it's left out too
+    wiki.link_to(quantum, physics);
+
+    let dir = scratch("templates");
+    let mut dumps = wiki.write(&dir.join("legacy")).unwrap();
+    assert!(dumps.templatelinks.is_some());
+    let expected = wiki.expected(&Namespaces::main());
+    assert_eq!(expected.pages[&quantum].children, set(&[planck]));
+    assert_eq!(expected.pages[&chemistry].children, set(&[]));
+    assert_eq!(expected.pages[&planck].children, set(&[physics]));
+    for dumps in vec![dumps.clone(), wiki.write_linktargets(&dir.join("linktarget")).unwrap()] {
+        let ls_dt = parse(&dumps, Namespaces::main());
+        let left_out = ls_dt.report().unwrap().count(DropReason::TemplateOnlyLink);
+        assert_eq!(left_out, 4);
+        let hl: LinkState<HashLinks> = ls_dt.into();
+        check(&hl, &expected);
+    }
+
+    // without templatelinks.sql, the navbox's links look like the page's own
+    dumps.templatelinks = None;
+    let hl: LinkState<HashLinks> = parse(&dumps, Namespaces::main()).into();
+    let links = hl.get_links();
+    assert_eq!(set(links[&quantum].get_children()), set(&[physics, biology, planck]));
+    assert_eq!(set(links[&chemistry].get_children()), set(&[physics, biology]));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn two_language_editions() {
+    let (mut en, mut simple) = (Wiki::new(), Wiki::new());
+    let physics = en.page(0, "Physics");
+    let energy = en.page(0, "Energy");
+    let heat = en.page(0, "Heat");
+    let matter = en.page(0, "Matter");
+    en.link_to(physics, energy);
+    en.link_to(energy, heat);
+    en.link_to(physics, matter);
+    let simple_heat = simple.page(0, "Heat");
+    let simple_physics = simple.page(0, "Physics");
+    let simple_energy = simple.page(0, "Energy");
+    simple.redirect(0, "Warmth", 0, "Heat");
+    simple.page(0, "Matter");
+    simple.link_to(simple_physics, simple_heat);
+    // looked up in simple by en's langlinks (titles as shown there)
+    en.langlink(physics, "simple", "Physics");
+    en.langlink(heat, "simple", "Warmth");
+    // and in en by simple's langlinks back to it
+    simple.langlink(simple_energy, "en", "Energy");
+    simple.langlink(simple_physics, "de", "Physik");
+
+    let dir = scratch("bilingual");
+    let edition = |wiki: &Wiki, lang: &str| {
+        let dumps = wiki.write(&dir.join(lang)).unwrap();
+        assert!(dumps.langlinks.is_some());
+        Edition { lang: String::from(lang), links: parse(&dumps, Namespaces::main()).into() }
+    };
+    let (a, b) = (edition(&en, "en"), edition(&simple, "simple"));
+    assert_eq!(a.links.langlinks().unwrap().title_in(heat, "simple"), Some("Warmth"));
+    let bilingual = Bilingual::new(a, b);
+    assert_eq!(bilingual.translate(physics), Some(simple_physics));
+    assert_eq!(bilingual.translate(heat), Some(simple_heat));
+    assert_eq!(bilingual.translate(energy), Some(simple_energy));
+    assert_eq!(bilingual.translate(matter), None);
+
+    let c = bilingual.compare(physics, heat).unwrap();
+    assert_eq!(c.a.path.clone().unwrap(), vec![physics, energy, heat]);
+    assert_eq!(c.b.path.clone().unwrap(), vec![simple_physics, simple_heat]);
+    assert_eq!(c.lengths(), (Some(2), Some(1)));
+    // no path between energy and physics in simple, but they're both there
+    assert_eq!(bilingual.compare(energy, physics).unwrap().lengths().1, None);
+    assert!(bilingual.compare(physics, matter).is_none());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn redirects_to_sections() {
+    let mut wiki = Wiki::new();
+    let paper = wiki.page(0, "USA_Today");
+    let tv = wiki.page(0, "Television");
+    let news = wiki.page(0, "News");
+    let sky = wiki.redirect_to_section(0, "USA_Today_Sky", 0, "USA_Today", "Sky");
+    wiki.link_to(news, tv);
+    wiki.link_to(tv, sky);
+    wiki.link_to(news, paper);
+
+    let dir = scratch("sections");
+    let sql = parse(&wiki.write(&dir).unwrap(), Namespaces::main());
+    let xml = wiki.write_xml(&dir).unwrap();
+    let xml = LinkState::<LinkDb>::from_xml(&xml, Namespaces::main()).unwrap().into();
+    for ls_dt in vec![sql, xml] {
+        let hl: LinkState<HashLinks> = ls_dt.into();
+        check(&hl, &wiki.expected(&Namespaces::main()));
+        let sections = hl.sections().expect("no sections");
+        assert_eq!(sections.len(), 1);
+        let section = sections.by_title("USA_Today_Sky").unwrap();
+        assert_eq!((section.id, section.target), (sky, paper));
+        assert_eq!(section.fragment, "Sky");
+        assert_eq!(section.from, vec![tv]);
+
+        // the title leads to the page, and tells which section of it
+        let (id, found) = hl.resolve_section(" USA_Today  Sky").unwrap();
+        assert_eq!((id, found), (paper, Some(section)));
+        assert_eq!(hl.resolve_section("USA Today"), Some((paper, None)));
+        // as does a link through it, but not a link straight to the page
+        assert_eq!(sections.hop(tv, paper), Some(section));
+        assert_eq!(sections.hop(news, paper), None);
+        let path = hl.bfs(tv, paper);
+        assert_eq!(path.path.clone().unwrap(), vec![tv, paper]);
+        assert_eq!(hl.path_sections(&path), vec![Some(section)]);
+        assert_eq!(sections.along(&[news, tv, paper]), vec![None, Some(section)]);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn random_wikis() {
+    for seed in 1..5 {
+        let wiki = Wiki::random(1500, seed);
+        let dir = scratch(&format!("random_{}", seed));
+        let dumps = wiki.write(&dir).unwrap();
+        let expected = wiki.expected(&Namespaces::main());
+        let hl: LinkState<HashLinks> = parse(&dumps, Namespaces::main()).into();
+        check(&hl, &expected);
+        check_paths(&hl, &expected);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[test]
+fn random_wiki_with_namespaces() {
+    let wiki = Wiki::random(1500, 42);
+    let dir = scratch("random_namespaces");
+    let dumps = wiki.write(&dir).unwrap();
+    let namespaces = Namespaces::with(&[namespace::CATEGORY, namespace::PORTAL]);
+    let expected = wiki.expected(&namespaces);
+    let hl: LinkState<HashLinks> = parse(&dumps, namespaces).into();
+    check(&hl, &expected);
+    check_paths(&hl, &expected);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn low_memory_and_export_are_the_same() {
+    let wiki = Wiki::random(1000, 7);
+    let dir = scratch("low_memory");
+    let mut dumps = wiki.write(&dir).unwrap();
+    let ls_dt = parse(&dumps, Namespaces::main());
+    dumps.low_memory = true;
+    // (links are counted in parallel for plain dumps, and serially for compressed ones)
+    for dumps in vec![dumps.clone(), synthetic::compressed(&dumps, "gz")] {
+        let low_memory = parse(&dumps, Namespaces::main());
+        assert!(ls_dt.diff(&low_memory).is_empty());
+        assert_eq!(ls_dt.differences(&low_memory), Vec::<String>::new());
+        assert_eq!(ls_dt.report(), low_memory.report());
+    }
+
+    let manifest = dir.join("links.json");
+    ls_dt.export(manifest.clone()).unwrap();
+    let imported = LinkState::<LinkData>::import(manifest, new_logger()).unwrap();
+    assert!(ls_dt.diff(&imported).is_empty());
+    assert!(ls_dt.differences(&imported).is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn importing_a_bad_manifest_is_an_error() {
+    let dir = scratch("bad_manifest");
+    let import = |manifest: PathBuf| match LinkState::<LinkData>::import(manifest, new_logger()) {
+        Ok(_) => panic!("imported a bad manifest"),
+        Err(e) => e.kind(),
+    };
+    assert_eq!(import(dir.join("missing.json")), io::ErrorKind::NotFound);
+    let manifest = dir.join("links.json");
+    fs::write(&manifest, "{ \"threads\": ").unwrap();
+    assert_eq!(import(manifest.clone()), io::ErrorKind::InvalidData);
+
+    let dumps = Wiki::random(200, 23).write(&dir).unwrap();
+    parse(&dumps, Namespaces::main()).export(manifest.clone()).unwrap();
+    fs::write(dir.join("links_entry1.json"), "{ \"not\": \"an entry\" }\n").unwrap();
+    assert_eq!(import(manifest), io::ErrorKind::InvalidData);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn migrating_a_snapshot_with_escaped_titles() {
+    // before format 1, titles kept mysql's escapes; a wiki whose titles have them in
+    //  literally exports the same snapshot, so dropping its version makes it format 0
+    let mut wiki = Wiki::new();
+    let ender = wiki.page(0, "Ender\\'s_Game");
+    let speaker = wiki.page(0, "Speaker_for_the_Dead");
+    wiki.link_to(ender, speaker);
+    wiki.categorize(ender, "Orson_Scott_Card\\'s_novels");
+    wiki.langlink(ender, "fr", "La_Stratégie_Ender");
+    wiki.langlink(speaker, "fr", "La_Voix_des_morts");
+    wiki.langlink(speaker, "it", "Il_riscatto_dell\\'ombra");
+
+    let dir = scratch("migrate");
+    let dumps = wiki.write(&dir).unwrap();
+    let manifest = dir.join("links.json");
+    parse(&dumps, Namespaces::main()).export(manifest.clone()).unwrap();
+    let current = fs::read_to_string(&manifest).unwrap();
+    assert!(current.contains("\"version\":1,"));
+    fs::write(&manifest, current.replace("\"version\":1,", "")).unwrap();
+
+    let ls_dt = LinkState::<LinkData>::import(manifest, new_logger()).unwrap();
+    assert_eq!(ls_dt.validate(), Vec::<String>::new());
+    let hl: LinkState<HashLinks> = ls_dt.into();
+    assert_eq!(hl.resolve_title("Ender's Game"), Some(ender));
+    assert_eq!(hl.get_links()[&ender].title, "Ender's_Game");
+    assert_eq!(hl.get_links()[&ender].get_children(), &[speaker]);
+    assert_eq!(hl.categories().unwrap().members("Orson_Scott_Card's_novels"), &[ender]);
+    let langlinks = hl.langlinks().unwrap();
+    assert_eq!(langlinks.title_in(speaker, "it"), Some("Il_riscatto_dell'ombra"));
+    assert_eq!(langlinks.title_in(ender, "fr"), Some("La_Stratégie_Ender"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compressed_dumps_are_the_same() {
+    let wiki = Wiki::random(1000, 3);
+    let dir = scratch("compressed");
+    let dumps = wiki.write(&dir).unwrap();
+    let plain = parse(&dumps, Namespaces::main());
+    for extension in &["gz", "bz2"] {
+        let compressed = parse(&synthetic::compressed(&dumps, extension), Namespaces::main());
+        assert!(plain.diff(&compressed).is_empty(), "{}", extension);
+        assert_eq!(plain.differences(&compressed), Vec::<String>::new(), "{}", extension);
+        assert_eq!(plain.report(), compressed.report(), "{}", extension);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn parallel_and_serial_are_the_same() {
+    let wiki = Wiki::random(1500, 9);
+    let dir = scratch("parallel");
+    let mut dumps = wiki.write(&dir).unwrap();
+    dumps.threads = 1;
+    let serial = parse(&dumps, Namespaces::main());
+    // plain dumps are split between threads, compressed ones are always read serially
+    for &threads in &[2, 4, 7] {
+        dumps.threads = threads;
+        for dumps in vec![dumps.clone(), synthetic::compressed(&dumps, "gz")] {
+            let ls_dt = parse(&dumps, Namespaces::main());
+            let name = format!("{} on {} threads", dumps.pagelinks.display(), threads);
+            assert!(serial.diff(&ls_dt).is_empty(), "{}", name);
+            assert_eq!(serial.differences(&ls_dt), Vec::<String>::new(), "{}", name);
+            assert_eq!(serial.report(), ls_dt.report(), "{}", name);
+        }
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn linktarget_layout_is_the_same() {
+    let wiki = Wiki::random(1500, 5);
+    let dir = scratch("linktarget");
+    let legacy = parse(&wiki.write(&dir.join("legacy")).unwrap(), Namespaces::main());
+    let mut dumps = wiki.write_linktargets(&dir.join("linktarget")).unwrap();
+    let ls_dt = parse(&dumps, Namespaces::main());
+    assert!(legacy.diff(&ls_dt).is_empty());
+    assert_eq!(legacy.differences(&ls_dt), Vec::<String>::new());
+    let hl: LinkState<HashLinks> = ls_dt.into();
+    check(&hl, &wiki.expected(&Namespaces::main()));
+
+    // without linktarget.sql, none of the links could be resolved
+    dumps.linktarget = None;
+    match LinkState::<LinkDb>::new(&dumps, Namespaces::main()) {
+        Err(e) => match e.kind {
+            ErrorKind::MissingLinkTargets { ref table } => assert_eq!(table, "pagelinks"),
+            ref kind => panic!("wrong error: {}", kind),
+        },
+        Ok(_) => panic!("parsed pagelinks.sql by target without linktarget.sql"),
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn linktarget_layout_without_redirects() {
+    // redirect.sql has no rows, so link targets come straight after the pages
+    let mut wiki = Wiki::new();
+    let alpha = wiki.page(0, "Alpha");
+    let beta = wiki.page(0, "Beta");
+    wiki.link_to(alpha, beta);
+    wiki.link_to(beta, alpha);
+    wiki.link(beta, 0, "Missing");
+    let dir = scratch("linktarget_without_redirects");
+    let dumps = wiki.write_linktargets(&dir).unwrap();
+    let hl: LinkState<HashLinks> = parse(&dumps, Namespaces::main()).into();
+    check(&hl, &wiki.expected(&Namespaces::main()));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn wanted_pages_are_the_same_in_both_layouts() {
+    let wiki = Wiki::random(1500, 13);
+    let dir = scratch("wanted");
+    let wanted = |mut dumps: Dumps| {
+        dumps.wanted = true;
+        parse(&dumps, Namespaces::main()).extras().wanted.clone().unwrap()
+    };
+    let legacy = wanted(wiki.write(&dir.join("legacy")).unwrap());
+    let targets = wanted(wiki.write_linktargets(&dir.join("linktarget")).unwrap());
+    assert!(legacy.is_empty() == false);
+    assert_eq!(legacy.iter().collect::<Vec<_>>(), targets.iter().collect::<Vec<_>>());
+    // titles that only led to a broken redirect (or a loop) count in both
+    let dropped = &wiki.expected(&Namespaces::main()).dropped_titles;
+    assert!(dropped.iter().any(|t| targets.get(t).is_some()));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// `wiki` without what its XML export can't show (see `Wiki::write_xml`)
+fn exportable(mut wiki: Wiki) -> Wiki {
+    let untargeted: Vec<u32> = wiki.pages().iter()
+        .filter(|p| p.redirect == Some(None))
+        .map(|p| p.id)
+        .collect();
+    for id in untargeted {
+        wiki.delete(id);
+    }
+    let from_redirects: Vec<(u32, i32, String)> = wiki.links().iter()
+        .filter(|l| wiki.get(l.0).is_some_and(|p| p.redirect.is_some()))
+        .cloned()
+        .collect();
+    for (from, ns, title) in from_redirects {
+        wiki.unlink(from, ns, &title);
+    }
+    wiki
+}
+
+#[test]
+fn xml_export_is_the_same() {
+    let wiki = exportable(Wiki::random(1500, 17));
+    let dir = scratch("xml");
+    let dumps = wiki.write(&dir).unwrap();
+    let xml = wiki.write_xml(&dir).unwrap();
+    let bz2 = xml.with_extension("xml.bz2");
+    for namespaces in [Namespaces::main(),
+                       Namespaces::with(&[namespace::CATEGORY, namespace::PORTAL])] {
+        let sql = parse(&dumps, namespaces.clone());
+        for path in [&xml, &bz2] {
+            let from_xml: LinkState<LinkData> = 
+                LinkState::<LinkDb>::from_xml(path, namespaces.clone()).unwrap().into();
+            assert!(sql.diff(&from_xml).is_empty(), "{}", path.display());
+            assert_eq!(sql.differences(&from_xml), Vec::<String>::new(), "{}", path.display());
+            let hl: LinkState<HashLinks> = from_xml.into();
+            check(&hl, &wiki.expected(&namespaces));
+        }
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// an XML export of main namespace `pages`: (id, title, wikitext)
+fn write_export(path: &PathBuf, pages: &[(u32, &str, &str)]) {
+    let mut xml = String::from("<mediawiki>\n");
+    for &(id, title, text) in pages {
+        let text = text.replace('<', "&lt;").replace('>', "&gt;");
+        xml.push_str(&format!("<page><title>{}</title><ns>0</ns><id>{}</id><revision><id>1</id>\
+                               <text xml:space=\"preserve\">{}</text></revision></page>\n",
+                              title, id, text));
+    }
+    xml.push_str("</mediawiki>\n");
+    fs::write(path, xml).unwrap();
+}
+
+#[test]
+fn first_links() {
+    let dir = scratch("first_links");
+    let xml = dir.join("pages-articles.xml");
+    write_export(&xml, &[
+        // a cycle, from whichever of its pages it's entered
+        (1, "Alpha", "'''Alpha''' ([[Gamma]]) is a [[Beta]]."),
+        (2, "Beta", "{{Infobox|[[Alpha]]}} [[Beta]] is a [[Gamma]]."),
+        (3, "Gamma", "[[Alpha]] and [[Delta]]."),
+        // links, but none in the body
+        (4, "Delta", "{{Hatnote|[[Alpha]]}} Delta.<ref>[[Beta]]</ref>"),
+        (5, "Epsilon", "[[Delta]]"),
+        (6, "Zeta", "[[Epsilon]], then [[Alpha]]"),
+        // only a link to itself
+        (7, "Eta", "[[Eta]]"),
+        (8, "Theta", "[[Beta]]"),
+        (9, "Iota", "[[Theta|The (theta)]]"),
+        (10, "Kappa", "[[Lambda]]"),
+        (11, "Lambda", "[[Kappa]]"),
+        (12, "Mu", "[[Lambda]]"),
+        // no links at all
+        (13, "Nu", "Nu."),
+    ]);
+    let hl: LinkState<HashLinks> = LinkState::<LinkDb>::from_xml(&xml, Namespaces::main())
+        .map(LinkState::<LinkData>::from).unwrap().into();
+    let fl = hl.first_links().expect("no link order in an XML export");
+
+    // a link to the page itself is skipped, even if it's first
+    assert_eq!(fl.first_link(2), Some(3));
+    assert_eq!(fl.first_link(7), None);
+    assert_eq!(fl.first_link(4), None);
+    assert_eq!(fl.follow(9), vec![9, 8, 2, 3, 1, 2]);
+    assert_eq!(fl.follow(6), vec![6, 5, 4]);
+
+    let cycles = fl.cycles();
+    assert_eq!(cycles.len(), 2);
+    assert_eq!((&cycles[0].members[..], cycles[0].basin, cycles[0].depth), (&[1, 2, 3][..], 2, 2));
+    assert_eq!((&cycles[1].members[..], cycles[1].basin, cycles[1].depth), (&[10, 11][..], 1, 1));
+    let reach = |id| fl.reach(id).cloned().unwrap();
+    assert_eq!(reach(3), Reach { destination: Destination::Cycle(0), steps: 0 });
+    assert_eq!(reach(9), Reach { destination: Destination::Cycle(0), steps: 2 });
+    assert_eq!(reach(12), Reach { destination: Destination::Cycle(1), steps: 1 });
+    for &(id, steps) in &[(4, 0), (5, 1), (6, 2)] {
+        assert_eq!(reach(id), Reach { destination: Destination::DeadEnd(4), steps });
+    }
+    assert_eq!(reach(7), Reach { destination: Destination::DeadEnd(7), steps: 0 });
+    assert_eq!(reach(13), Reach { destination: Destination::DeadEnd(13), steps: 0 });
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn first_links_reach_every_page() {
+    let wiki = exportable(Wiki::random(1500, 19));
+    let dir = scratch("first_links_random");
+    let xml = wiki.write_xml(&dir).unwrap();
+    let hl: LinkState<HashLinks> = LinkState::<LinkDb>::from_xml(&xml, Namespaces::main())
+        .map(LinkState::<LinkData>::from).unwrap().into();
+    let fl = hl.first_links().unwrap();
+    let pages = hl.get_links().len();
+    assert!(fl.cycles().is_empty() == false);
+
+    // every page is in a cycle, in a cycle's basin, or ends up at a dead end
+    let in_cycles: usize = fl.cycles().iter().map(|c| c.members.len() + c.basin).sum();
+    let dead_ends = hl.get_links().keys()
+        .filter(|&&id| matches!(fl.reach(id).unwrap().destination, Destination::DeadEnd(_)))
+        .count();
+    assert_eq!(in_cycles + dead_ends, pages);
+    // and gets there one first link at a time
+    for &id in hl.get_links().keys() {
+        let r = fl.reach(id).unwrap();
+        match fl.first_link(id) {
+            Some(next) if r.steps > 0 => {
+                let n = fl.reach(next).unwrap();
+                assert_eq!((n.destination, n.steps + 1), (r.destination, r.steps), "{}", id);
+            },
+            Some(_) => assert!(matches!(r.destination, Destination::Cycle(_)), "{}", id),
+            None => assert_eq!(r.destination, Destination::DeadEnd(id)),
+        }
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn update_is_the_same_as_a_full_parse() {
+    let mut wiki = Wiki::random(1500, 11);
+    let dir = scratch("update");
+    let manifest = dir.join("old").join("links.json");
+    parse(&wiki.write(&dir.join("old")).unwrap(), Namespaces::main())
+        .export(manifest.clone()).unwrap();
+    let old = LinkState::<LinkData>::import(manifest, new_logger()).unwrap();
+
+    wiki.edit(12);
+    let dumps = wiki.write(&dir.join("new")).unwrap();
+    let hl: LinkState<HashLinks> = parse(&dumps, Namespaces::main()).into();
+    check(&hl, &wiki.expected(&Namespaces::main()));
+    let full = parse(&dumps, Namespaces::main());
+    let diff = old.diff(&full);
+    let (updated, summary) = old.update(&dumps, Namespaces::main()).unwrap();
+    assert_eq!(updated.differences(&full), Vec::<String>::new());
+    assert!(updated.diff(&full).is_empty());
+
+    assert!(diff.added.is_empty() == false && diff.deleted.is_empty() == false);
+    assert!(diff.renamed.is_empty() == false);
+    assert_eq!((summary.added, summary.removed, summary.renamed),
+               (diff.added.len(), diff.deleted.len(), diff.renamed.len()));
+    assert_eq!((summary.links_added, summary.links_removed),
+               (diff.links_added(), diff.links_removed()));
+    // only new and renamed pages, and pages that gained or lost a child or parent, are
+    //  rebuilt
+    let touched: BTreeSet<u32> = diff.links.iter()
+        .flat_map(|l| l.added.iter().chain(&l.removed).chain(Some(&l.id)))
+        .chain(diff.added.iter().map(|p| &p.id))
+        .chain(diff.renamed.iter().map(|r| &r.id))
+        .filter(|id| hl.get_links().contains_key(id))
+        .cloned().collect();
+    assert_eq!(summary.rebuilt, touched.len());
+    assert_eq!(summary.reused, hl.get_links().len() - touched.len());
+    assert!(summary.reused > summary.rebuilt);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,167 @@
+// The streaming tokenizer (see `parse::tokenizer`), fed through readers small enough that
+//  tuples (and whole lines) get split across buffer refills
+
+extern crate links;
+
+use links::link_state::link_db::parse::{ErrorKind, Position};
+use links::link_state::link_db::parse::tokenizer::{Field, Tokenizer, unescape};
+
+use std::io::BufReader;
+
+// a field that outlives the tokenizer's buffer
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Int(i64),
+    Float(f64),
+    Str(Vec<u8>),
+}
+
+impl<'a> From<Field<'a>> for Value {
+    fn from(f: Field<'a>) -> Value {
+        match f {
+            Field::Null => Value::Null,
+            Field::Int(i) => Value::Int(i),
+            Field::Float(f) => Value::Float(f),
+            Field::Str(s) => Value::Str(s.to_vec()),
+        }
+    }
+}
+
+fn s(s: &str) -> Value {
+    Value::Str(s.as_bytes().to_vec())
+}
+
+// every tuple in `dump`, read `capacity` bytes at a time
+fn tuples(dump: &[u8], capacity: usize) -> Vec<Vec<Value>> {
+    let mut tokens = Tokenizer::new(BufReader::with_capacity(capacity, dump),
+                                    Position::default());
+    let mut tuples = vec![];
+    while let Some(tuple) = tokens.next_tuple().unwrap() {
+        tuples.push((0..tuple.len()).map(|i| Value::from(tuple.get(i).unwrap())).collect());
+    }
+    tuples
+}
+
+const DUMP: &str = "-- MySQL dump 10.13\n\
+    CREATE TABLE `page` (\n  `page_id` int(8)\n) ENGINE=InnoDB;\n\
+    INSERT INTO `page` VALUES (1,0,'Alpha',NULL),(2,14,'It\\'s,_(a)_title',-3.5);\n\
+    /*!40000 ALTER TABLE `page` ENABLE KEYS */;\n\
+    INSERT INTO `page` VALUES (3,0,'',1e3);\n";
+
+#[test]
+fn tuples_across_refills() {
+    let expected = vec![
+        vec![Value::Int(1), Value::Int(0), s("Alpha"), Value::Null],
+        vec![Value::Int(2), Value::Int(14), s("It\\'s,_(a)_title"), Value::Float(-3.5)],
+        vec![Value::Int(3), Value::Int(0), s(""), Value::Float(1000.0)],
+    ];
+    // from every tuple in one read down to a byte at a time
+    for &capacity in &[1 << 16, 64, 7, 3, 1] {
+        assert_eq!(tuples(DUMP.as_bytes(), capacity), expected, "capacity {}", capacity);
+    }
+}
+
+#[test]
+fn lines_longer_than_the_buffer() {
+    let title = "x".repeat(5000);
+    let rows: Vec<String> = (0..100).map(|i| format!("({},'{}_{}')", i, title, i)).collect();
+    let dump = format!("-- {}\nINSERT INTO `t` VALUES {};\n", title, rows.join(","));
+    let found = tuples(dump.as_bytes(), 256);
+    assert_eq!(found.len(), 100);
+    for (i, tuple) in found.into_iter().enumerate() {
+        assert_eq!(tuple, vec![Value::Int(i as i64), s(&format!("{}_{}", title, i))]);
+    }
+}
+
+#[test]
+fn table_and_position() {
+    let dump = b"-- header\nINSERT INTO `pagelinks` VALUES (1,2),(3,4);\n";
+    let start = Position { line: 1, offset: 10 };
+    let mut tokens = Tokenizer::new(BufReader::with_capacity(4, &dump[10..]), start);
+    let first = tokens.next_tuple().unwrap().unwrap().position();
+    assert_eq!(tokens.table(), b"pagelinks");
+    assert_eq!(first, Position { line: 2, offset: 10 + 31 });
+    let second = tokens.next_tuple().unwrap().unwrap().position();
+    assert_eq!(second, Position { line: 2, offset: 10 + 37 });
+    assert!(tokens.next_tuple().unwrap().is_none());
+}
+
+#[test]
+fn escapes() {
+    let cases: &[(&[u8], &[u8])] = &[
+        (b"plain", b"plain"),
+        (b"\\0", b"\0"),
+        (b"\\b", b"\x08"),
+        (b"\\n", b"\n"),
+        (b"\\r", b"\r"),
+        (b"\\t", b"\t"),
+        (b"\\Z", b"\x1a"),
+        // only special in `LIKE` patterns, so they keep their backslash
+        (b"\\%", b"\\%"),
+        (b"\\_", b"\\_"),
+        // anything else is just the character itself
+        (b"\\\\", b"\\"),
+        (b"\\'", b"'"),
+        (b"\\\"", b"\""),
+        (b"\\q", b"q"),
+        (b"Ender\\'s_Game", b"Ender's_Game"),
+        // a trailing backslash can't be escaping anything
+        (b"trailing\\", b"trailing\\"),
+    ];
+    for &(raw, decoded) in cases {
+        assert_eq!(&*unescape(raw), decoded, "{}", String::from_utf8_lossy(raw));
+    }
+    // fields are only decoded when they're read as a `str`
+    let dump = b"INSERT INTO `t` VALUES ('It\\'s\\n\\\\');\n";
+    let mut tokens = Tokenizer::new(BufReader::with_capacity(5, &dump[..]), Position::default());
+    let tuple = tokens.next_tuple().unwrap().unwrap();
+    assert_eq!(tuple.get(0).unwrap().as_bytes(), Some(&b"It\\'s\\n\\\\"[..]));
+    assert_eq!(tuple.get(0).unwrap().as_str().unwrap(), "It's\n\\");
+}
+
+#[test]
+fn null_and_numbers() {
+    let dump = b"INSERT INTO `t` VALUES (NULL,-7,0.5,4294967296,.25,'NULL');\n";
+    let found = tuples(dump, 2);
+    assert_eq!(found, vec![vec![Value::Null, Value::Int(-7), Value::Float(0.5),
+                                Value::Int(1 << 32), Value::Float(0.25), s("NULL")]]);
+    let mut tokens = Tokenizer::new(&dump[..], Position::default());
+    let tuple = tokens.next_tuple().unwrap().unwrap();
+    assert_eq!(tuple.get(1).unwrap().as_u32(), None);
+    assert_eq!(tuple.get(3).unwrap().as_u32(), None);
+    assert_eq!(tuple.get(0).unwrap().as_int(), None);
+}
+
+// the error from the first malformed tuple in `dump`, and where it was
+fn error(dump: &[u8]) -> (Position, String) {
+    let mut tokens = Tokenizer::new(BufReader::with_capacity(3, dump), Position::default());
+    loop {
+        match tokens.next_tuple() {
+            Ok(Some(_)) => continue,
+            Ok(None) => panic!("no error in `{}`", String::from_utf8_lossy(dump)),
+            Err(e) => match e.kind {
+                ErrorKind::Expected(expected) => return (e.position, String::from(expected)),
+                kind => panic!("unexpected error: {}", kind),
+            },
+        }
+    }
+}
+
+#[test]
+fn malformed() {
+    // the position is of the byte that was wrong, on a 1-based line
+    let dump = b"-- header\nINSERT INTO `t` VALUES (1,'a'),(2,x);\n";
+    assert_eq!(error(dump), (Position { line: 2, offset: 44 }, String::from("a value")));
+    let dump = b"INSERT INTO `t` VALUES (1 2);\n";
+    assert_eq!(error(dump), (Position { line: 1, offset: 25 }, String::from("`,` or `)`")));
+    let dump = b"INSERT INTO `t` VALUES (1),2;\n";
+    assert_eq!(error(dump), (Position { line: 1, offset: 27 }, String::from("`(`")));
+    let dump = b"INSERT INTO `t` (1);\n";
+    assert_eq!(error(dump), (Position { line: 1, offset: 0 }, String::from("`VALUES`")));
+    let dump = b"INSERT INTO `t` VALUES (1,'unterminated";
+    assert_eq!(error(dump), (Position { line: 1, offset: 39 },
+                             String::from("the rest of the statement")));
+    let dump = b"INSERT INTO `t` VALUES (1-2-);\n";
+    assert_eq!(error(dump), (Position { line: 1, offset: 24 }, String::from("a number")));
+}
@@ -0,0 +1,110 @@
+// Finding links in wikitext (see `xml::wikitext`), and which of them are in the body of
+//  the article: one test for each thing that takes a link out of it
+
+extern crate links;
+
+use links::link_state::link_db::xml::wikitext::{self, WikiLink};
+
+// each link's target, and whether it's in the body
+fn found(text: &str) -> Vec<(&str, bool)> {
+    wikitext::links(text).into_iter().map(|l| (l.target, l.body)).collect()
+}
+
+#[test]
+fn links_in_order() {
+    let text = "A [[Physics]] and [[ chemistry |Chemistry]] link to [[Biology#Cells]].";
+    assert_eq!(wikitext::links(text), vec![
+        WikiLink { target: "Physics", fragment: None, offset: 2, colon: false, body: true },
+        WikiLink { target: " chemistry ", fragment: None, offset: 18, colon: false, body: true },
+        WikiLink { target: "Biology", fragment: Some("Cells"), offset: 52, colon: false,
+                   body: true },
+    ]);
+    // links within the page, brackets that aren't links, and ones that never close
+    assert_eq!(found("[[#History]] [[[x]]] [[a{b]] [[:Category:Physics]] [[Open"),
+               vec![("x", true), (":Category:Physics", true)]);
+    assert!(wikitext::links("[[:Category:Physics]]")[0].colon);
+}
+
+#[test]
+fn templates() {
+    assert_eq!(found("{{Infobox|capital=[[Paris]]}} [[France]]"),
+               vec![("Paris", false), ("France", true)]);
+    // nested, and closed one at a time
+    assert_eq!(found("{{a|{{b|[[X]]}}[[Y]]}}[[Z]]"),
+               vec![("X", false), ("Y", false), ("Z", true)]);
+    // a stray `}}` doesn't leave the next template open
+    assert_eq!(found("}} [[A]] {{b}} [[C]]"), vec![("A", true), ("C", true)]);
+}
+
+#[test]
+fn tables_open_and_close_at_the_start_of_a_line() {
+    assert_eq!(found("{| class=\"wikitable\"\n| [[Cell]]\n|}\n[[After]]"),
+               vec![("Cell", false), ("After", true)]);
+    // anywhere else, `{|` and `|}` are just text (or the end of a template's argument)
+    assert_eq!(found("a {| b [[A]]"), vec![("A", true)]);
+    assert_eq!(found("{|\n[[In]] |} [[Still]]\n|}[[Out]]"),
+               vec![("In", false), ("Still", false), ("Out", true)]);
+    assert_eq!(found("{{t|x=|}}\n[[A]]"), vec![("A", true)]);
+}
+
+#[test]
+fn refs() {
+    assert_eq!(found("Text.<ref>See [[Source]].</ref> [[More]]"),
+               vec![("Source", false), ("More", true)]);
+    assert_eq!(found("<ref name=\"a\">[[Source]]</REF>[[More]]"),
+               vec![("Source", false), ("More", true)]);
+    // reusing a reference doesn't open one
+    assert_eq!(found("Text.<ref name=\"a\" />[[More]] <ref name=b/> [[Again]]"),
+               vec![("More", true), ("Again", true)]);
+    // nor does the list of them
+    assert_eq!(found("<references />[[A]] <refs>[[B]]"), vec![("A", true), ("B", true)]);
+}
+
+#[test]
+fn captions() {
+    let text = "[[File:Map.png|thumb|A [[map]] of [[Europe|the (continent)]]]] [[After]]";
+    assert_eq!(found(text), vec![("File:Map.png", true), ("map", false), ("Europe", false),
+                                 ("After", true)]);
+    // the parentheses in a caption don't count
+    assert_eq!(found("[[A|(b]] [[C]]"), vec![("A", true), ("C", true)]);
+}
+
+#[test]
+fn parentheses_end_with_the_paragraph() {
+    assert_eq!(found("Physics ([[Greek]]) is [[Science]]."),
+               vec![("Greek", false), ("Science", true)]);
+    assert_eq!(found("((nested) [[A]]) [[B]]"), vec![("A", false), ("B", true)]);
+    // an unclosed one lasts until the next blank line, and no further
+    assert_eq!(found("Oops (see [[A]]\nstill [[B]]\n\n[[C]]"),
+               vec![("A", false), ("B", false), ("C", true)]);
+    // and one in a template doesn't count
+    assert_eq!(found("{{lang|(}} [[A]]"), vec![("A", true)]);
+    assert_eq!(found(") [[A]]"), vec![("A", true)]);
+}
+
+#[test]
+fn skipped_text() {
+    assert_eq!(found("<!-- [[Hidden]] --> [[Shown]]"), vec![("Shown", true)]);
+    assert_eq!(found("<nowiki>[[Literal]]</nowiki><PRE class=\"x\">[[Too]]</pre>[[Link]]"),
+               vec![("Link", true)]);
+    // an unclosed comment (or `<nowiki>`) hides the rest of the page
+    assert_eq!(found("[[Before]] <!-- [[A]]\n\n[[B]]"), vec![("Before", true)]);
+    assert_eq!(found("[[Before]] <nowiki> [[A]]"), vec![("Before", true)]);
+    // and other tags are left alone
+    assert_eq!(found("<b>[[Bold]]</b> < [[Lt]]"), vec![("Bold", true), ("Lt", true)]);
+}
+
+#[test]
+fn redirects() {
+    assert_eq!(wikitext::redirect("#REDIRECT [[Physics]]"), Some(("Physics", None)));
+    assert_eq!(wikitext::redirect("  #redirect:[[Physics#History|x]] {{R from move}}"),
+               Some(("Physics", Some("History"))));
+    assert_eq!(wikitext::redirect("#Redirect [[ Category:Physics ]]"),
+               Some((" Category:Physics ", None)));
+    // the link has to come first
+    assert_eq!(wikitext::redirect("#REDIRECT to [[Physics]]"), None);
+    assert_eq!(wikitext::redirect("#REDIRECT [[#Section]]"), None);
+    assert_eq!(wikitext::redirect("See #REDIRECT [[Physics]]"), None);
+    assert_eq!(wikitext::redirect("[[Physics]]"), None);
+    assert_eq!(wikitext::redirect(""), None);
+}